@@ -52,6 +52,5 @@ fn main() {
     }
     if f.unwrap().write_all(hex_bytes.unwrap().as_slice()).is_err() {
         println!("Failed to write the binary file ({})", bin_filename);
-        return;
     }
 }
@@ -28,7 +28,7 @@ impl Bytes {
     }
 
     pub fn to_u32(bytes: &[u8]) -> u32 {
-        let mut val = 0 as u32;
+        let mut val: u32 = 0;
         let bytes_size = bytes.len();
         for n in 0..bytes_size {
             let idx = (bytes_size - 1) - n;
@@ -53,8 +53,8 @@ impl Bytes {
         Bytes::from_hexstr(std::str::from_utf8(bytes).unwrap())
     }
 
-    pub fn from_hexstring(hex_str: &String) -> Result<Vec<u8>, Error> {
-        Bytes::from_hexstr(hex_str.as_str())
+    pub fn from_hexstring(hex_str: &str) -> Result<Vec<u8>, Error> {
+        Bytes::from_hexstr(hex_str)
     }
 
     pub fn to_hexstring(bytes: &[u8]) -> String {
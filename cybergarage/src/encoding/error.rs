@@ -12,12 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 
 pub struct ParseError {}
 
 impl ParseError {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(msg: &str) -> Error {
-        Error::new(ErrorKind::Other, msg)
+        Error::other(msg)
     }
 }
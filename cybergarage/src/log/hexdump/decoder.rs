@@ -23,15 +23,16 @@ impl Decoder {
         Decoder::from_str(std::str::from_utf8(bytes).unwrap())
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Vec<u8>, Error> {
-        if s.len() == 0 {
+        if s.is_empty() {
             return Ok(vec![]);
         }
         let split_hexes: Vec<&str> = s.split(" ").collect();
         let line_hexes = &split_hexes[1..(HEXDUMP_TWO_COLUMN_BYTES + 3)];
         let mut bytes = vec![];
         for s in line_hexes {
-            if s.len() == 0 {
+            if s.is_empty() {
                 continue;
             }
             let hex_byte = hex::decode(s);
@@ -43,7 +44,7 @@ impl Decoder {
         Ok(bytes.concat())
     }
 
-    pub fn from_lines(lines: &Vec<&str>) -> Result<Vec<u8>, Error> {
+    pub fn from_lines(lines: &[&str]) -> Result<Vec<u8>, Error> {
         let mut bytes = vec![];
         for line in lines {
             let line_bytes = Decoder::from_str(line);
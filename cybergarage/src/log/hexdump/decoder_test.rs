@@ -40,8 +40,8 @@ mod tests {
             if test.expected_bytes.len() != hexdump_bytes.len() {
                 return;
             }
-            for n in 0..test.expected_bytes.len() {
-                assert_eq!(test.expected_bytes[n], hexdump_bytes[n]);
+            for (n, expected_byte) in test.expected_bytes.iter().enumerate() {
+                assert_eq!(*expected_byte, hexdump_bytes[n]);
             }
         }
     }
@@ -28,6 +28,12 @@ static LOGGER: Logger = Logger {
     level: LevelFilter::Trace,
 };
 
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Logger {
     pub fn new() -> Logger {
         Logger {
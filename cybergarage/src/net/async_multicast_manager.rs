@@ -0,0 +1,381 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use log::*;
+use std::net::IpAddr;
+
+use crate::net::async_multicast_server::AsyncMulticastServer;
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::interface::*;
+use crate::net::interface_observer::InterfaceEvent;
+use crate::net::multicast_options::MulticastOptions;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::packet_stream::{packet_stream_new, PacketStream};
+use crate::net::send_report::SendReport;
+
+/// AsyncMulticastManager is an async variant of MulticastManager which binds an AsyncMulticastServer to each interface.
+pub struct AsyncMulticastManager {
+    mcast_servers: Vec<AsyncMulticastServer>,
+    observers: NotifytManager,
+    filter: InterfaceFilter,
+    options: MulticastOptions,
+    maddrs: Vec<IpAddr>,
+    ifaddrs: Vec<IpAddr>,
+    port: Option<u16>,
+}
+
+impl Default for AsyncMulticastManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncMulticastManager {
+    pub fn new() -> AsyncMulticastManager {
+        AsyncMulticastManager {
+            mcast_servers: Vec::new(),
+            observers: NotifytManager::new(),
+            filter: InterfaceFilter::new(),
+            options: MulticastOptions::new(),
+            maddrs: Vec::new(),
+            ifaddrs: Vec::new(),
+            port: None,
+        }
+    }
+
+    /// set_interface_filter sets the filter which selects the interfaces to bind in start() and rebind().
+    pub fn set_interface_filter(&mut self, filter: InterfaceFilter) {
+        self.filter = filter;
+    }
+
+    pub fn interface_filter(&self) -> &InterfaceFilter {
+        &self.filter
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers by the servers which are bound after this call.
+    /// The servers share the worker threads of DispatchMode::Pool, which are started once for the manager.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.observers.set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> &DispatchOptions {
+        self.observers.dispatch_options()
+    }
+
+    /// set_multicast_options sets the send options of the servers which are bound after this call.
+    pub fn set_multicast_options(&mut self, options: MulticastOptions) {
+        self.options = options;
+    }
+
+    pub fn multicast_options(&self) -> &MulticastOptions {
+        &self.options
+    }
+
+    /// remove_observer removes the observer from all servers, and returns false if it has not been added.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        for mcast_server in self.mcast_servers.iter_mut() {
+            mcast_server.remove_observer(id);
+        }
+        self.observers.remove_observer(id)
+    }
+
+    /// packets returns a new Stream of the Packets received by all running servers.
+    pub fn packets(&mut self) -> PacketStream {
+        let (stream, observer) = packet_stream_new();
+        self.add_observer(observer);
+        stream
+    }
+
+    /// notify sends the packet to the groups from all servers. It tries all servers even if some of them could not send it, and returns the first error.
+    pub async fn notify(&self, msg: &Packet) -> Result<()> {
+        for (_, result) in self.notify_all(msg).await.into_results() {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// notify_all sends the packet to the groups from all servers, and returns the outcome for each interface.
+    pub async fn notify_all(&self, msg: &Packet) -> SendReport {
+        let mut report = SendReport::new();
+        for mcast_server in self.mcast_servers.iter() {
+            if let Ok(ifaddr) = mcast_server.ifaddr() {
+                report.add(ifaddr, mcast_server.notify(msg).await);
+            }
+        }
+        report
+    }
+
+    /// notify_with_filter sends the packet to the groups only from the servers whose interfaces are selected by the filter,
+    /// and returns the outcome for each interface.
+    pub async fn notify_with_filter(&self, msg: &Packet, filter: &InterfaceFilter) -> SendReport {
+        let ifaces = get_interfaces();
+        let mut report = SendReport::new();
+        for mcast_server in self.mcast_servers.iter() {
+            if let Ok(ifaddr) = mcast_server.ifaddr() {
+                if !filter.is_interface_address_match(&ifaces, &ifaddr.ip()) {
+                    continue;
+                }
+                report.add(ifaddr, mcast_server.notify(msg).await);
+            }
+        }
+        report
+    }
+
+    /// groups returns the multicast groups which the running servers join.
+    pub fn groups(&self) -> &[IpAddr] {
+        &self.maddrs
+    }
+
+    /// join_group joins the group on all interfaces of the same address family.
+    /// It tries all interfaces even if some of them could not join the group, and returns the first error.
+    pub fn join_group(&mut self, maddr: IpAddr) -> Result<()> {
+        if self.maddrs.contains(&maddr) {
+            return Ok(());
+        }
+        self.maddrs.push(maddr);
+
+        let mut ret = Ok(());
+        for mcast_server in self.mcast_servers.iter_mut() {
+            if !matches!(mcast_server.ifaddr(), Ok(ifaddr) if ifaddr.is_ipv4() == maddr.is_ipv4()) {
+                continue;
+            }
+            if let Err(e) = mcast_server.join_group(maddr) {
+                warn!("JOIN {} ({})", maddr, e);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+        }
+
+        // The interfaces which had no group of the same address family have no server yet.
+        let port = match self.port {
+            Some(port) => port,
+            None => return ret,
+        };
+        for ifaddr in self.ifaddrs.clone() {
+            if ifaddr.is_ipv4() != maddr.is_ipv4() || self.has_interface(ifaddr) {
+                continue;
+            }
+            match self.start_server(ifaddr, port) {
+                Ok(Some(mcast_server)) => self.mcast_servers.push(mcast_server),
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("BIND {}:{} ({})", ifaddr, port, e);
+                    if ret.is_ok() {
+                        ret = Err(e);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    /// leave_group leaves the group on all interfaces, and stops the servers which have no group left.
+    /// It tries all interfaces even if some of them could not leave the group, and returns the first error.
+    pub async fn leave_group(&mut self, maddr: IpAddr) -> Result<()> {
+        if !self.maddrs.contains(&maddr) {
+            return Ok(());
+        }
+        self.maddrs.retain(|joined| *joined != maddr);
+
+        let mut ret = Ok(());
+        let mut mcast_servers = Vec::new();
+        for mut mcast_server in self.mcast_servers.drain(..) {
+            if let Err(e) = mcast_server.leave_group(maddr) {
+                warn!("LEAVE {} ({})", maddr, e);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+            if mcast_server.groups().is_empty() {
+                info!("UNBIND {:?}", mcast_server.ifaddr());
+                let _ = mcast_server.stop().await;
+                continue;
+            }
+            mcast_servers.push(mcast_server);
+        }
+        self.mcast_servers = mcast_servers;
+        ret
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.port.is_some()
+    }
+
+    pub fn has_interface(&self, addr: IpAddr) -> bool {
+        self.mcast_servers
+            .iter()
+            .any(|mcast_server| matches!(mcast_server.ifaddr(), Ok(ifaddr) if ifaddr.ip() == addr))
+    }
+
+    /// start_server binds the groups which have the same address family as the interface, or returns None if there is no such group.
+    fn start_server(&self, ifaddr: IpAddr, port: u16) -> Result<Option<AsyncMulticastServer>> {
+        let mut mcast_server = AsyncMulticastServer::new();
+        mcast_server.share_dispatch_options(&self.observers);
+        mcast_server.set_multicast_options(self.options);
+        for (id, observer) in self.observers.entries() {
+            mcast_server.insert_observer(*id, observer.clone());
+        }
+        for maddr in self.maddrs.iter() {
+            if maddr.is_ipv4() != ifaddr.is_ipv4() {
+                continue;
+            }
+            if mcast_server.is_bound() {
+                mcast_server.join_group(*maddr)?;
+            } else {
+                mcast_server.bind(*maddr, port, ifaddr)?;
+            }
+            info!("BIND {}:{} to {}", maddr, port, ifaddr);
+        }
+        if !mcast_server.is_bound() {
+            return Ok(None);
+        }
+        mcast_server.start()?;
+        Ok(Some(mcast_server))
+    }
+
+    pub async fn start(&mut self, maddrs: &[IpAddr], port: u16) -> Result<()> {
+        self.start_with_interfaces(maddrs, port, &self.filter.addresses(&get_interfaces()))
+            .await
+    }
+
+    /// start_with_interfaces binds the multicast groups and the groups joined before to the specified interfaces which have the same address family.
+    pub async fn start_with_interfaces(
+        &mut self,
+        maddrs: &[IpAddr],
+        port: u16,
+        ifaddrs: &[IpAddr],
+    ) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+
+        if ifaddrs.is_empty() {
+            return Err(Error::NoInterfaces);
+        }
+
+        // The groups joined by join_group() before start() are kept as well as the source-specific groups.
+        for maddr in maddrs.iter() {
+            if !self.maddrs.contains(maddr) {
+                self.maddrs.push(*maddr);
+            }
+        }
+        self.ifaddrs = ifaddrs.to_vec();
+        self.port = Some(port);
+        for ifaddr in ifaddrs.iter().copied() {
+            match self.start_server(ifaddr, port) {
+                Ok(Some(mcast_server)) => self.mcast_servers.push(mcast_server),
+                Ok(None) => continue,
+                Err(e) => {
+                    let _ = self.stop().await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// rebind joins the groups on the interfaces which are newly selected by the interface filter, and stops the servers of the vanished interfaces.
+    pub async fn rebind(&mut self) -> Result<()> {
+        self.rebind_with_interfaces(&self.filter.addresses(&get_interfaces()))
+            .await
+    }
+
+    /// rebind_with_interfaces updates the running servers to the specified interfaces.
+    /// It tries all interfaces even if some of them could not be bound, and returns the first error.
+    pub async fn rebind_with_interfaces(&mut self, ifaddrs: &[IpAddr]) -> Result<()> {
+        self.rebind_servers(ifaddrs, 0).await
+    }
+
+    /// rebind_with_event rebinds the servers like rebind(), and joins the groups again on the kept server of the interface of the event,
+    /// since the interface may have been reconfigured with the same address. The servers of the other interfaces keep their groups.
+    pub async fn rebind_with_event(&mut self, event: &InterfaceEvent) -> Result<()> {
+        let ifaddrs = self.filter.addresses(&get_interfaces());
+        self.rebind_servers(&ifaddrs, event.interface().index())
+            .await
+    }
+
+    /// rebind_servers updates the running servers to the specified interfaces, and joins the groups again on the kept server of rejoin_index.
+    async fn rebind_servers(&mut self, ifaddrs: &[IpAddr], rejoin_index: u32) -> Result<()> {
+        let port = match self.port {
+            Some(port) => port,
+            None => return Ok(()),
+        };
+        self.ifaddrs = ifaddrs.to_vec();
+
+        let mut ret = Ok(());
+        let mut mcast_servers = Vec::new();
+        for mut mcast_server in self.mcast_servers.drain(..) {
+            match mcast_server.ifaddr() {
+                Ok(addr) if ifaddrs.contains(&addr.ip()) => {
+                    if rejoin_index != 0 && mcast_server.interface_index() == rejoin_index {
+                        if let Err(e) = mcast_server.rejoin_groups() {
+                            warn!("REJOIN {} ({})", addr, e);
+                            if ret.is_ok() {
+                                ret = Err(e);
+                            }
+                        }
+                    }
+                    mcast_servers.push(mcast_server);
+                }
+                _ => {
+                    info!("UNBIND {:?}", mcast_server.ifaddr());
+                    let _ = mcast_server.stop().await;
+                }
+            }
+        }
+        self.mcast_servers = mcast_servers;
+
+        for ifaddr in ifaddrs.iter().copied() {
+            if self.has_interface(ifaddr) {
+                continue;
+            }
+            match self.start_server(ifaddr, port) {
+                Ok(Some(mcast_server)) => self.mcast_servers.push(mcast_server),
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("BIND {}:{} ({})", ifaddr, port, e);
+                    if ret.is_ok() {
+                        ret = Err(e);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        for mcast_server in self.mcast_servers.iter_mut() {
+            mcast_server.stop().await?;
+        }
+        self.mcast_servers.clear();
+        self.ifaddrs.clear();
+        self.port = None;
+        Ok(())
+    }
+}
+
+impl ObserverRegistry for AsyncMulticastManager {
+    /// insert_observer adds the observer to the running servers and the servers which are bound later.
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        let id = self.observers.insert_observer(id, observer.clone());
+        for mcast_server in self.mcast_servers.iter_mut() {
+            mcast_server.insert_observer(id, observer.clone());
+        }
+        id
+    }
+}
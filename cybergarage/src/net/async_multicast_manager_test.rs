@@ -0,0 +1,85 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time;
+
+    use crate::log::Logger;
+    use crate::net::async_multicast_manager::*;
+    use crate::net::default_test::*;
+    use crate::net::notify_manager_test::*;
+    use crate::net::observer::ObserverObject;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+
+    #[tokio::test]
+    async fn async_multicast_manager() {
+        Logger::init();
+
+        let maddrs = vec![TEST_MULTICAST_V4_ADDRESS, TEST_MULTICAST_V6_ADDRESS];
+        let mut mgr = AsyncMulticastManager::new();
+        let ret = mgr.start(&maddrs, TEST_PORT).await;
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        assert!(mgr.join_group(TEST_MULTICAST_V4_ADDRESS2).is_ok());
+        assert!(mgr.groups().contains(&TEST_MULTICAST_V4_ADDRESS2));
+        assert!(mgr.leave_group(TEST_MULTICAST_V4_ADDRESS2).await.is_ok());
+        assert_eq!(mgr.groups(), &maddrs);
+        let ret = mgr.stop().await;
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_multicast_manager_observer_order() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let before_packets = Arc::new(Mutex::new(Vec::new()));
+        let after_packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = AsyncMulticastManager::new();
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(before_packets.clone())));
+        mgr.add_observer(observer);
+        let ret = mgr
+            .start_with_interfaces(&[TEST_MULTICAST_V4_ADDRESS], TEST_PORT, &[ifaddr])
+            .await;
+        assert!(ret.is_ok(), "{:?}", ret);
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(after_packets.clone())));
+        mgr.add_observer(observer);
+
+        // Both observers are attached to the server which is bound again after an interface change.
+        assert!(mgr.rebind_with_interfaces(&[]).await.is_ok());
+        assert!(!mgr.has_interface(ifaddr));
+        assert!(mgr.rebind_with_interfaces(&[ifaddr]).await.is_ok());
+        assert!(mgr.has_interface(ifaddr));
+
+        // The other tests may send to the same group and port, so only the packets of this test are counted.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(b"async_multicast_manager_observer_order".to_vec());
+        assert!(mgr.notify(&pkt).await.is_ok());
+        tokio::time::sleep(time::Duration::from_millis(500)).await;
+        assert!(mgr.stop().await.is_ok());
+
+        for packets in [before_packets, after_packets] {
+            let packets = packets.lock().unwrap();
+            assert!(packets
+                .iter()
+                .any(|recv_pkt| recv_pkt.bytes() == pkt.bytes()));
+        }
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::net::async_udp_socket::*;
+use crate::net::default::MAX_PACKET_SIZE;
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::interface::get_interface_index;
+use crate::net::multicast_options::MulticastOptions;
+use crate::net::multicast_server::disable_multicast_all;
+use crate::net::notifier::*;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::packet_stream::{packet_stream_new, PacketStream};
+use crate::net::udp_socket::UdpSocket;
+
+/// AsyncMulticastServer is an async variant of MulticastServer which runs the receive loop as a tokio task.
+pub struct AsyncMulticastServer {
+    socket: Option<AsyncUdpSocket>,
+    notifier: Notifier,
+    shutdown: Arc<Notify>,
+    running: Arc<AtomicBool>,
+    task: Option<JoinHandle<()>>,
+    recv_buffer_size: usize,
+    maddrs: Vec<IpAddr>,
+    ifaddr: IpAddr,
+    port: u16,
+    scope_id: u32,
+    options: MulticastOptions,
+}
+
+impl Default for AsyncMulticastServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncMulticastServer {
+    pub fn new() -> AsyncMulticastServer {
+        AsyncMulticastServer {
+            socket: None,
+            notifier: notifier_new(),
+            shutdown: Arc::new(Notify::new()),
+            running: Arc::new(AtomicBool::new(false)),
+            task: None,
+            recv_buffer_size: MAX_PACKET_SIZE,
+            maddrs: Vec::new(),
+            ifaddr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 0,
+            scope_id: 0,
+            options: MulticastOptions::new(),
+        }
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        self.notifier.lock().unwrap().remove_observer(id)
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.notifier.lock().unwrap().set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> DispatchOptions {
+        *self.notifier.lock().unwrap().dispatch_options()
+    }
+
+    /// share_dispatch_options sets the dispatch options of the manager, and shares its worker threads instead of starting new ones.
+    pub(crate) fn share_dispatch_options(&mut self, manager: &NotifytManager) {
+        self.notifier
+            .lock()
+            .unwrap()
+            .share_dispatch_options(manager);
+    }
+
+    /// packets returns a new Stream of the received Packets.
+    pub fn packets(&mut self) -> PacketStream {
+        let (stream, observer) = packet_stream_new();
+        self.add_observer(observer);
+        stream
+    }
+
+    /// notify sends the packet to all joined groups. It tries all groups even if some of them could not be sent to, and returns the first error.
+    pub async fn notify(&self, msg: &Packet) -> Result<usize> {
+        if self.maddrs.is_empty() {
+            return Err(Error::NotBound);
+        }
+        let mut ret = Ok(0);
+        for maddr in self.maddrs.iter() {
+            let res = self.notify_group(maddr, msg).await;
+            if ret.is_ok() {
+                ret = res;
+            }
+        }
+        ret
+    }
+
+    /// notify_group sends the packet to the specified group.
+    pub async fn notify_group(&self, maddr: &IpAddr, msg: &Packet) -> Result<usize> {
+        let socket = match &self.socket {
+            Some(socket) => socket,
+            None => return Err(Error::NotBound),
+        };
+        let to_addr = self.multicast_addr(maddr);
+        info!("MCST {} -> {} ({})", self.ifaddr()?, to_addr, msg);
+        let ret = async_send_to(socket, msg.bytes(), to_addr).await;
+        if ret.is_err() {
+            warn!("Couldn't notify Packet to {}", to_addr);
+        }
+        ret
+    }
+
+    /// set_multicast_options sets the send options, which are applied to the socket with the outgoing interface when it is bound.
+    pub fn set_multicast_options(&mut self, options: MulticastOptions) {
+        self.options = options;
+    }
+
+    pub fn multicast_options(&self) -> &MulticastOptions {
+        &self.options
+    }
+
+    /// set_recv_buffer_size sets the size of the buffer which received datagrams are read into. Datagrams larger than it are notified as truncated Packets.
+    pub fn set_recv_buffer_size(&mut self, size: usize) {
+        self.recv_buffer_size = size;
+    }
+
+    pub fn recv_buffer_size(&self) -> usize {
+        self.recv_buffer_size
+    }
+
+    /// set_socket_recv_buffer_size sets SO_RCVBUF of the bound socket.
+    pub fn set_socket_recv_buffer_size(&self, size: usize) -> Result<()> {
+        match &self.socket {
+            Some(socket) => socket.get_ref().set_recv_buffer_size(size),
+            None => Err(Error::NotBound),
+        }
+    }
+
+    /// set_socket_send_buffer_size sets SO_SNDBUF of the bound socket.
+    pub fn set_socket_send_buffer_size(&self, size: usize) -> Result<()> {
+        match &self.socket {
+            Some(socket) => socket.get_ref().set_send_buffer_size(size),
+            None => Err(Error::NotBound),
+        }
+    }
+
+    /// interface_index returns the index of the bound interface, or 0 if it is unknown.
+    pub fn interface_index(&self) -> u32 {
+        self.scope_id
+    }
+
+    /// ifaddr returns the address of the interface which the groups are joined on, with the bound port.
+    pub fn ifaddr(&self) -> Result<SocketAddr> {
+        match &self.socket {
+            Some(socket) => Ok(SocketAddr::new(
+                self.ifaddr,
+                socket.get_ref().addr()?.port(),
+            )),
+            None => Err(Error::NotBound),
+        }
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    /// bind binds a new socket to the port, and joins the group on the interface.
+    /// The other groups can be joined to the bound socket by join_group().
+    pub fn bind(&mut self, maddr: IpAddr, port: u16, ifaddr: IpAddr) -> Result<()> {
+        let addr = match (maddr, ifaddr) {
+            (IpAddr::V4(_), IpAddr::V4(_)) => {
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)
+            }
+            (IpAddr::V6(_), IpAddr::V6(_)) => {
+                SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port)
+            }
+            _ => {
+                error!("BIND MCT {}:{} -> {}", ifaddr, port, maddr);
+                return Err(Error::AddressFamilyMismatch {
+                    group: maddr,
+                    iface: ifaddr,
+                });
+            }
+        };
+        debug!("BIND MCT {}", addr);
+        let mut socket = UdpSocket::new();
+        socket.bind(addr)?;
+        self.options.apply(&socket, &ifaddr)?;
+        disable_multicast_all(&socket);
+        socket.join_multicast(&maddr, &ifaddr)?;
+        debug!("BIND MCT {}:{} -> {}", ifaddr, port, maddr);
+        self.socket = Some(async_udp_socket_new(socket)?);
+        self.maddrs = vec![maddr];
+        self.ifaddr = ifaddr;
+        self.port = port;
+        self.scope_id = get_interface_index(ifaddr).unwrap_or(0);
+        Ok(())
+    }
+
+    /// join_group joins the group on the bound socket. It does nothing if the group has already been joined.
+    pub fn join_group(&mut self, maddr: IpAddr) -> Result<()> {
+        let socket = match &self.socket {
+            Some(socket) => socket,
+            None => return Err(Error::NotBound),
+        };
+        if self.maddrs.contains(&maddr) {
+            return Ok(());
+        }
+        socket.get_ref().join_multicast(&maddr, &self.ifaddr)?;
+        debug!("JOIN MCT {}:{} -> {}", self.ifaddr, self.port, maddr);
+        self.maddrs.push(maddr);
+        Ok(())
+    }
+
+    /// leave_group leaves the group on the bound socket. It does nothing if the group has not been joined.
+    pub fn leave_group(&mut self, maddr: IpAddr) -> Result<()> {
+        let socket = match &self.socket {
+            Some(socket) => socket,
+            None => return Err(Error::NotBound),
+        };
+        if !self.maddrs.contains(&maddr) {
+            return Ok(());
+        }
+        socket.get_ref().leave_multicast(&maddr, &self.ifaddr)?;
+        debug!("LEAVE MCT {}:{} -> {}", self.ifaddr, self.port, maddr);
+        self.maddrs.retain(|joined| *joined != maddr);
+        Ok(())
+    }
+
+    /// rejoin_groups leaves and joins all groups again on the bound socket, such as after the interface has been reconfigured with the same address.
+    /// It tries all groups even if some of them could not be joined, and returns the first error.
+    pub fn rejoin_groups(&mut self) -> Result<()> {
+        let socket = match &self.socket {
+            Some(socket) => socket,
+            None => return Err(Error::NotBound),
+        };
+        let mut ret = Ok(());
+        for maddr in self.maddrs.iter() {
+            let _ = socket.get_ref().leave_multicast(maddr, &self.ifaddr);
+            if let Err(e) = socket.get_ref().join_multicast(maddr, &self.ifaddr) {
+                warn!(
+                    "JOIN MCT {}:{} -> {} ({})",
+                    self.ifaddr, self.port, maddr, e
+                );
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+        }
+        debug!("REJOIN MCT {}:{}", self.ifaddr, self.port);
+        ret
+    }
+
+    /// groups returns the joined groups.
+    pub fn groups(&self) -> &[IpAddr] {
+        &self.maddrs
+    }
+
+    /// multicast_addr returns the group address to notify, which is scoped to the bound interface for IPv6.
+    fn multicast_addr(&self, maddr: &IpAddr) -> SocketAddr {
+        match maddr {
+            IpAddr::V4(_) => SocketAddr::new(*maddr, self.port),
+            IpAddr::V6(maddr_v6) => {
+                SocketAddr::V6(SocketAddrV6::new(*maddr_v6, self.port, 0, self.scope_id))
+            }
+        }
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        if self.task.as_ref().is_some_and(|task| !task.is_finished()) {
+            return Ok(());
+        }
+        let socket = match &self.socket {
+            Some(socket) => socket.clone(),
+            None => return Err(Error::NotBound),
+        };
+        let notifier = self.notifier.clone();
+        // NOTE: The shutdown is renewed for each task, since the permit stored by stop() after the previous task has exited would stop the new task at once.
+        self.shutdown = Arc::new(Notify::new());
+        let shutdown = self.shutdown.clone();
+        self.running.store(true, Ordering::SeqCst);
+        let running = self.running.clone();
+        let buffer_size = self.recv_buffer_size;
+        let if_index = self.scope_id;
+        self.task = Some(tokio::spawn(async move {
+            async_recv_loop(socket, notifier, shutdown, running, buffer_size, if_index).await;
+        }));
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        if let Some(task) = self.task.take() {
+            self.running.store(false, Ordering::SeqCst);
+            self.shutdown.notify_one();
+            let _ = task.await;
+        }
+        self.socket = None;
+        Ok(())
+    }
+}
+
+impl ObserverRegistry for AsyncMulticastServer {
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        self.notifier.lock().unwrap().insert_observer(id, observer)
+    }
+}
+
+impl Drop for AsyncMulticastServer {
+    fn drop(&mut self) {
+        // The receive task owns its own reference to the socket and releases it when it exits.
+        if self.task.is_some() {
+            self.running.store(false, Ordering::SeqCst);
+            self.shutdown.notify_one();
+        }
+    }
+}
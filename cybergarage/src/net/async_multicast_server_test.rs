@@ -0,0 +1,130 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time;
+
+    use tokio_stream::StreamExt;
+
+    use crate::net::async_multicast_server::*;
+    use crate::net::default_test::*;
+    use crate::net::interface::*;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+
+    use crate::log::Logger;
+    use crate::net::notify_manager_test::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_multicast_server() {
+        async fn test_multicast_server(ifaddr: IpAddr) {
+            Logger::init();
+            const TEST_OBSERVER_COUNT: i32 = 5;
+            let counter = Arc::new(Mutex::new(0));
+
+            let mut server = AsyncMulticastServer::new();
+
+            let observer = TestNotifyCounter::new(counter.clone());
+            server.add_observer(Arc::new(Mutex::new(observer)));
+            let mut packets = server.packets();
+
+            if ifaddr.is_ipv4() {
+                assert!(server
+                    .bind(TEST_MULTICAST_V4_ADDRESS, TEST_PORT, ifaddr)
+                    .is_ok());
+            } else if ifaddr.is_ipv6() {
+                assert!(server
+                    .bind(TEST_MULTICAST_V6_ADDRESS, TEST_PORT, ifaddr)
+                    .is_ok());
+            }
+
+            assert!(server.start().is_ok());
+
+            let mut pkt = Packet::new();
+            pkt.set_bytes(vec![0_u8; 1]);
+            for _ in 0..TEST_OBSERVER_COUNT {
+                assert!(server.ifaddr().is_ok());
+                assert!(server.notify(&pkt).await.is_ok());
+            }
+
+            // NOTE: GitHub Action is slow and may drop to send UDP packets.
+            let recv_pkt = tokio::time::timeout(time::Duration::from_secs(5), packets.next()).await;
+            if let Ok(Some(recv_pkt)) = recv_pkt {
+                assert_eq!(recv_pkt.bytes(), pkt.bytes());
+                assert!(0 < *counter.lock().unwrap());
+            }
+
+            assert!(server.stop().await.is_ok());
+        }
+
+        for ifaddr in get_all_interfaces() {
+            test_multicast_server(ifaddr).await;
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_multicast_server_interfaces() {
+        Logger::init();
+
+        let lo_addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ifaddr = match get_all_interfaces()
+            .into_iter()
+            .find(|ifaddr| ifaddr.is_ipv4() && !ifaddr.is_loopback())
+        {
+            Some(ifaddr) => ifaddr,
+            None => return,
+        };
+        let lo_packets = Arc::new(Mutex::new(Vec::new()));
+        let if_packets = Arc::new(Mutex::new(Vec::new()));
+
+        // The servers of the two interfaces are bound to the same wildcard address and port.
+        let mut lo_server = AsyncMulticastServer::new();
+        let observer = TestPacketRecorder::new(lo_packets.clone());
+        lo_server.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(lo_server
+            .bind(TEST_MULTICAST_V4_ADDRESS2, TEST_PORT, lo_addr)
+            .is_ok());
+        assert!(lo_server.start().is_ok());
+        let mut if_server = AsyncMulticastServer::new();
+        let observer = TestPacketRecorder::new(if_packets.clone());
+        if_server.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(if_server
+            .bind(TEST_MULTICAST_V4_ADDRESS2, TEST_PORT, ifaddr)
+            .is_ok());
+        assert!(if_server.start().is_ok());
+
+        // The datagram on the loopback interface is notified only once by the server of the interface.
+        // The other tests may send to the same group and port, so only the packets of this test are counted.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(b"async_multicast_server_interfaces".to_vec());
+        assert!(lo_server.notify(&pkt).await.is_ok());
+        tokio::time::sleep(time::Duration::from_millis(500)).await;
+        assert!(lo_server.stop().await.is_ok());
+        assert!(if_server.stop().await.is_ok());
+
+        let count = |packets: &Arc<Mutex<Vec<Packet>>>| {
+            packets
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|recv_pkt| recv_pkt.bytes() == pkt.bytes())
+                .count()
+        };
+        assert_eq!(count(&lo_packets), 1);
+        assert_eq!(count(&if_packets), 0);
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use tokio::io::unix::AsyncFd;
+use tokio::sync::Notify;
+
+use crate::net::error::Result;
+use crate::net::notifier::*;
+use crate::net::udp_socket::UdpSocket;
+
+/// AsyncUdpSocket is a bound UdpSocket registered to the tokio reactor.
+pub type AsyncUdpSocket = Arc<AsyncFd<UdpSocket>>;
+
+/// async_udp_socket_new switches the bound socket to non-blocking mode and registers it to the tokio reactor.
+pub fn async_udp_socket_new(socket: UdpSocket) -> Result<AsyncUdpSocket> {
+    socket.set_nonblocking(true)?;
+    // SAFETY: The bound socket owns its file descriptor, which is only released when the AsyncFd drops it.
+    let socket = unsafe { AsyncFd::register(socket).map_err(io::Error::from)? };
+    Ok(Arc::new(socket))
+}
+
+/// async_send_to sends the specified bytes once the socket becomes writable.
+pub async fn async_send_to(
+    socket: &AsyncUdpSocket,
+    buf: &[u8],
+    to_addr: SocketAddr,
+) -> Result<usize> {
+    loop {
+        let mut guard = socket.writable().await?;
+        match guard.try_io(|inner| would_block(inner.get_ref().send_to(buf, to_addr))) {
+            Ok(Ok(ret)) => return ret,
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_would_block) => continue,
+        }
+    }
+}
+
+/// async_recv_loop receives Packets and notifies them to the observers until the shutdown is notified.
+/// The packets which came in on other interfaces than if_index are discarded unless if_index is 0.
+pub async fn async_recv_loop(
+    socket: AsyncUdpSocket,
+    notifier: Notifier,
+    shutdown: Arc<Notify>,
+    running: Arc<AtomicBool>,
+    buffer_size: usize,
+    if_index: u32,
+) {
+    let mut buf = vec![0_u8; buffer_size];
+    loop {
+        let mut guard = tokio::select! {
+            _ = shutdown.notified() => break,
+            guard = socket.readable() => match guard {
+                Ok(guard) => guard,
+                Err(e) => {
+                    warn!("RECV ({})", e);
+                    break;
+                }
+            },
+        };
+        let recv_res =
+            match guard.try_io(|inner| would_block(inner.get_ref().recv_packet(&mut buf))) {
+                Ok(Ok(recv_res)) => recv_res,
+                Ok(Err(e)) => Err(e.into()),
+                Err(_would_block) => continue,
+            };
+        match recv_res {
+            Ok(msg) => {
+                if if_index != 0 && msg.interface_index() != if_index {
+                    continue;
+                }
+                if let Ok(addr) = socket.get_ref().addr() {
+                    info!("RECV {} -> {} ({})", msg.from(), addr, msg);
+                }
+                if msg.is_truncated() {
+                    warn!("RECV {} truncated to {} bytes", msg.from(), buffer_size);
+                }
+                notifier_notify_while(&notifier, &msg, &running);
+            }
+            Err(e) => {
+                warn!("RECV ({})", e);
+                break;
+            }
+        }
+    }
+}
+
+/// would_block lifts WouldBlock errors to io::Error so that AsyncFdReadyGuard::try_io() clears the readiness.
+fn would_block<T>(ret: Result<T>) -> io::Result<Result<T>> {
+    match ret {
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(e.into()),
+        ret => Ok(ret),
+    }
+}
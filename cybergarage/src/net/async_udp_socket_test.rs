@@ -0,0 +1,68 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use tokio::sync::Notify;
+
+    use crate::net::async_udp_socket::*;
+    use crate::net::interface::get_interface_index;
+    use crate::net::notifier::*;
+    use crate::net::notify_manager_test::*;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::udp_socket::UdpSocket;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_recv_loop_interface_index() {
+        let lo_addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let lo_index = get_interface_index(lo_addr).unwrap();
+
+        // The datagram on the loopback interface is discarded by the loop of another interface.
+        for (if_index, expected) in [(0, 1), (lo_index, 1), (lo_index + 1, 0)] {
+            let mut socket = UdpSocket::new();
+            assert!(socket.bind(SocketAddr::new(lo_addr, 0)).is_ok());
+            let addr = socket.addr().unwrap();
+            let socket = async_udp_socket_new(socket).unwrap();
+            let packets = Arc::new(Mutex::new(Vec::new()));
+            let notifier = notifier_new();
+            let observer = TestPacketRecorder::new(packets.clone());
+            notifier
+                .lock()
+                .unwrap()
+                .add_observer(Arc::new(Mutex::new(observer)));
+            let shutdown = Arc::new(Notify::new());
+            let running = Arc::new(AtomicBool::new(true));
+            let task = tokio::spawn(async_recv_loop(
+                socket,
+                notifier,
+                shutdown.clone(),
+                running,
+                1024,
+                if_index,
+            ));
+
+            let sender = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            sender.send_to(b"async_recv_loop", addr).unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            shutdown.notify_one();
+            assert!(task.await.is_ok());
+            assert_eq!(packets.lock().unwrap().len(), expected, "{}", if_index);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use log::*;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::net::async_unicast_server::AsyncUnicastServer;
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::interface::*;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::packet_stream::{packet_stream_new, PacketStream};
+use crate::net::route::{find_route, is_same_family, Route, SendFallback};
+use crate::net::send_report::SendReport;
+
+/// AsyncUnicastManager is an async variant of UnicastManager which binds an AsyncUnicastServer to each interface.
+pub struct AsyncUnicastManager {
+    udp_servers: Vec<AsyncUnicastServer>,
+    observers: NotifytManager,
+    filter: InterfaceFilter,
+    port: Option<u16>,
+    fallback: SendFallback,
+}
+
+impl Default for AsyncUnicastManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncUnicastManager {
+    pub fn new() -> AsyncUnicastManager {
+        AsyncUnicastManager {
+            udp_servers: Vec::new(),
+            observers: NotifytManager::new(),
+            filter: InterfaceFilter::new(),
+            port: None,
+            fallback: SendFallback::default(),
+        }
+    }
+
+    /// set_interface_filter sets the filter which selects the interfaces to bind in start() and rebind().
+    pub fn set_interface_filter(&mut self, filter: InterfaceFilter) {
+        self.filter = filter;
+    }
+
+    pub fn interface_filter(&self) -> &InterfaceFilter {
+        &self.filter
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers by the servers which are bound after this call.
+    /// The servers share the worker threads of DispatchMode::Pool, which are started once for the manager.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.observers.set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> &DispatchOptions {
+        self.observers.dispatch_options()
+    }
+
+    /// remove_observer removes the observer from all servers, and returns false if it has not been added.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        for udp_server in self.udp_servers.iter_mut() {
+            udp_server.remove_observer(id);
+        }
+        self.observers.remove_observer(id)
+    }
+
+    /// packets returns a new Stream of the Packets received by all running servers.
+    pub fn packets(&mut self) -> PacketStream {
+        let (stream, observer) = packet_stream_new();
+        self.add_observer(observer);
+        stream
+    }
+
+    /// set_send_fallback sets how send() handles the destinations which are not on the subnet of any bound interface.
+    pub fn set_send_fallback(&mut self, fallback: SendFallback) {
+        self.fallback = fallback;
+    }
+
+    pub fn send_fallback(&self) -> SendFallback {
+        self.fallback
+    }
+
+    /// routes returns the bound servers with their interface addresses.
+    fn routes(&self) -> (Vec<&AsyncUnicastServer>, Vec<Route>) {
+        let mut udp_servers = Vec::new();
+        let mut routes = Vec::new();
+        for udp_server in self.udp_servers.iter() {
+            if let Ok(ifaddr) = udp_server.ifaddr() {
+                udp_servers.push(udp_server);
+                routes.push(Route {
+                    ifaddr,
+                    network: udp_server.network(),
+                    if_index: udp_server.interface_index(),
+                });
+            }
+        }
+        (udp_servers, routes)
+    }
+
+    /// send sends the packet from the server whose interface subnet reaches the destination,
+    /// or as specified by the send fallback, and returns the address of the interface which sent it.
+    pub async fn send(&self, to_addr: SocketAddr, msg: &Packet) -> Result<SocketAddr> {
+        let (udp_servers, routes) = self.routes();
+        let n = match find_route(&routes, &to_addr) {
+            Some(n) => n,
+            None => match self.fallback {
+                SendFallback::FirstInterface => {
+                    match routes
+                        .iter()
+                        .position(|route| is_same_family(&route.ifaddr, &to_addr))
+                    {
+                        Some(n) => n,
+                        None => return Err(Error::NoRoute { to: to_addr }),
+                    }
+                }
+                SendFallback::AllInterfaces => {
+                    let mut first_err = None;
+                    for (ifaddr, result) in self.send_all(to_addr, msg).await.into_results() {
+                        match result {
+                            Ok(_) => return Ok(ifaddr),
+                            Err(e) => {
+                                first_err.get_or_insert(e);
+                            }
+                        }
+                    }
+                    return Err(first_err.unwrap_or(Error::NoRoute { to: to_addr }));
+                }
+                SendFallback::Fail => return Err(Error::NoRoute { to: to_addr }),
+            },
+        };
+        udp_servers[n].send(to_addr, msg).await?;
+        Ok(routes[n].ifaddr)
+    }
+
+    /// send_all sends the packet from all servers of the same address family as the destination, and returns the outcome for each interface.
+    pub async fn send_all(&self, to_addr: SocketAddr, msg: &Packet) -> SendReport {
+        let mut report = SendReport::new();
+        for udp_server in self.udp_servers.iter() {
+            let ifaddr = match udp_server.ifaddr() {
+                Ok(ifaddr) if is_same_family(&ifaddr, &to_addr) => ifaddr,
+                _ => continue,
+            };
+            report.add(ifaddr, udp_server.send(to_addr, msg).await);
+        }
+        report
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.port.is_some()
+    }
+
+    /// ifaddrs returns the addresses of the bound servers.
+    pub fn ifaddrs(&self) -> Vec<SocketAddr> {
+        self.udp_servers
+            .iter()
+            .filter_map(|udp_server| udp_server.ifaddr().ok())
+            .collect()
+    }
+
+    pub fn has_interface(&self, addr: IpAddr) -> bool {
+        self.udp_servers
+            .iter()
+            .any(|udp_server| matches!(udp_server.ifaddr(), Ok(ifaddr) if ifaddr.ip() == addr))
+    }
+
+    fn start_server(&self, ifaddr: IpAddr, port: u16) -> Result<AsyncUnicastServer> {
+        let mut udp_server = AsyncUnicastServer::new();
+        udp_server.share_dispatch_options(&self.observers);
+        for (id, observer) in self.observers.entries() {
+            udp_server.insert_observer(*id, observer.clone());
+        }
+        udp_server.bind(ifaddr, port)?;
+        udp_server.start()?;
+        Ok(udp_server)
+    }
+
+    pub async fn start(&mut self, port: u16) -> Result<()> {
+        self.start_with_interfaces(port, &self.filter.addresses(&get_interfaces()))
+            .await
+    }
+
+    /// start_with_interfaces binds a server to each of the specified interfaces.
+    pub async fn start_with_interfaces(&mut self, port: u16, ifaddrs: &[IpAddr]) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+
+        if ifaddrs.is_empty() {
+            return Err(Error::NoInterfaces);
+        }
+
+        self.port = Some(port);
+        for ifaddr in ifaddrs.iter().copied() {
+            match self.start_server(ifaddr, port) {
+                Ok(udp_server) => self.udp_servers.push(udp_server),
+                Err(e) => {
+                    let _ = self.stop().await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// rebind binds the interfaces which are newly selected by the interface filter, and stops the servers of the vanished interfaces.
+    pub async fn rebind(&mut self) -> Result<()> {
+        self.rebind_with_interfaces(&self.filter.addresses(&get_interfaces()))
+            .await
+    }
+
+    /// rebind_with_interfaces updates the running servers to the specified interfaces.
+    /// It tries all interfaces even if some of them could not be bound, and returns the first error.
+    pub async fn rebind_with_interfaces(&mut self, ifaddrs: &[IpAddr]) -> Result<()> {
+        let port = match self.port {
+            Some(port) => port,
+            None => return Ok(()),
+        };
+
+        let mut udp_servers = Vec::new();
+        for mut udp_server in self.udp_servers.drain(..) {
+            match udp_server.ifaddr() {
+                Ok(addr) if ifaddrs.contains(&addr.ip()) => udp_servers.push(udp_server),
+                _ => {
+                    info!("UNBIND {:?}", udp_server.ifaddr());
+                    let _ = udp_server.stop().await;
+                }
+            }
+        }
+        self.udp_servers = udp_servers;
+
+        let mut ret = Ok(());
+        for ifaddr in ifaddrs.iter().copied() {
+            if self.has_interface(ifaddr) {
+                continue;
+            }
+            match self.start_server(ifaddr, port) {
+                Ok(udp_server) => {
+                    info!("BIND {}:{}", ifaddr, port);
+                    self.udp_servers.push(udp_server);
+                }
+                Err(e) => {
+                    warn!("BIND {}:{} ({})", ifaddr, port, e);
+                    if ret.is_ok() {
+                        ret = Err(e);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        for udp_server in self.udp_servers.iter_mut() {
+            udp_server.stop().await?;
+        }
+        self.udp_servers.clear();
+        self.port = None;
+        Ok(())
+    }
+}
+
+impl ObserverRegistry for AsyncUnicastManager {
+    /// insert_observer adds the observer to the running servers and the servers which are bound later.
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        let id = self.observers.insert_observer(id, observer.clone());
+        for udp_server in self.udp_servers.iter_mut() {
+            udp_server.insert_observer(id, observer.clone());
+        }
+        id
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time;
+
+    use crate::log::Logger;
+    use crate::net::async_unicast_manager::*;
+    use crate::net::default_test::*;
+    use crate::net::notify_manager_test::*;
+    use crate::net::observer::ObserverObject;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+
+    #[tokio::test]
+    async fn async_unicast_manager() {
+        let mut mgr = AsyncUnicastManager::new();
+        assert!(mgr.start(TEST_PORT).await.is_ok());
+        assert!(mgr.stop().await.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_unicast_manager_observer_order() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let before_packets = Arc::new(Mutex::new(Vec::new()));
+        let after_packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = AsyncUnicastManager::new();
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(before_packets.clone())));
+        mgr.add_observer(observer);
+        assert!(mgr.start_with_interfaces(0, &[v4_ifaddr]).await.is_ok());
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(after_packets.clone())));
+        mgr.add_observer(observer);
+
+        // Both observers are attached to the server which is bound after they are added.
+        let ret = mgr.rebind_with_interfaces(&[v4_ifaddr, v6_ifaddr]).await;
+        assert!(ret.is_ok(), "{:?}", ret);
+        let ifaddrs = mgr.ifaddrs();
+        assert_eq!(ifaddrs.len(), 2);
+
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        for ifaddr in ifaddrs {
+            assert!(mgr.send(ifaddr, &pkt).await.is_ok());
+        }
+        tokio::time::sleep(time::Duration::from_millis(500)).await;
+        assert!(mgr.stop().await.is_ok());
+
+        assert_eq!(before_packets.lock().unwrap().len(), 2);
+        assert_eq!(after_packets.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::net::async_udp_socket::*;
+use crate::net::cidr::Cidr;
+use crate::net::default::MAX_PACKET_SIZE;
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::interface::{find_interface_index, find_interface_network, get_interfaces};
+use crate::net::notifier::*;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::packet_stream::{packet_stream_new, PacketStream};
+use crate::net::udp_socket::UdpSocket;
+
+/// AsyncUnicastServer is an async variant of UnicastServer which runs the receive loop as a tokio task.
+pub struct AsyncUnicastServer {
+    socket: Option<AsyncUdpSocket>,
+    notifier: Notifier,
+    shutdown: Arc<Notify>,
+    running: Arc<AtomicBool>,
+    task: Option<JoinHandle<()>>,
+    recv_buffer_size: usize,
+    if_index: u32,
+    network: Option<Cidr>,
+}
+
+impl Default for AsyncUnicastServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncUnicastServer {
+    pub fn new() -> AsyncUnicastServer {
+        AsyncUnicastServer {
+            socket: None,
+            notifier: notifier_new(),
+            shutdown: Arc::new(Notify::new()),
+            running: Arc::new(AtomicBool::new(false)),
+            task: None,
+            recv_buffer_size: MAX_PACKET_SIZE,
+            if_index: 0,
+            network: None,
+        }
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        self.notifier.lock().unwrap().remove_observer(id)
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.notifier.lock().unwrap().set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> DispatchOptions {
+        *self.notifier.lock().unwrap().dispatch_options()
+    }
+
+    /// share_dispatch_options sets the dispatch options of the manager, and shares its worker threads instead of starting new ones.
+    pub(crate) fn share_dispatch_options(&mut self, manager: &NotifytManager) {
+        self.notifier
+            .lock()
+            .unwrap()
+            .share_dispatch_options(manager);
+    }
+
+    /// packets returns a new Stream of the received Packets.
+    pub fn packets(&mut self) -> PacketStream {
+        let (stream, observer) = packet_stream_new();
+        self.add_observer(observer);
+        stream
+    }
+
+    pub async fn send(&self, to_addr: SocketAddr, msg: &Packet) -> Result<usize> {
+        let socket = match &self.socket {
+            Some(socket) => socket,
+            None => return Err(Error::NotBound),
+        };
+        info!("SEND {} -> {} ({})", socket.get_ref().addr()?, to_addr, msg,);
+        let ret = async_send_to(socket, msg.bytes(), to_addr).await;
+        if ret.is_err() {
+            warn!("Couldn't send Packet to {}", to_addr);
+        }
+        ret
+    }
+
+    /// set_recv_buffer_size sets the size of the buffer which received datagrams are read into. Datagrams larger than it are notified as truncated Packets.
+    pub fn set_recv_buffer_size(&mut self, size: usize) {
+        self.recv_buffer_size = size;
+    }
+
+    pub fn recv_buffer_size(&self) -> usize {
+        self.recv_buffer_size
+    }
+
+    /// set_socket_recv_buffer_size sets SO_RCVBUF of the bound socket.
+    pub fn set_socket_recv_buffer_size(&self, size: usize) -> Result<()> {
+        match &self.socket {
+            Some(socket) => socket.get_ref().set_recv_buffer_size(size),
+            None => Err(Error::NotBound),
+        }
+    }
+
+    /// set_socket_send_buffer_size sets SO_SNDBUF of the bound socket.
+    pub fn set_socket_send_buffer_size(&self, size: usize) -> Result<()> {
+        match &self.socket {
+            Some(socket) => socket.get_ref().set_send_buffer_size(size),
+            None => Err(Error::NotBound),
+        }
+    }
+
+    /// interface_index returns the index of the bound interface, or 0 if it is unknown.
+    pub fn interface_index(&self) -> u32 {
+        self.if_index
+    }
+
+    /// network returns the address and the prefix length of the bound interface, or None if it is unknown.
+    pub fn network(&self) -> Option<Cidr> {
+        self.network
+    }
+
+    pub fn ifaddr(&self) -> Result<SocketAddr> {
+        match &self.socket {
+            Some(socket) => socket.get_ref().addr(),
+            None => Err(Error::NotBound),
+        }
+    }
+
+    pub fn bind(&mut self, ifaddr: IpAddr, port: u16) -> Result<()> {
+        let addr = SocketAddr::new(ifaddr, port);
+        debug!("BIND UDP {}", addr);
+        let mut socket = UdpSocket::new();
+        socket.bind(addr)?;
+        self.socket = Some(async_udp_socket_new(socket)?);
+        let ifaces = get_interfaces();
+        self.if_index = find_interface_index(&ifaces, ifaddr).unwrap_or(0);
+        self.network = find_interface_network(&ifaces, ifaddr);
+        Ok(())
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        if self.task.as_ref().is_some_and(|task| !task.is_finished()) {
+            return Ok(());
+        }
+        let socket = match &self.socket {
+            Some(socket) => socket.clone(),
+            None => return Err(Error::NotBound),
+        };
+        let notifier = self.notifier.clone();
+        // NOTE: The shutdown is renewed for each task, since the permit stored by stop() after the previous task has exited would stop the new task at once.
+        self.shutdown = Arc::new(Notify::new());
+        let shutdown = self.shutdown.clone();
+        self.running.store(true, Ordering::SeqCst);
+        let running = self.running.clone();
+        let buffer_size = self.recv_buffer_size;
+        self.task = Some(tokio::spawn(async move {
+            async_recv_loop(socket, notifier, shutdown, running, buffer_size, 0).await;
+        }));
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        if let Some(task) = self.task.take() {
+            self.running.store(false, Ordering::SeqCst);
+            self.shutdown.notify_one();
+            let _ = task.await;
+        }
+        self.socket = None;
+        Ok(())
+    }
+}
+
+impl ObserverRegistry for AsyncUnicastServer {
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        self.notifier.lock().unwrap().insert_observer(id, observer)
+    }
+}
+
+impl Drop for AsyncUnicastServer {
+    fn drop(&mut self) {
+        // The receive task owns its own reference to the socket and releases it when it exits.
+        if self.task.is_some() {
+            self.running.store(false, Ordering::SeqCst);
+            self.shutdown.notify_one();
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time;
+
+    use tokio_stream::StreamExt;
+
+    use crate::net::async_unicast_server::*;
+    use crate::net::default_test::*;
+    use crate::net::interface::*;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+
+    use crate::log::Logger;
+    use crate::net::notify_manager_test::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_unicast_server() {
+        async fn test_udp_server(ifaddr: IpAddr) {
+            Logger::init();
+
+            const TEST_OBSERVER_COUNT: i32 = 5;
+            let counter = Arc::new(Mutex::new(0));
+
+            let mut server = AsyncUnicastServer::new();
+
+            let observer = TestNotifyCounter::new(counter.clone());
+            server.add_observer(Arc::new(Mutex::new(observer)));
+            let mut packets = server.packets();
+
+            let ret = server.bind(ifaddr, TEST_PORT);
+            assert!(ret.is_ok(), "{:?}", ret);
+            assert!(server.start().is_ok());
+
+            let mut pkt = Packet::new();
+            pkt.set_bytes(vec![0_u8; 1]);
+            for _ in 0..TEST_OBSERVER_COUNT {
+                let server_addr = server.ifaddr();
+                assert!(server_addr.is_ok());
+                let ret = server.send(server_addr.unwrap(), &pkt).await;
+                assert!(ret.is_ok(), "{:?}", ret);
+            }
+
+            let recv_pkt = tokio::time::timeout(time::Duration::from_secs(5), packets.next()).await;
+            assert!(recv_pkt.is_ok());
+            assert_eq!(recv_pkt.unwrap().unwrap().bytes(), pkt.bytes());
+
+            let counter = counter.lock();
+            if let Ok(counter) = counter {
+                assert!(0 < *counter);
+            }
+
+            assert!(server.stop().await.is_ok());
+        }
+
+        for ifaddr in get_all_interfaces() {
+            test_udp_server(ifaddr).await;
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_unicast_server_start_twice() {
+        Logger::init();
+
+        let counter = Arc::new(Mutex::new(0));
+        let mut server = AsyncUnicastServer::new();
+        let observer = TestNotifyCounter::new(counter.clone());
+        server.add_observer(Arc::new(Mutex::new(observer)));
+        let ret = server.bind(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        assert!(ret.is_ok(), "{:?}", ret);
+        let server_addr = server.ifaddr().unwrap();
+
+        // The second start is ignored, so that stop() stops the only receive task.
+        assert!(server.start().is_ok());
+        assert!(server.start().is_ok());
+        let ret = tokio::time::timeout(time::Duration::from_secs(5), server.stop()).await;
+        assert!(ret.is_ok_and(|ret| ret.is_ok()));
+
+        let sender = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        sender.send_to(b"start_twice", server_addr).unwrap();
+        tokio::time::sleep(time::Duration::from_millis(200)).await;
+        assert_eq!(*counter.lock().unwrap(), 0);
+    }
+}
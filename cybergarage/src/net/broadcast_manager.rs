@@ -0,0 +1,287 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::net::broadcast_server::BroadcastServer;
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::interface::*;
+use crate::net::interface_observer::{InterfaceEvent, InterfaceObserver};
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::send_report::SendReport;
+use crate::net::udp_socket_options::UdpSocketOptions;
+
+/// BroadcastManager runs a BroadcastServer on each IPv4 interface which supports broadcast.
+pub struct BroadcastManager {
+    bcast_servers: Vec<BroadcastServer>,
+    observers: NotifytManager,
+    filter: InterfaceFilter,
+    port: Option<u16>,
+    socket_options: UdpSocketOptions,
+}
+
+impl Default for BroadcastManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// broadcast_addresses returns the IPv4 addresses of the interfaces which are selected by the filter and support broadcast.
+fn broadcast_addresses(filter: &InterfaceFilter, ifaces: &[Interface]) -> Vec<IpAddr> {
+    let ifaces: Vec<Interface> = ifaces
+        .iter()
+        .filter(|iface| iface.is_broadcast())
+        .cloned()
+        .collect();
+    filter
+        .addresses(&ifaces)
+        .into_iter()
+        .filter(|addr| addr.is_ipv4())
+        .collect()
+}
+
+impl BroadcastManager {
+    pub fn new() -> BroadcastManager {
+        BroadcastManager {
+            bcast_servers: Vec::new(),
+            observers: NotifytManager::new(),
+            filter: InterfaceFilter::new(),
+            port: None,
+            socket_options: UdpSocketOptions::new(),
+        }
+    }
+
+    /// set_interface_filter sets the filter which selects the interfaces to bind in start() and rebind().
+    pub fn set_interface_filter(&mut self, filter: InterfaceFilter) {
+        self.filter = filter;
+    }
+
+    pub fn interface_filter(&self) -> &InterfaceFilter {
+        &self.filter
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers by the servers which are bound after this call.
+    /// The servers share the worker threads of DispatchMode::Pool, which are started once for the manager.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.observers.set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> &DispatchOptions {
+        self.observers.dispatch_options()
+    }
+
+    /// set_socket_options sets the socket options of the servers which are bound after this call.
+    pub fn set_socket_options(&mut self, options: UdpSocketOptions) {
+        self.socket_options = options;
+    }
+
+    pub fn socket_options(&self) -> &UdpSocketOptions {
+        &self.socket_options
+    }
+
+    /// remove_observer removes the observer from all servers, and returns false if it has not been added.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        for bcast_server in self.bcast_servers.iter_mut() {
+            bcast_server.remove_observer(id);
+        }
+        self.observers.remove_observer(id)
+    }
+
+    /// notify sends the packet to the broadcast address of every interface. It tries all servers even if some of them could not send it, and returns the first error.
+    pub fn notify(&self, msg: &Packet) -> Result<()> {
+        for (_, result) in self.notify_all(msg).into_results() {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// notify_all sends the packet to the broadcast address of every interface, and returns the outcome for each interface.
+    pub fn notify_all(&self, msg: &Packet) -> SendReport {
+        let mut report = SendReport::new();
+        for bcast_server in self.bcast_servers.iter() {
+            if let Ok(ifaddr) = bcast_server.ifaddr() {
+                report.add(ifaddr, bcast_server.notify(msg));
+            }
+        }
+        report
+    }
+
+    /// send_all sends the packet to the address out of every interface, such as the limited broadcast address with another port,
+    /// and returns the outcome for each interface.
+    pub fn send_all(&self, to_addr: SocketAddr, msg: &Packet) -> SendReport {
+        let mut report = SendReport::new();
+        for bcast_server in self.bcast_servers.iter() {
+            if let Ok(ifaddr) = bcast_server.ifaddr() {
+                report.add(ifaddr, bcast_server.send(to_addr, msg));
+            }
+        }
+        report
+    }
+
+    /// reply sends the bytes back to the sender of the received packet, from the server on the interface which the packet came in on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        let bcast_server = self
+            .bcast_servers
+            .iter()
+            .find(|bcast_server| bcast_server.interface_index() == msg.interface_index());
+        match bcast_server {
+            Some(bcast_server) => bcast_server.reply(msg, bytes),
+            None => Err(Error::NoRoute { to: msg.from() }),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.port.is_some()
+    }
+
+    /// ifaddrs returns the addresses of the bound servers.
+    pub fn ifaddrs(&self) -> Vec<SocketAddr> {
+        self.bcast_servers
+            .iter()
+            .filter_map(|server| server.ifaddr().ok())
+            .collect()
+    }
+
+    pub fn has_interface(&self, addr: IpAddr) -> bool {
+        self.bcast_servers
+            .iter()
+            .any(|server| matches!(server.ifaddr(), Ok(ifaddr) if ifaddr.ip() == addr))
+    }
+
+    fn start_server(&self, ifaddr: IpAddr, port: u16) -> Result<BroadcastServer> {
+        let mut bcast_server = BroadcastServer::new();
+        bcast_server.share_dispatch_options(&self.observers);
+        bcast_server.set_socket_options(self.socket_options.clone());
+        for (id, observer) in self.observers.entries() {
+            bcast_server.insert_observer(*id, observer.clone());
+        }
+        bcast_server.bind(ifaddr, port)?;
+        bcast_server.start()?;
+        Ok(bcast_server)
+    }
+
+    pub fn start(&mut self, port: u16) -> Result<()> {
+        self.start_with_interfaces(port, &broadcast_addresses(&self.filter, &get_interfaces()))
+    }
+
+    /// start_with_interfaces binds a server to each of the specified IPv4 interfaces. The IPv6 addresses are ignored.
+    pub fn start_with_interfaces(&mut self, port: u16, ifaddrs: &[IpAddr]) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+
+        let ifaddrs: Vec<IpAddr> = ifaddrs.iter().copied().filter(IpAddr::is_ipv4).collect();
+        if ifaddrs.is_empty() {
+            return Err(Error::NoInterfaces);
+        }
+
+        self.port = Some(port);
+        for ifaddr in ifaddrs {
+            match self.start_server(ifaddr, port) {
+                Ok(bcast_server) => self.bcast_servers.push(bcast_server),
+                Err(e) => {
+                    let _ = self.stop();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// rebind binds the interfaces which are newly selected by the interface filter, and stops the servers of the vanished interfaces.
+    pub fn rebind(&mut self) -> Result<()> {
+        self.rebind_with_interfaces(&broadcast_addresses(&self.filter, &get_interfaces()))
+    }
+
+    /// rebind_with_interfaces updates the running servers to the specified IPv4 interfaces.
+    /// It tries all interfaces even if some of them could not be bound, and returns the first error.
+    pub fn rebind_with_interfaces(&mut self, ifaddrs: &[IpAddr]) -> Result<()> {
+        let port = match self.port {
+            Some(port) => port,
+            None => return Ok(()),
+        };
+
+        let mut bcast_servers = Vec::new();
+        for mut bcast_server in self.bcast_servers.drain(..) {
+            match bcast_server.ifaddr() {
+                Ok(addr) if ifaddrs.contains(&addr.ip()) => bcast_servers.push(bcast_server),
+                _ => {
+                    info!("UNBIND {:?}", bcast_server.ifaddr());
+                    let _ = bcast_server.stop();
+                }
+            }
+        }
+        self.bcast_servers = bcast_servers;
+
+        let mut ret = Ok(());
+        for ifaddr in ifaddrs.iter().copied().filter(IpAddr::is_ipv4) {
+            if self.has_interface(ifaddr) {
+                continue;
+            }
+            match self.start_server(ifaddr, port) {
+                Ok(bcast_server) => {
+                    info!("BIND {}:{}", ifaddr, port);
+                    self.bcast_servers.push(bcast_server);
+                }
+                Err(e) => {
+                    warn!("BIND {}:{} ({})", ifaddr, port, e);
+                    if ret.is_ok() {
+                        ret = Err(e);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        for bcast_server in self.bcast_servers.iter_mut() {
+            bcast_server.stop()?;
+        }
+        self.bcast_servers.clear();
+        self.port = None;
+        Ok(())
+    }
+}
+
+impl ObserverRegistry for BroadcastManager {
+    /// insert_observer adds the observer to the running servers and the servers which are bound later.
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        let id = self.observers.insert_observer(id, observer.clone());
+        for bcast_server in self.bcast_servers.iter_mut() {
+            bcast_server.insert_observer(id, observer.clone());
+        }
+        id
+    }
+}
+
+impl InterfaceObserver for BroadcastManager {
+    /// interface_changed rebinds the servers, so that a manager shared with InterfaceWatcher follows the interface changes.
+    fn interface_changed(&mut self, _event: &InterfaceEvent) {
+        if let Err(e) = self.rebind() {
+            warn!("REBIND ({})", e);
+        }
+    }
+}
+
+impl Drop for BroadcastManager {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time;
+
+    use crate::log::Logger;
+    use crate::net::broadcast_manager::*;
+    use crate::net::default_test::*;
+    use crate::net::error::Error;
+    use crate::net::interface::InterfaceFilter;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+    use crate::net::udp_socket::UdpSocket;
+
+    use crate::net::notify_manager_test::*;
+
+    #[test]
+    fn broadcast_manager() {
+        Logger::init();
+
+        let mut mgr = BroadcastManager::new();
+        mgr.set_interface_filter(InterfaceFilter::new().include_name("lo").loopback(true));
+        assert!(matches!(
+            mgr.start(TEST_BROADCAST_PORT),
+            Err(Error::NoInterfaces)
+        ));
+
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert!(matches!(
+            mgr.start_with_interfaces(TEST_BROADCAST_PORT, &[v6_ifaddr]),
+            Err(Error::NoInterfaces)
+        ));
+        assert!(!mgr.is_running());
+    }
+
+    #[test]
+    fn broadcast_manager_notify() {
+        Logger::init();
+
+        let ifaddr = match find_broadcast_interface() {
+            Some(ifaddr) => ifaddr,
+            None => return,
+        };
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = BroadcastManager::new();
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(mgr
+            .start_with_interfaces(TEST_BROADCAST_PORT, &[ifaddr])
+            .is_ok());
+        assert!(mgr.has_interface(ifaddr));
+
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        let report = mgr.notify_all(&pkt);
+        assert!(report.is_ok());
+        assert_eq!(report.successes().len(), 1);
+        assert_eq!(report.successes()[0].ip(), ifaddr);
+        thread::sleep(time::Duration::from_millis(500));
+
+        let received = packets.lock().unwrap().first().cloned();
+        assert!(received.is_some());
+        let received = received.unwrap();
+        assert!(received.is_broadcast());
+        assert!(mgr.reply(&received, &[1_u8; 1]).is_ok());
+
+        // The server stopped by rebind no longer receives the broadcast datagrams.
+        assert!(mgr.rebind_with_interfaces(&[]).is_ok());
+        assert!(!mgr.has_interface(ifaddr));
+        assert!(mgr.notify_all(&pkt).is_empty());
+        assert!(mgr.stop().is_ok());
+    }
+
+    #[test]
+    fn broadcast_manager_interfaces() {
+        Logger::init();
+
+        let ifaddr = match find_broadcast_interface() {
+            Some(ifaddr) => ifaddr,
+            None => return,
+        };
+        let lo_addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = BroadcastManager::new();
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+        let ret = mgr.start_with_interfaces(TEST_BROADCAST_PORT, &[lo_addr, ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        assert!(mgr.has_interface(lo_addr));
+        assert!(mgr.has_interface(ifaddr));
+
+        // The servers of both interfaces share the port, but the unicast datagrams are notified by the server of the receiving interface.
+        const TEST_PACKET_COUNT: usize = 20;
+        let mut client = UdpSocket::new();
+        assert!(client.bind(SocketAddr::new(lo_addr, 0)).is_ok());
+        let to_addr = SocketAddr::new(lo_addr, TEST_BROADCAST_PORT);
+        for _ in 0..TEST_PACKET_COUNT {
+            assert!(client
+                .send_to(b"broadcast_manager_interfaces", to_addr)
+                .is_ok());
+        }
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        let packets = packets.lock().unwrap();
+        let received: Vec<&Packet> = packets
+            .iter()
+            .filter(|pkt| pkt.bytes() == b"broadcast_manager_interfaces")
+            .collect();
+        assert_eq!(received.len(), TEST_PACKET_COUNT);
+        assert!(received.iter().all(|pkt| pkt.is_unicast()));
+    }
+}
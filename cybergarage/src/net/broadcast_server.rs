@@ -0,0 +1,260 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![allow(dead_code)]
+
+use log::*;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, RwLock};
+
+use crate::net::cidr::Cidr;
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::interface::{find_interface_index, find_interface_network, get_interfaces};
+use crate::net::notifier::*;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::udp_receiver::UdpReceiver;
+use crate::net::udp_socket::UdpSocket;
+use crate::net::udp_socket_options::UdpSocketOptions;
+
+/// BroadcastServer receives and sends the IPv4 broadcast datagrams on an interface.
+/// The socket is bound to the unspecified address with SO_BROADCAST, since a socket bound to a unicast address does not receive the broadcast datagrams,
+/// and only the packets which came in on the interface are notified to the observers.
+/// On Linux, the socket is also bound to the device of the interface, so that the unicast datagrams are not received by the servers of the other interfaces.
+pub struct BroadcastServer {
+    socket: Arc<RwLock<UdpSocket>>,
+    notifier: Notifier,
+    receiver: UdpReceiver,
+    ifaddr: IpAddr,
+    if_index: u32,
+    network: Option<Cidr>,
+    socket_options: UdpSocketOptions,
+}
+
+impl Default for BroadcastServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BroadcastServer {
+    pub fn new() -> BroadcastServer {
+        BroadcastServer {
+            socket: Arc::new(RwLock::new(UdpSocket::new())),
+            notifier: notifier_new(),
+            receiver: UdpReceiver::new(),
+            ifaddr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            if_index: 0,
+            network: None,
+            socket_options: UdpSocketOptions::new(),
+        }
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        self.notifier.lock().unwrap().remove_observer(id)
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.notifier.lock().unwrap().set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> DispatchOptions {
+        *self.notifier.lock().unwrap().dispatch_options()
+    }
+
+    /// share_dispatch_options sets the dispatch options of the manager, and shares its worker threads instead of starting new ones.
+    pub(crate) fn share_dispatch_options(&mut self, manager: &NotifytManager) {
+        self.notifier
+            .lock()
+            .unwrap()
+            .share_dispatch_options(manager);
+    }
+
+    /// notify sends the packet to the broadcast address of the interface with the bound port.
+    pub fn notify(&self, msg: &Packet) -> Result<usize> {
+        let to_addr = self.broadcast_addr()?;
+        info!("BCST {} -> {} ({})", self.ifaddr()?, to_addr, msg);
+        let ret = self.send_to(to_addr, msg.bytes());
+        if ret.is_err() {
+            warn!("Couldn't notify Packet to {}", to_addr);
+        }
+        ret
+    }
+
+    /// send sends the packet out of the interface to any address, such as the directed or the limited broadcast address with another port.
+    pub fn send(&self, to_addr: SocketAddr, msg: &Packet) -> Result<usize> {
+        info!("SEND {} -> {} ({})", self.ifaddr()?, to_addr, msg);
+        let ret = self.send_to(to_addr, msg.bytes());
+        if ret.is_err() {
+            warn!("Couldn't send Packet to {}", to_addr);
+        }
+        ret
+    }
+
+    /// reply sends the bytes back to the sender of the received packet, out of the interface the packet came in on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        let to_addr = msg.from();
+        info!(
+            "SEND {} -> {} ({})",
+            self.ifaddr()?,
+            to_addr,
+            hex::encode_upper(bytes),
+        );
+        let ret = self.send_to(to_addr, bytes);
+        if ret.is_err() {
+            warn!("Couldn't send Packet to {}", to_addr);
+        }
+        ret
+    }
+
+    /// send_to sends the bytes out of the bound interface, since the socket bound to the unspecified address would send the limited broadcast datagrams out of the default route.
+    fn send_to(&self, to_addr: SocketAddr, bytes: &[u8]) -> Result<usize> {
+        self.socket
+            .read()
+            .unwrap()
+            .send_to_interface(bytes, to_addr, self.if_index)
+    }
+
+    /// broadcast_addr returns the directed broadcast address of the interface with the bound port,
+    /// or the limited broadcast address 255.255.255.255 if the interface network is unknown or has no broadcast address.
+    pub fn broadcast_addr(&self) -> Result<SocketAddr> {
+        let port = self.socket.read().unwrap().addr()?.port();
+        let addr = self
+            .network
+            .and_then(|network| network.broadcast())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::BROADCAST));
+        Ok(SocketAddr::new(addr, port))
+    }
+
+    /// set_socket_options sets the socket options, which are applied with SO_BROADCAST when the socket is bound.
+    pub fn set_socket_options(&mut self, options: UdpSocketOptions) {
+        self.socket_options = options;
+    }
+
+    pub fn socket_options(&self) -> &UdpSocketOptions {
+        &self.socket_options
+    }
+
+    /// set_recv_buffer_size sets the size of the buffer which received datagrams are read into. Datagrams larger than it are notified as truncated Packets.
+    pub fn set_recv_buffer_size(&mut self, size: usize) {
+        self.receiver.set_buffer_size(size);
+    }
+
+    pub fn recv_buffer_size(&self) -> usize {
+        self.receiver.buffer_size()
+    }
+
+    /// interface_index returns the index of the bound interface, or 0 if it is unknown.
+    pub fn interface_index(&self) -> u32 {
+        self.if_index
+    }
+
+    /// network returns the address and the prefix length of the bound interface, or None if it is unknown.
+    pub fn network(&self) -> Option<Cidr> {
+        self.network
+    }
+
+    /// ifaddr returns the address of the interface with the bound port.
+    pub fn ifaddr(&self) -> Result<SocketAddr> {
+        let port = self.socket.read().unwrap().addr()?.port();
+        Ok(SocketAddr::new(self.ifaddr, port))
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.socket.read().unwrap().addr().is_ok()
+    }
+
+    /// bind binds a new socket with SO_BROADCAST to the port for the IPv4 interface,
+    /// or returns Error::AddressFamilyMismatch for an IPv6 interface which has no broadcast.
+    pub fn bind(&mut self, ifaddr: IpAddr, port: u16) -> Result<()> {
+        if !ifaddr.is_ipv4() {
+            error!("BIND BCT {}:{}", ifaddr, port);
+            return Err(Error::AddressFamilyMismatch {
+                group: IpAddr::V4(Ipv4Addr::BROADCAST),
+                iface: ifaddr,
+            });
+        }
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
+        debug!("BIND BCT {}", addr);
+        let ifaces = get_interfaces();
+        let options = self.socket_options.clone().broadcast(true);
+        // NOTE: The sockets of the other interfaces are bound to the same port with SO_REUSEPORT, and Linux would spread the unicast datagrams among them,
+        // so the socket is bound to the device of the interface before bind().
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let device_options = ifaces
+            .iter()
+            .find(|iface| iface.addresses().iter().any(|cidr| cidr.addr() == ifaddr))
+            .map(|iface| options.clone().bind_to_device(iface.name()));
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let device_options: Option<UdpSocketOptions> = None;
+        let mut socket = self.socket.write().unwrap();
+        match device_options {
+            Some(device_options) => match socket.bind_with_options(addr, &device_options) {
+                // NOTE: SO_BINDTODEVICE requires CAP_NET_RAW before Linux 5.7, so the socket is bound without it,
+                // and the packets of the other interfaces are still dropped by the interface index of the receiver.
+                Err(e) if is_bind_to_device_denied(&e) => {
+                    warn!("BIND BCT {} without SO_BINDTODEVICE ({})", ifaddr, e);
+                    socket.bind_with_options(addr, &options)?;
+                }
+                ret => ret?,
+            },
+            None => socket.bind_with_options(addr, &options)?,
+        }
+        drop(socket);
+        self.ifaddr = ifaddr;
+        self.if_index = find_interface_index(&ifaces, ifaddr).unwrap_or(0);
+        self.network = find_interface_network(&ifaces, ifaddr);
+        self.receiver.set_interface_index(self.if_index);
+        self.receiver
+            .set_broadcast_addr(self.network.and_then(|network| network.broadcast()));
+        debug!("BIND BCT {}:{} ({:?})", ifaddr, port, self.network);
+        Ok(())
+    }
+
+    pub fn close(&self) -> Result<()> {
+        self.socket.write().unwrap().close();
+        Ok(())
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        self.receiver
+            .start(self.socket.clone(), self.notifier.clone())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        self.receiver.stop();
+        self.close()
+    }
+}
+
+/// is_bind_to_device_denied returns true if the socket could not be bound because SO_BINDTODEVICE was not permitted.
+pub(crate) fn is_bind_to_device_denied(e: &Error) -> bool {
+    matches!(e, Error::Bind { source, .. } if source.raw_os_error() == Some(nix::libc::EPERM))
+}
+
+impl ObserverRegistry for BroadcastServer {
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        self.notifier.lock().unwrap().insert_observer(id, observer)
+    }
+}
+
+impl Drop for BroadcastServer {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
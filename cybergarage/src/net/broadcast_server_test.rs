@@ -0,0 +1,105 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use nix::libc;
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time;
+
+    use crate::log::Logger;
+    use crate::net::broadcast_server::*;
+    use crate::net::default_test::*;
+    use crate::net::error::Error;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+
+    use crate::net::notify_manager_test::*;
+
+    #[test]
+    fn broadcast_server() {
+        Logger::init();
+
+        let ifaddr = match find_broadcast_interface() {
+            Some(ifaddr) => ifaddr,
+            None => return,
+        };
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut server = BroadcastServer::new();
+        let observer = TestPacketRecorder::new(packets.clone());
+        server.add_observer(Arc::new(Mutex::new(observer)));
+        let ret = server.bind(ifaddr, TEST_BROADCAST_PORT);
+        assert!(ret.is_ok(), "{:?}", ret);
+        assert!(server.start().is_ok());
+        assert_eq!(server.ifaddr().unwrap().ip(), ifaddr);
+        assert!(server.interface_index() != 0);
+
+        let network = server.network().unwrap();
+        let broadcast_addr = server.broadcast_addr().unwrap();
+        assert_eq!(Some(broadcast_addr.ip()), network.broadcast());
+        assert_eq!(broadcast_addr.port(), TEST_BROADCAST_PORT);
+
+        // The broadcast datagrams are looped back to the local sockets.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        let ret = server.notify(&pkt);
+        assert!(ret.is_ok(), "{:?}", ret);
+        let limited_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), TEST_BROADCAST_PORT);
+        let ret = server.send(limited_addr, &pkt);
+        assert!(ret.is_ok(), "{:?}", ret);
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(server.stop().is_ok());
+
+        let packets = packets.lock().unwrap();
+        for to_addr in [broadcast_addr, limited_addr] {
+            let pkt = packets.iter().find(|pkt| pkt.to() == to_addr);
+            assert!(pkt.is_some(), "{}", to_addr);
+            let pkt = pkt.unwrap();
+            assert!(pkt.is_broadcast());
+            assert_eq!(pkt.interface_index(), server.interface_index());
+        }
+    }
+
+    #[test]
+    fn broadcast_server_bind_v6() {
+        let mut server = BroadcastServer::new();
+        let ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert!(matches!(
+            server.bind(ifaddr, TEST_BROADCAST_PORT),
+            Err(Error::AddressFamilyMismatch { iface, .. }) if iface == ifaddr
+        ));
+        assert!(!server.is_bound());
+        assert!(matches!(
+            server.notify(&Packet::new()),
+            Err(Error::NotBound)
+        ));
+    }
+
+    #[test]
+    fn broadcast_server_bind_to_device_denied() {
+        let bind_err = |errno| Error::Bind {
+            addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), TEST_BROADCAST_PORT),
+            source: io::Error::from_raw_os_error(errno),
+        };
+        // Only EPERM of SO_BINDTODEVICE falls back, and EACCES of a privileged port still fails the bind.
+        assert!(is_bind_to_device_denied(&bind_err(libc::EPERM)));
+        assert!(!is_bind_to_device_denied(&bind_err(libc::EACCES)));
+        assert!(!is_bind_to_device_denied(&bind_err(libc::EADDRINUSE)));
+        assert!(!is_bind_to_device_denied(&Error::NotBound));
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+
+use crate::net::error::{Error, Result};
+
+/// Cidr represents an IP address with a prefix length, such as 192.168.0.1/24 or fe80::1/64.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Cidr> {
+        let max_prefix_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if max_prefix_len < prefix_len {
+            return Err(Error::InvalidCidr(format!("{}/{}", addr, prefix_len)));
+        }
+        Ok(Cidr { addr, prefix_len })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// contains returns true if the specified address belongs to the network of the CIDR.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.addr, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                (u32::from(net) & mask) == (u32::from(*addr) & mask)
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                (u128::from(net) & mask) == (u128::from(*addr) & mask)
+            }
+            _ => false,
+        }
+    }
+
+    /// broadcast returns the directed broadcast address of the IPv4 network, or None for IPv6 and for the /31 and /32 networks which have no broadcast address.
+    pub fn broadcast(&self) -> Option<IpAddr> {
+        match self.addr {
+            IpAddr::V4(addr) if self.prefix_len < 31 => {
+                let host_mask = u32::MAX >> self.prefix_len;
+                Some(IpAddr::V4(Ipv4Addr::from(u32::from(addr) | host_mask)))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = Error;
+
+    /// from_str parses a CIDR string. An address without a prefix length is parsed as a host address.
+    fn from_str(s: &str) -> Result<Cidr> {
+        let invalid = || Error::InvalidCidr(s.to_string());
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse().map_err(|_| invalid())?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        Cidr::new(addr, prefix_len).map_err(|_| invalid())
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
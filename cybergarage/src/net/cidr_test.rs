@@ -0,0 +1,74 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::IpAddr;
+
+    use crate::net::cidr::*;
+
+    #[test]
+    fn cidr_parse() {
+        let cidr: Cidr = "192.0.2.2/24".parse().unwrap();
+        assert_eq!(cidr.addr(), "192.0.2.2".parse::<IpAddr>().unwrap());
+        assert_eq!(cidr.prefix_len(), 24);
+        assert_eq!(cidr.to_string(), "192.0.2.2/24");
+
+        let cidr: Cidr = "fe80::1".parse().unwrap();
+        assert_eq!(cidr.prefix_len(), 128);
+
+        for cidr in [
+            "192.0.2.2/33",
+            "fe80::1/129",
+            "192.0.2/24",
+            "192.0.2.2/",
+            "",
+        ] {
+            assert!(cidr.parse::<Cidr>().is_err(), "{}", cidr);
+        }
+    }
+
+    #[test]
+    fn cidr_contains() {
+        let cidr: Cidr = "192.0.2.0/24".parse().unwrap();
+        assert!(cidr.contains(&"192.0.2.1".parse().unwrap()));
+        assert!(cidr.contains(&"192.0.2.255".parse().unwrap()));
+        assert!(!cidr.contains(&"192.0.3.1".parse().unwrap()));
+        assert!(!cidr.contains(&"::ffff:192.0.2.1".parse().unwrap()));
+
+        let cidr: Cidr = "fe80::/10".parse().unwrap();
+        assert!(cidr.contains(&"fe80::1".parse().unwrap()));
+        assert!(!cidr.contains(&"fd00::1".parse().unwrap()));
+
+        let cidr: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(cidr.contains(&"198.51.100.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn cidr_broadcast() {
+        for (cidr, broadcast) in [
+            ("192.0.2.2/24", Some("192.0.2.255")),
+            ("10.1.2.3/8", Some("10.255.255.255")),
+            ("0.0.0.0/0", Some("255.255.255.255")),
+            ("192.0.2.2/31", None),
+            ("192.0.2.2/32", None),
+            ("fd00::2/64", None),
+        ] {
+            let cidr: Cidr = cidr.parse().unwrap();
+            let broadcast = broadcast.map(|addr| addr.parse::<IpAddr>().unwrap());
+            assert_eq!(cidr.broadcast(), broadcast, "{}", cidr);
+        }
+    }
+}
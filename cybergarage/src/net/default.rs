@@ -12,4 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub const MAX_PACKET_SIZE: usize = 1024;
+use std::time::Duration;
+
+/// MAX_PACKET_SIZE is the default receive buffer size, which is large enough for any UDP datagram.
+pub const MAX_PACKET_SIZE: usize = 65535;
+
+/// MAX_FRAME_SIZE is the default limit of the message length of the TCP framers, so that a peer can not make the connection buffer grow without bound.
+pub const MAX_FRAME_SIZE: usize = MAX_PACKET_SIZE;
+
+/// RECV_TIMEOUT is the interval at which the receive threads check whether they have been stopped.
+pub const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// INTERFACE_POLLING_INTERVAL is the default interval at which InterfaceWatcher rescans the interfaces without rtnetlink.
+pub const INTERFACE_POLLING_INTERVAL: Duration = Duration::from_secs(5);
@@ -16,10 +16,28 @@
 
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
+use crate::net::interface::get_interfaces;
+
 pub const TEST_MULTICAST_V4_ADDRESS: IpAddr = std::net::IpAddr::V4(Ipv4Addr::new(224, 0, 23, 0));
 pub const TEST_MULTICAST_V6_ADDRESS: IpAddr =
-    std::net::IpAddr::V6(Ipv6Addr::new(0xff, 0x02, 0, 0, 0, 0, 0, 1));
+    std::net::IpAddr::V6(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1));
+pub const TEST_MULTICAST_V4_ADDRESS2: IpAddr =
+    std::net::IpAddr::V4(Ipv4Addr::new(239, 255, 255, 1));
+pub const TEST_MULTICAST_V6_ADDRESS2: IpAddr =
+    std::net::IpAddr::V6(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0x114));
 pub const ECHONET_PORT: u16 = 3610;
 pub const MDNST_PORT: u16 = 5353;
 pub const TEST_PORT: u16 = MDNST_PORT;
 pub const TEST_PORTS: [u16; 2] = [ECHONET_PORT, MDNST_PORT];
+// NOTE: The broadcast tests use their own port, since the sockets of the other tests bound to the unspecified address would receive the broadcast datagrams.
+pub const TEST_BROADCAST_PORT: u16 = 40009;
+
+/// find_broadcast_interface returns an IPv4 address of an interface which supports broadcast, or None if there is no such interface.
+pub fn find_broadcast_interface() -> Option<IpAddr> {
+    get_interfaces()
+        .iter()
+        .filter(|iface| iface.is_up() && iface.is_broadcast())
+        .flat_map(|iface| iface.addresses().iter())
+        .find(|cidr| cidr.broadcast().is_some())
+        .map(|cidr| cidr.addr())
+}
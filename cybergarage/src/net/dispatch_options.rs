@@ -0,0 +1,64 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// DispatchMode selects the thread which the observers are called on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DispatchMode {
+    /// Inline calls the observers one after another on the receive thread.
+    #[default]
+    Inline,
+    /// Pool calls each observer as a job of the worker threads, so that a slow observer does not delay the others.
+    /// The packets may be notified to an observer out of the received order.
+    Pool(usize),
+}
+
+/// DispatchOptions holds how the received packets are dispatched to the observers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DispatchOptions {
+    mode: DispatchMode,
+    remove_failing: bool,
+}
+
+impl DispatchOptions {
+    pub fn new() -> DispatchOptions {
+        DispatchOptions {
+            mode: DispatchMode::Inline,
+            remove_failing: false,
+        }
+    }
+
+    /// pool returns the options which dispatch with the specified number of worker threads.
+    pub fn pool(workers: usize) -> DispatchOptions {
+        DispatchOptions::new().mode(DispatchMode::Pool(workers))
+    }
+
+    pub fn mode(mut self, mode: DispatchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// remove_failing removes the observers which panic, instead of only logging the panics.
+    pub fn remove_failing(mut self, on: bool) -> Self {
+        self.remove_failing = on;
+        self
+    }
+
+    pub fn dispatch_mode(&self) -> DispatchMode {
+        self.mode
+    }
+
+    pub fn removes_failing(&self) -> bool {
+        self.remove_failing
+    }
+}
@@ -11,57 +11,122 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-pub struct ScoketError {}
-
-impl ScoketError {
-    pub fn new(msg: &str) -> std::io::Error {
-        std::io::Error::new(std::io::ErrorKind::Other, msg)
-    }
-}
-
-pub struct BindError {}
-
-impl BindError {
-    pub fn new() -> std::io::Error {
-        ScoketError::new("socket is not bound")
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct Error {
-    pub msg: String,
+/// Error represents an error of the net module.
+#[derive(Debug)]
+pub enum Error {
+    /// The socket is not bound yet, or has already been closed.
+    NotBound,
+    /// The socket could not be bound to the address.
+    Bind { addr: SocketAddr, source: io::Error },
+    /// The socket could not join the multicast group on the interface.
+    JoinMulticast {
+        group: IpAddr,
+        iface: IpAddr,
+        source: io::Error,
+    },
+    /// The socket could not leave the multicast group on the interface.
+    LeaveMulticast {
+        group: IpAddr,
+        iface: IpAddr,
+        source: io::Error,
+    },
+    /// The TCP connection to the address could not be established.
+    Connect { to: SocketAddr, source: io::Error },
+    /// The datagram could not be sent to the address.
+    Send { to: SocketAddr, source: io::Error },
+    /// The datagram could not be received.
+    Recv { source: io::Error },
+    /// The multicast group and the interface address belong to different address families.
+    AddressFamilyMismatch { group: IpAddr, iface: IpAddr },
+    /// No bound socket can send to the address.
+    NoRoute { to: SocketAddr },
+    /// No available network interface was found.
+    NoInterfaces,
+    /// The string is not a valid CIDR notation.
+    InvalidCidr(String),
+    /// Any other socket error.
+    Io(io::Error),
 }
 
 impl Error {
-    /// from_str creates a new Error with the specified string.
-    pub fn from_str(str: &str) -> Error {
-        Error {
-            msg: str.to_string(),
+    /// kind returns the io::ErrorKind of the underlying error.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::NotBound => io::ErrorKind::NotConnected,
+            Error::AddressFamilyMismatch { .. } | Error::InvalidCidr(_) => {
+                io::ErrorKind::InvalidInput
+            }
+            Error::NoRoute { .. } => io::ErrorKind::NetworkUnreachable,
+            Error::NoInterfaces => io::ErrorKind::NotFound,
+            Error::Bind { source, .. }
+            | Error::Connect { source, .. }
+            | Error::JoinMulticast { source, .. }
+            | Error::LeaveMulticast { source, .. }
+            | Error::Send { source, .. }
+            | Error::Recv { source }
+            | Error::Io(source) => source.kind(),
         }
     }
+}
 
-    /// from_string creates a new Error with the specified string.
-    pub fn from_string(str: &String) -> Error {
-        Error { msg: str.clone() }
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotBound => write!(f, "socket is not bound"),
+            Error::Bind { addr, source } => write!(f, "could not bind to {} ({})", addr, source),
+            Error::JoinMulticast {
+                group,
+                iface,
+                source,
+            } => write!(f, "could not join {} on {} ({})", group, iface, source),
+            Error::LeaveMulticast {
+                group,
+                iface,
+                source,
+            } => write!(f, "could not leave {} on {} ({})", group, iface, source),
+            Error::Connect { to, source } => write!(f, "could not connect to {} ({})", to, source),
+            Error::Send { to, source } => write!(f, "could not send to {} ({})", to, source),
+            Error::Recv { source } => write!(f, "could not receive ({})", source),
+            Error::AddressFamilyMismatch { group, iface } => {
+                write!(f, "address family mismatch between {} and {}", group, iface)
+            }
+            Error::NoRoute { to } => write!(f, "no socket to send to {}", to),
+            Error::NoInterfaces => write!(f, "no available interfaces"),
+            Error::InvalidCidr(cidr) => write!(f, "invalid CIDR {}", cidr),
+            Error::Io(source) => write!(f, "{}", source),
+        }
     }
+}
 
-    /// message returns the error message.
-    pub fn message(&self) -> &str {
-        &self.msg
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Bind { source, .. }
+            | Error::Connect { source, .. }
+            | Error::JoinMulticast { source, .. }
+            | Error::LeaveMulticast { source, .. }
+            | Error::Send { source, .. }
+            | Error::Recv { source }
+            | Error::Io(source) => Some(source),
+            _ => None,
+        }
     }
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.msg)
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
     }
 }
 
-impl std::error::Error for Error {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        io::Error::new(e.kind(), e)
     }
 }
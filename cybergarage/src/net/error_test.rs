@@ -0,0 +1,82 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    use crate::net::default_test::*;
+    use crate::net::error::*;
+    use crate::net::multicast_server::MulticastServer;
+    use crate::net::udp_socket::UdpSocket;
+
+    #[test]
+    fn not_bound_error() {
+        let sock = UdpSocket::new();
+        let to_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), TEST_PORT);
+        let ret = sock.send_to(&[0_u8; 1], to_addr);
+        assert!(matches!(ret, Err(Error::NotBound)));
+        let err = ret.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn bind_error() {
+        // 198.51.100.1 (TEST-NET-2) is never assigned to a local interface.
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)), TEST_PORT);
+        let mut sock = UdpSocket::new();
+        match sock.bind(addr) {
+            Err(Error::Bind {
+                addr: err_addr,
+                source,
+            }) => {
+                assert_eq!(err_addr, addr);
+                assert_eq!(source.kind(), io::ErrorKind::AddrNotAvailable);
+            }
+            ret => panic!("{:?}", ret),
+        }
+    }
+
+    #[test]
+    fn address_family_mismatch_error() {
+        let mut server = MulticastServer::new();
+        let ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        match server.bind(TEST_MULTICAST_V4_ADDRESS, TEST_PORT, ifaddr) {
+            Err(Error::AddressFamilyMismatch { group, iface }) => {
+                assert_eq!(group, TEST_MULTICAST_V4_ADDRESS);
+                assert_eq!(iface, ifaddr);
+            }
+            ret => panic!("{:?}", ret),
+        }
+        assert!(!server.is_bound());
+    }
+
+    #[test]
+    fn error_source() {
+        let to = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), TEST_PORT);
+        let err = Error::Send {
+            to,
+            source: io::Error::from(io::ErrorKind::PermissionDenied),
+        };
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        let source = err.source().unwrap().downcast_ref::<io::Error>();
+        assert_eq!(source.unwrap().kind(), io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains(&to.to_string()));
+
+        let io_err: io::Error = err.into();
+        assert_eq!(io_err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
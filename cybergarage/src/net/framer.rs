@@ -0,0 +1,210 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io;
+use std::sync::Arc;
+
+use crate::net::default::MAX_FRAME_SIZE;
+use crate::net::error::{Error, Result};
+
+/// Framer splits a TCP stream into messages, and wraps a message into the bytes to write to the stream.
+/// Framers have no state of their own, so that a framer can be shared by all connections.
+pub trait Framer {
+    /// decode removes the first complete frame from the buffer and returns its message, or None if the buffer does not hold a complete frame yet.
+    fn decode(&self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>>;
+    /// encode returns the frame which carries the message.
+    fn encode(&self, msg: &[u8]) -> Result<Vec<u8>>;
+}
+
+pub type FramerObject = Arc<dyn Framer + Send + Sync>;
+
+fn invalid_data(msg: String) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+/// LengthPrefixedFramer frames each message with a big-endian length header of 1, 2 or 4 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LengthPrefixedFramer {
+    header_len: usize,
+    max_len: usize,
+}
+
+impl LengthPrefixedFramer {
+    /// new returns a framer with the header length of 1, 2 or 4 bytes. The other lengths are treated as 4 bytes.
+    /// The length of the messages is limited to MAX_FRAME_SIZE unless max_len() is specified.
+    pub fn new(header_len: usize) -> LengthPrefixedFramer {
+        let header_len = match header_len {
+            1 | 2 => header_len,
+            _ => 4,
+        };
+        let framer = LengthPrefixedFramer {
+            header_len,
+            max_len: 0,
+        };
+        framer.max_len(MAX_FRAME_SIZE)
+    }
+
+    /// max_len limits the length of the messages, so that a broken header can not make the connection buffer grow without bound.
+    /// The limit is clamped to the largest length which the header can carry.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        let header_max_len = match self.header_len {
+            1 => u8::MAX as usize,
+            2 => u16::MAX as usize,
+            _ => u32::MAX as usize,
+        };
+        self.max_len = max_len.min(header_max_len);
+        self
+    }
+
+    pub fn header_len(&self) -> usize {
+        self.header_len
+    }
+}
+
+impl Framer for LengthPrefixedFramer {
+    fn decode(&self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+        if buf.len() < self.header_len {
+            return Ok(None);
+        }
+        let len = buf[..self.header_len]
+            .iter()
+            .fold(0_usize, |len, b| (len << 8) | *b as usize);
+        if self.max_len < len {
+            return Err(invalid_data(format!(
+                "frame length {} exceeds {}",
+                len, self.max_len
+            )));
+        }
+        if buf.len() < self.header_len + len {
+            return Ok(None);
+        }
+        let frame: Vec<u8> = buf.drain(..self.header_len + len).collect();
+        Ok(Some(frame[self.header_len..].to_vec()))
+    }
+
+    fn encode(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        if self.max_len < msg.len() {
+            return Err(invalid_data(format!(
+                "message length {} exceeds {}",
+                msg.len(),
+                self.max_len
+            )));
+        }
+        let len = (msg.len() as u32).to_be_bytes();
+        let mut frame = len[4 - self.header_len..].to_vec();
+        frame.extend_from_slice(msg);
+        Ok(frame)
+    }
+}
+
+/// DelimiterFramer splits the messages at a delimiter, such as b"\r\n", which is not included in the decoded messages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DelimiterFramer {
+    delimiter: Vec<u8>,
+    max_len: usize,
+}
+
+impl DelimiterFramer {
+    /// new returns a framer with the delimiter. The length of the messages is limited to MAX_FRAME_SIZE unless max_len() is specified.
+    pub fn new(delimiter: &[u8]) -> DelimiterFramer {
+        DelimiterFramer {
+            delimiter: delimiter.to_vec(),
+            max_len: MAX_FRAME_SIZE,
+        }
+    }
+
+    /// max_len limits the length of the messages, so that a stream without the delimiter can not make the connection buffer grow without bound.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn delimiter(&self) -> &[u8] {
+        &self.delimiter
+    }
+}
+
+impl Framer for DelimiterFramer {
+    fn decode(&self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+        if self.delimiter.is_empty() {
+            return Err(invalid_data("empty delimiter".to_string()));
+        }
+        let pos = buf
+            .windows(self.delimiter.len())
+            .position(|window| window == self.delimiter.as_slice());
+        match pos {
+            Some(pos) if pos <= self.max_len => {
+                let frame: Vec<u8> = buf.drain(..pos + self.delimiter.len()).collect();
+                Ok(Some(frame[..pos].to_vec()))
+            }
+            None if buf.len() < self.max_len.saturating_add(self.delimiter.len()) => Ok(None),
+            _ => Err(invalid_data(format!(
+                "no delimiter within {} bytes",
+                self.max_len
+            ))),
+        }
+    }
+
+    fn encode(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        if self.max_len < msg.len() {
+            return Err(invalid_data(format!(
+                "message length {} exceeds {}",
+                msg.len(),
+                self.max_len
+            )));
+        }
+        let mut frame = msg.to_vec();
+        frame.extend_from_slice(&self.delimiter);
+        Ok(frame)
+    }
+}
+
+/// FixedSizeFramer splits the stream into the messages of a fixed size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedSizeFramer {
+    size: usize,
+}
+
+impl FixedSizeFramer {
+    pub fn new(size: usize) -> FixedSizeFramer {
+        FixedSizeFramer { size }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Framer for FixedSizeFramer {
+    fn decode(&self, buf: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+        if self.size == 0 {
+            return Err(invalid_data("zero frame size".to_string()));
+        }
+        if buf.len() < self.size {
+            return Ok(None);
+        }
+        Ok(Some(buf.drain(..self.size).collect()))
+    }
+
+    fn encode(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        if msg.len() != self.size {
+            return Err(invalid_data(format!(
+                "message length {} is not {}",
+                msg.len(),
+                self.size
+            )));
+        }
+        Ok(msg.to_vec())
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use crate::net::default::MAX_FRAME_SIZE;
+    use crate::net::framer::*;
+
+    #[test]
+    fn length_prefixed_framer() {
+        for header_len in [1, 2, 4] {
+            let framer = LengthPrefixedFramer::new(header_len);
+            let frame = framer.encode(b"abc").unwrap();
+            assert_eq!(frame.len(), header_len + 3);
+            assert_eq!(frame[header_len - 1], 3);
+
+            // A frame split across reads is decoded when it is complete, and the rest is left in the buffer.
+            let mut buf = frame[..header_len + 1].to_vec();
+            assert_eq!(framer.decode(&mut buf).unwrap(), None);
+            buf.extend_from_slice(&frame[header_len + 1..]);
+            buf.extend_from_slice(&frame);
+            assert_eq!(framer.decode(&mut buf).unwrap(), Some(b"abc".to_vec()));
+            assert_eq!(framer.decode(&mut buf).unwrap(), Some(b"abc".to_vec()));
+            assert!(buf.is_empty());
+        }
+
+        let framer = LengthPrefixedFramer::new(1);
+        assert!(framer.encode(&[0_u8; 256]).is_err());
+        let framer = LengthPrefixedFramer::new(2).max_len(4);
+        assert!(framer.encode(b"abcde").is_err());
+        assert!(framer.decode(&mut vec![0, 5]).is_err());
+        assert_eq!(LengthPrefixedFramer::new(3).header_len(), 4);
+
+        // The header of the largest length is rejected unless a larger limit is specified.
+        let mut buf = vec![0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(LengthPrefixedFramer::new(4).decode(&mut buf).is_err());
+        let framer = LengthPrefixedFramer::new(4).max_len(usize::MAX);
+        assert_eq!(framer.decode(&mut buf).unwrap(), None);
+        let framer = LengthPrefixedFramer::new(4).max_len(MAX_FRAME_SIZE + 1);
+        assert!(framer.encode(&vec![0_u8; MAX_FRAME_SIZE + 1]).is_ok());
+        assert!(LengthPrefixedFramer::new(4)
+            .encode(&vec![0_u8; MAX_FRAME_SIZE + 1])
+            .is_err());
+    }
+
+    #[test]
+    fn delimiter_framer() {
+        let framer = DelimiterFramer::new(b"\r\n");
+        assert_eq!(framer.encode(b"abc").unwrap(), b"abc\r\n".to_vec());
+
+        let mut buf = b"abc\r".to_vec();
+        assert_eq!(framer.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(b"\n\r\nde");
+        assert_eq!(framer.decode(&mut buf).unwrap(), Some(b"abc".to_vec()));
+        assert_eq!(framer.decode(&mut buf).unwrap(), Some(Vec::new()));
+        assert_eq!(framer.decode(&mut buf).unwrap(), None);
+        assert_eq!(buf, b"de".to_vec());
+
+        let framer = DelimiterFramer::new(b"\n").max_len(3);
+        assert_eq!(
+            framer.decode(&mut b"abc\n".to_vec()).unwrap(),
+            Some(b"abc".to_vec())
+        );
+        assert_eq!(framer.decode(&mut b"abc".to_vec()).unwrap(), None);
+        assert!(framer.decode(&mut b"abcd".to_vec()).is_err());
+        assert!(framer.encode(b"abcd").is_err());
+        assert!(DelimiterFramer::new(b"")
+            .decode(&mut b"abc".to_vec())
+            .is_err());
+
+        // A stream without the delimiter is rejected once it exceeds the default limit.
+        let framer = DelimiterFramer::new(b"\n");
+        assert_eq!(
+            framer.decode(&mut vec![0_u8; MAX_FRAME_SIZE]).unwrap(),
+            None
+        );
+        assert!(framer.decode(&mut vec![0_u8; MAX_FRAME_SIZE + 1]).is_err());
+    }
+
+    #[test]
+    fn fixed_size_framer() {
+        let framer = FixedSizeFramer::new(2);
+        let mut buf = b"abc".to_vec();
+        assert_eq!(framer.decode(&mut buf).unwrap(), Some(b"ab".to_vec()));
+        assert_eq!(framer.decode(&mut buf).unwrap(), None);
+        assert_eq!(framer.encode(b"ab").unwrap(), b"ab".to_vec());
+        assert!(framer.encode(b"abc").is_err());
+        assert!(FixedSizeFramer::new(0).decode(&mut buf).is_err());
+    }
+}
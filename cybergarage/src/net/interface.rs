@@ -14,66 +14,406 @@
 
 #![allow(dead_code)]
 
-use pnet::datalink;
-use pnet::ipnetwork;
+use nix::libc;
+use pnet::datalink::{self, NetworkInterface};
+use std::fs;
 use std::net::IpAddr;
+use std::path::Path;
 
-type EnableInterface = fn(ipnetwork::IpNetwork) -> bool;
+use crate::net::cidr::Cidr;
 
-fn is_ignore_interface(ipnet: ipnetwork::IpNetwork) -> bool {
-    let binding = ipnet.to_string();
-    let ifaddr = binding.as_str();
-    match ifaddr {
-        "172.17.0.1/16" => return true, // Docker default gateway
-        _ => return false,
-    }
+const IFF_UP: u32 = libc::IFF_UP as u32;
+const IFF_BROADCAST: u32 = libc::IFF_BROADCAST as u32;
+const IFF_LOOPBACK: u32 = libc::IFF_LOOPBACK as u32;
+const IFF_POINTOPOINT: u32 = libc::IFF_POINTOPOINT as u32;
+const IFF_MULTICAST: u32 = libc::IFF_MULTICAST as u32;
+
+/// CONTAINER_INTERFACE_NAMES are the name patterns of the interfaces created by container runtimes.
+const CONTAINER_INTERFACE_NAMES: [&str; 8] = [
+    "docker*", "br-*", "veth*", "cni*", "flannel*", "cali*", "podman*", "lxcbr*",
+];
+
+/// Interface represents a network interface of the host.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Interface {
+    name: String,
+    index: u32,
+    flags: u32,
+    mac: Option<[u8; 6]>,
+    addrs: Vec<Cidr>,
+    mtu: Option<u32>,
+    is_virtual: bool,
+    is_bridge: bool,
 }
 
-fn is_all_interface(_ipnet: ipnetwork::IpNetwork) -> bool {
-    true
+impl Interface {
+    pub fn new(name: &str, index: u32) -> Interface {
+        Interface {
+            name: name.to_string(),
+            index,
+            ..Default::default()
+        }
+    }
+
+    fn from_network_interface(iface: NetworkInterface) -> Interface {
+        let mut addrs = Vec::new();
+        for ipnet in iface.ips.iter() {
+            if let Ok(addr) = Cidr::new(ipnet.ip(), ipnet.prefix()) {
+                addrs.push(addr);
+            }
+        }
+        let sys_path = Path::new("/sys/class/net").join(&iface.name);
+        let mtu = fs::read_to_string(sys_path.join("mtu"))
+            .ok()
+            .and_then(|mtu| mtu.trim().parse().ok());
+        Interface {
+            is_virtual: Path::new("/sys/devices/virtual/net")
+                .join(&iface.name)
+                .exists(),
+            is_bridge: sys_path.join("bridge").exists(),
+            name: iface.name,
+            index: iface.index,
+            flags: iface.flags,
+            mac: iface.mac.map(|mac| mac.octets()),
+            addrs,
+            mtu,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn set_flags(&mut self, flags: u32) -> &mut Self {
+        self.flags = flags;
+        self
+    }
+
+    /// flags returns the IFF_* flags of the interface.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn set_mac(&mut self, mac: [u8; 6]) -> &mut Self {
+        self.mac = Some(mac);
+        self
+    }
+
+    pub fn mac(&self) -> Option<[u8; 6]> {
+        self.mac
+    }
+
+    pub fn add_address(&mut self, addr: Cidr) -> &mut Self {
+        self.addrs.push(addr);
+        self
+    }
+
+    /// addresses returns the addresses of the interface with their prefix lengths.
+    pub fn addresses(&self) -> &[Cidr] {
+        &self.addrs
+    }
+
+    pub fn set_mtu(&mut self, mtu: u32) -> &mut Self {
+        self.mtu = Some(mtu);
+        self
+    }
+
+    pub fn mtu(&self) -> Option<u32> {
+        self.mtu
+    }
+
+    pub fn set_virtual(&mut self, is_virtual: bool) -> &mut Self {
+        self.is_virtual = is_virtual;
+        self
+    }
+
+    /// is_virtual returns true if the interface is not backed by a physical device, such as a bridge, veth or tunnel.
+    pub fn is_virtual(&self) -> bool {
+        self.is_virtual
+    }
+
+    pub fn set_bridge(&mut self, is_bridge: bool) -> &mut Self {
+        self.is_bridge = is_bridge;
+        self
+    }
+
+    pub fn is_bridge(&self) -> bool {
+        self.is_bridge
+    }
+
+    /// is_container returns true if the interface name is one of the container runtime interfaces, such as docker0 or veth*.
+    pub fn is_container(&self) -> bool {
+        CONTAINER_INTERFACE_NAMES
+            .iter()
+            .any(|pattern| glob_match(pattern, &self.name))
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.flags & IFF_UP != 0
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        self.flags & IFF_BROADCAST != 0
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        self.flags & IFF_LOOPBACK != 0
+    }
+
+    pub fn is_point_to_point(&self) -> bool {
+        self.flags & IFF_POINTOPOINT != 0
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.flags & IFF_MULTICAST != 0
+    }
 }
 
-fn is_v4_interface(ipnet: ipnetwork::IpNetwork) -> bool {
-    ipnet.is_ipv4()
+/// InterfaceFilter selects the interfaces and addresses which the managers bind to.
+/// The default filter selects the IPv4 and IPv6 addresses of the interfaces which are up,
+/// except loopback, point-to-point and container interfaces.
+#[derive(Clone, Debug)]
+pub struct InterfaceFilter {
+    include_names: Vec<String>,
+    exclude_names: Vec<String>,
+    include_networks: Vec<Cidr>,
+    exclude_networks: Vec<Cidr>,
+    ipv4: bool,
+    ipv6: bool,
+    loopback: bool,
+    point_to_point: bool,
+    virtual_interfaces: bool,
+    bridges: bool,
+    containers: bool,
 }
 
-fn is_v6_interface(ipnet: ipnetwork::IpNetwork) -> bool {
-    ipnet.is_ipv6()
+impl Default for InterfaceFilter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-fn get_interfaces(enable_interface: EnableInterface) -> Vec<IpAddr> {
-    let mut ifaddrs = Vec::new();
-    for iface in datalink::interfaces() {
+impl InterfaceFilter {
+    pub fn new() -> InterfaceFilter {
+        InterfaceFilter {
+            include_names: Vec::new(),
+            exclude_names: Vec::new(),
+            include_networks: Vec::new(),
+            exclude_networks: Vec::new(),
+            ipv4: true,
+            ipv6: true,
+            loopback: false,
+            point_to_point: false,
+            virtual_interfaces: true,
+            bridges: true,
+            containers: false,
+        }
+    }
+
+    /// include_name selects only the interfaces whose names match one of the included glob patterns, such as "eth*".
+    pub fn include_name(mut self, pattern: &str) -> Self {
+        self.include_names.push(pattern.to_string());
+        self
+    }
+
+    /// exclude_name skips the interfaces whose names match the glob pattern, such as "tun*".
+    pub fn exclude_name(mut self, pattern: &str) -> Self {
+        self.exclude_names.push(pattern.to_string());
+        self
+    }
+
+    /// include_network selects only the addresses which belong to one of the included networks.
+    pub fn include_network(mut self, network: Cidr) -> Self {
+        self.include_networks.push(network);
+        self
+    }
+
+    /// exclude_network skips the addresses which belong to the network.
+    pub fn exclude_network(mut self, network: Cidr) -> Self {
+        self.exclude_networks.push(network);
+        self
+    }
+
+    pub fn ipv4(mut self, enabled: bool) -> Self {
+        self.ipv4 = enabled;
+        self
+    }
+
+    pub fn ipv6(mut self, enabled: bool) -> Self {
+        self.ipv6 = enabled;
+        self
+    }
+
+    pub fn loopback(mut self, enabled: bool) -> Self {
+        self.loopback = enabled;
+        self
+    }
+
+    pub fn point_to_point(mut self, enabled: bool) -> Self {
+        self.point_to_point = enabled;
+        self
+    }
+
+    pub fn virtual_interfaces(mut self, enabled: bool) -> Self {
+        self.virtual_interfaces = enabled;
+        self
+    }
+
+    pub fn bridges(mut self, enabled: bool) -> Self {
+        self.bridges = enabled;
+        self
+    }
+
+    pub fn containers(mut self, enabled: bool) -> Self {
+        self.containers = enabled;
+        self
+    }
+
+    /// is_match returns true if the interface itself is selected, regardless of its addresses.
+    pub fn is_match(&self, iface: &Interface) -> bool {
         if !iface.is_up() {
-            continue;
+            return false;
         }
-        if iface.is_loopback() || iface.is_point_to_point() {
-            continue;
+        if (!self.loopback && iface.is_loopback())
+            || (!self.point_to_point && iface.is_point_to_point())
+            || (!self.bridges && iface.is_bridge())
+            || (!self.containers && iface.is_container())
+        {
+            return false;
         }
-        if iface.ips.is_empty() {
-            continue;
+        // NOTE: The loopback interface is also virtual on Linux, so it is only controlled by loopback().
+        if !self.virtual_interfaces && iface.is_virtual() && !iface.is_loopback() {
+            return false;
         }
-        for ifaddr in iface.ips {
-            if is_ignore_interface(ifaddr) {
-                continue;
-            }
-            if !enable_interface(ifaddr) {
-                continue;
+        if !self.include_names.is_empty()
+            && !self
+                .include_names
+                .iter()
+                .any(|pattern| glob_match(pattern, iface.name()))
+        {
+            return false;
+        }
+        !self
+            .exclude_names
+            .iter()
+            .any(|pattern| glob_match(pattern, iface.name()))
+    }
+
+    /// is_address_match returns true if the address is selected.
+    pub fn is_address_match(&self, addr: &IpAddr) -> bool {
+        if (addr.is_ipv4() && !self.ipv4) || (addr.is_ipv6() && !self.ipv6) {
+            return false;
+        }
+        if !self.include_networks.is_empty()
+            && !self
+                .include_networks
+                .iter()
+                .any(|network| network.contains(addr))
+        {
+            return false;
+        }
+        !self
+            .exclude_networks
+            .iter()
+            .any(|network| network.contains(addr))
+    }
+
+    /// is_interface_address_match returns true if the address is selected, and it belongs to a selected interface of the specified interfaces.
+    pub fn is_interface_address_match(&self, ifaces: &[Interface], addr: &IpAddr) -> bool {
+        self.is_address_match(addr)
+            && ifaces.iter().any(|iface| {
+                self.is_match(iface)
+                    && iface
+                        .addresses()
+                        .iter()
+                        .any(|ifaddr| ifaddr.addr() == *addr)
+            })
+    }
+
+    /// addresses returns the selected addresses of the selected interfaces.
+    pub fn addresses(&self, ifaces: &[Interface]) -> Vec<IpAddr> {
+        let mut ifaddrs = Vec::new();
+        for iface in ifaces.iter().filter(|iface| self.is_match(iface)) {
+            for addr in iface.addresses() {
+                if self.is_address_match(&addr.addr()) {
+                    ifaddrs.push(addr.addr());
+                }
             }
-            ifaddrs.push(ifaddr.ip());
         }
+        ifaddrs
     }
-    ifaddrs
+}
+
+/// glob_match returns true if the name matches the pattern, where '*' matches any sequence and '?' matches any character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// get_interfaces returns all network interfaces of the host.
+pub fn get_interfaces() -> Vec<Interface> {
+    datalink::interfaces()
+        .into_iter()
+        .map(Interface::from_network_interface)
+        .collect()
+}
+
+/// find_interface_index returns the index of the interface which has the specified address.
+pub fn find_interface_index(ifaces: &[Interface], ifaddr: IpAddr) -> Option<u32> {
+    ifaces
+        .iter()
+        .find(|iface| iface.addresses().iter().any(|addr| addr.addr() == ifaddr))
+        .map(|iface| iface.index())
+}
+
+/// find_interface_network returns the interface address, with its prefix length, which matches the specified address.
+pub fn find_interface_network(ifaces: &[Interface], ifaddr: IpAddr) -> Option<Cidr> {
+    ifaces
+        .iter()
+        .flat_map(|iface| iface.addresses().iter())
+        .find(|addr| addr.addr() == ifaddr)
+        .copied()
+}
+
+/// get_interface_index returns the index of the interface which has the specified address.
+pub fn get_interface_index(ifaddr: IpAddr) -> Option<u32> {
+    find_interface_index(&get_interfaces(), ifaddr)
 }
 
 pub fn get_all_interfaces() -> Vec<IpAddr> {
-    get_interfaces(is_v4_interface)
+    InterfaceFilter::new().addresses(&get_interfaces())
 }
 
 pub fn get_v4_interfaces() -> Vec<IpAddr> {
-    get_interfaces(is_v4_interface)
+    InterfaceFilter::new()
+        .ipv6(false)
+        .addresses(&get_interfaces())
 }
 
 pub fn get_v6_interfaces() -> Vec<IpAddr> {
-    get_interfaces(is_v6_interface)
+    InterfaceFilter::new()
+        .ipv4(false)
+        .addresses(&get_interfaces())
 }
@@ -0,0 +1,46 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::net::interface::Interface;
+
+/// InterfaceEvent represents a change of the network interfaces detected by InterfaceWatcher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InterfaceEvent {
+    /// The interface has appeared.
+    Added(Interface),
+    /// The interface has disappeared.
+    Removed(Interface),
+    /// The flags, addresses or MTU of the interface have changed.
+    Changed(Interface),
+}
+
+impl InterfaceEvent {
+    pub fn interface(&self) -> &Interface {
+        match self {
+            InterfaceEvent::Added(iface)
+            | InterfaceEvent::Removed(iface)
+            | InterfaceEvent::Changed(iface) => iface,
+        }
+    }
+}
+
+/// InterfaceObserver receives the changes of the network interfaces from InterfaceWatcher.
+pub trait InterfaceObserver {
+    fn interface_changed(&mut self, event: &InterfaceEvent);
+}
+
+pub type InterfaceObserverObject = Arc<Mutex<dyn InterfaceObserver + Send>>;
@@ -15,11 +15,175 @@
 #[cfg(test)]
 mod tests {
 
+    use nix::libc;
+    use std::net::IpAddr;
+
     use crate::net::interface::*;
 
+    fn test_interface(name: &str, index: u32, flags: i32, addrs: &[&str]) -> Interface {
+        let mut iface = Interface::new(name, index);
+        iface.set_flags((libc::IFF_UP | flags) as u32);
+        for addr in addrs {
+            iface.add_address(addr.parse().unwrap());
+        }
+        iface
+    }
+
+    fn test_host_interfaces(addrs: &[&str]) -> Vec<Interface> {
+        let mut docker = test_interface("docker0", 3, libc::IFF_MULTICAST, &["172.17.0.1/16"]);
+        docker.set_virtual(true).set_bridge(true);
+        vec![
+            test_interface("lo", 1, libc::IFF_LOOPBACK, &["127.0.0.1/8", "::1/128"]),
+            test_interface("eth0", 2, libc::IFF_MULTICAST, addrs),
+            docker,
+        ]
+    }
+
+    fn test_ifaddrs(addrs: &[&str]) -> Vec<IpAddr> {
+        addrs.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn v4_only_host_interfaces() {
+        let ifaces = test_host_interfaces(&["192.0.2.2/24"]);
+        let ifaddrs = test_ifaddrs(&["192.0.2.2"]);
+        let filter = InterfaceFilter::new();
+        assert_eq!(filter.addresses(&ifaces), ifaddrs);
+        assert_eq!(filter.clone().ipv6(false).addresses(&ifaces), ifaddrs);
+        assert!(filter.ipv4(false).addresses(&ifaces).is_empty());
+    }
+
+    #[test]
+    fn v6_only_host_interfaces() {
+        let ifaces = test_host_interfaces(&["fd00::2/64", "fe80::2/64"]);
+        let ifaddrs = test_ifaddrs(&["fd00::2", "fe80::2"]);
+        let filter = InterfaceFilter::new();
+        assert_eq!(filter.addresses(&ifaces), ifaddrs);
+        assert!(filter.clone().ipv6(false).addresses(&ifaces).is_empty());
+        assert_eq!(filter.ipv4(false).addresses(&ifaces), ifaddrs);
+        for ifaddr in ifaddrs {
+            assert_eq!(find_interface_index(&ifaces, ifaddr), Some(2));
+        }
+    }
+
+    #[test]
+    fn dual_stack_host_interfaces() {
+        let ifaces = test_host_interfaces(&["192.0.2.2/24", "fd00::2/64", "fe80::2/64"]);
+        let filter = InterfaceFilter::new();
+        assert_eq!(
+            filter.addresses(&ifaces),
+            test_ifaddrs(&["192.0.2.2", "fd00::2", "fe80::2"])
+        );
+        assert_eq!(
+            filter.clone().ipv6(false).addresses(&ifaces),
+            test_ifaddrs(&["192.0.2.2"])
+        );
+        assert_eq!(
+            filter.ipv4(false).addresses(&ifaces),
+            test_ifaddrs(&["fd00::2", "fe80::2"])
+        );
+        assert_eq!(
+            find_interface_index(&ifaces, "::1".parse().unwrap()),
+            Some(1)
+        );
+        assert_eq!(
+            find_interface_index(&ifaces, "198.51.100.1".parse().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn interface_filter() {
+        let mut ifaces = test_host_interfaces(&["192.0.2.2/24", "fd00::2/64", "fe80::2/64"]);
+        ifaces.push(test_interface(
+            "tun0",
+            4,
+            libc::IFF_POINTOPOINT,
+            &["10.8.0.2/24"],
+        ));
+        let mut veth = test_interface("veth1234", 5, libc::IFF_MULTICAST, &["10.9.0.1/24"]);
+        veth.set_virtual(true);
+        ifaces.push(veth);
+        let mut down = test_interface("eth1", 6, libc::IFF_MULTICAST, &["198.51.100.2/24"]);
+        down.set_flags(libc::IFF_MULTICAST as u32);
+        ifaces.push(down);
+
+        let filter = InterfaceFilter::new().loopback(true);
+        assert_eq!(
+            filter.addresses(&ifaces),
+            test_ifaddrs(&["127.0.0.1", "::1", "192.0.2.2", "fd00::2", "fe80::2"])
+        );
+
+        let filter = InterfaceFilter::new().include_name("lo").loopback(true);
+        assert_eq!(
+            filter.addresses(&ifaces),
+            test_ifaddrs(&["127.0.0.1", "::1"])
+        );
+
+        let filter = InterfaceFilter::new()
+            .point_to_point(true)
+            .exclude_name("eth*");
+        assert_eq!(filter.addresses(&ifaces), test_ifaddrs(&["10.8.0.2"]));
+
+        let filter = InterfaceFilter::new().containers(true);
+        assert_eq!(
+            filter.addresses(&ifaces),
+            test_ifaddrs(&["192.0.2.2", "fd00::2", "fe80::2", "172.17.0.1", "10.9.0.1"])
+        );
+        assert_eq!(
+            filter.clone().bridges(false).addresses(&ifaces),
+            test_ifaddrs(&["192.0.2.2", "fd00::2", "fe80::2", "10.9.0.1"])
+        );
+        assert_eq!(
+            filter.virtual_interfaces(false).addresses(&ifaces),
+            test_ifaddrs(&["192.0.2.2", "fd00::2", "fe80::2"])
+        );
+
+        let filter = InterfaceFilter::new().exclude_network("fe80::/10".parse().unwrap());
+        assert_eq!(
+            filter.addresses(&ifaces),
+            test_ifaddrs(&["192.0.2.2", "fd00::2"])
+        );
+
+        let filter = InterfaceFilter::new()
+            .include_network("192.0.2.0/24".parse().unwrap())
+            .include_network("fd00::/8".parse().unwrap());
+        assert_eq!(
+            filter.addresses(&ifaces),
+            test_ifaddrs(&["192.0.2.2", "fd00::2"])
+        );
+    }
+
+    #[test]
+    fn interface_name_glob() {
+        assert!(glob_match("eth*", "eth0"));
+        assert!(glob_match("eth?", "eth0"));
+        assert!(!glob_match("eth?", "eth10"));
+        assert!(glob_match("*0", "docker0"));
+        assert!(glob_match("br-*", "br-1a2b"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("e*h*1", "eth0eth1"));
+        assert!(!glob_match("wlan*", "eth0"));
+    }
+
+    #[test]
+    fn host_interfaces() {
+        let ifaces = get_interfaces();
+        assert!(ifaces.iter().any(|iface| iface.is_loopback()));
+        for iface in ifaces.iter() {
+            assert!(0 < iface.index());
+            for addr in iface.addresses() {
+                assert_eq!(
+                    get_interface_index(addr.addr()),
+                    find_interface_index(&ifaces, addr.addr())
+                );
+            }
+        }
+    }
+
     #[test]
     fn v4_interface() {
-        let ifaddrs = get_all_interfaces();
+        let ifaddrs = get_v4_interfaces();
         for ifaddr in ifaddrs {
             assert!(ifaddr.is_ipv4())
         }
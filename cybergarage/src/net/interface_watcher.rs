@@ -0,0 +1,234 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::net::default::*;
+use crate::net::interface::{get_interfaces, Interface};
+use crate::net::interface_observer::{InterfaceEvent, InterfaceObserverObject};
+use crate::net::observer::ObserverId;
+
+type InterfaceObservers = Arc<Mutex<Vec<(ObserverId, InterfaceObserverObject)>>>;
+
+/// InterfaceWatcher monitors the network interfaces of the host and notifies the changes to the observers.
+/// On Linux, the interfaces are rescanned when rtnetlink reports a link or address change,
+/// and on the other platforms they are polled at the specified interval.
+pub struct InterfaceWatcher {
+    observers: InterfaceObservers,
+    interval: Duration,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Default for InterfaceWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InterfaceWatcher {
+    pub fn new() -> InterfaceWatcher {
+        InterfaceWatcher {
+            observers: Arc::new(Mutex::new(Vec::new())),
+            interval: INTERFACE_POLLING_INTERVAL,
+            running: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    /// add_observer adds the observer, and returns the id to remove it.
+    pub fn add_observer(&mut self, observer: InterfaceObserverObject) -> ObserverId {
+        let id = ObserverId::next();
+        self.observers.lock().unwrap().push((id, observer));
+        id
+    }
+
+    /// remove_observer removes the observer, and returns false if it has not been added.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        let mut observers = self.observers.lock().unwrap();
+        let n = observers.len();
+        observers.retain(|(added, _)| *added != id);
+        observers.len() != n
+    }
+
+    /// set_interval sets the polling interval, which is used when rtnetlink is not available.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.is_some()
+    }
+
+    pub fn start(&mut self) -> bool {
+        if self.is_running() {
+            return true;
+        }
+        self.running.store(true, Ordering::SeqCst);
+        let observers = self.observers.clone();
+        let running = self.running.clone();
+        let interval = self.interval;
+        self.thread = Some(thread::spawn(move || {
+            watch_loop(observers, running, interval);
+        }));
+        true
+    }
+
+    pub fn stop(&mut self) -> bool {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("WATCH thread panicked");
+            }
+        }
+        true
+    }
+}
+
+impl Drop for InterfaceWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// diff_interfaces returns the events which change the old interfaces into the new interfaces.
+pub fn diff_interfaces(old_ifaces: &[Interface], new_ifaces: &[Interface]) -> Vec<InterfaceEvent> {
+    let mut events = Vec::new();
+    for old_iface in old_ifaces {
+        if !new_ifaces
+            .iter()
+            .any(|new_iface| new_iface.index() == old_iface.index())
+        {
+            events.push(InterfaceEvent::Removed(old_iface.clone()));
+        }
+    }
+    for new_iface in new_ifaces {
+        match old_ifaces
+            .iter()
+            .find(|old_iface| old_iface.index() == new_iface.index())
+        {
+            Some(old_iface) if old_iface == new_iface => {}
+            Some(_) => events.push(InterfaceEvent::Changed(new_iface.clone())),
+            None => events.push(InterfaceEvent::Added(new_iface.clone())),
+        }
+    }
+    events
+}
+
+fn watch_loop(observers: InterfaceObservers, running: Arc<AtomicBool>, interval: Duration) {
+    let mut monitor = LinkMonitor::new(interval);
+    let mut ifaces = get_interfaces();
+    while running.load(Ordering::SeqCst) {
+        if !monitor.wait() {
+            continue;
+        }
+        let new_ifaces = get_interfaces();
+        for event in diff_interfaces(&ifaces, &new_ifaces) {
+            info!("WATCH {:?} {}", event, event.interface().name());
+            // NOTE: The observers are notified without holding the list, so that adding or removing an observer is not blocked by a rebinding observer.
+            let snapshot: Vec<InterfaceObserverObject> = observers
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, observer)| observer.clone())
+                .collect();
+            for observer in snapshot {
+                observer.lock().unwrap().interface_changed(&event);
+            }
+        }
+        ifaces = new_ifaces;
+    }
+}
+
+/// LinkMonitor waits for the interface changes with rtnetlink, or for the polling interval if it is not available.
+struct LinkMonitor {
+    #[cfg(target_os = "linux")]
+    netlink: Option<std::os::fd::OwnedFd>,
+    interval: Duration,
+    last_scanned: Instant,
+}
+
+impl LinkMonitor {
+    fn new(interval: Duration) -> LinkMonitor {
+        LinkMonitor {
+            #[cfg(target_os = "linux")]
+            netlink: match rtnetlink_open() {
+                Ok(fd) => Some(fd),
+                Err(e) => {
+                    warn!("rtnetlink is not available ({})", e);
+                    None
+                }
+            },
+            interval,
+            last_scanned: Instant::now(),
+        }
+    }
+
+    /// wait returns true if the interfaces should be rescanned, or false after RECV_TIMEOUT otherwise.
+    fn wait(&mut self) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some(netlink) = &self.netlink {
+            return rtnetlink_wait(netlink);
+        }
+        if self.last_scanned.elapsed() < self.interval {
+            thread::sleep(RECV_TIMEOUT);
+            return false;
+        }
+        self.last_scanned = Instant::now();
+        true
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn rtnetlink_open() -> nix::Result<std::os::fd::OwnedFd> {
+    use nix::libc;
+    use nix::sys::socket::sockopt::ReceiveTimeout;
+    use nix::sys::socket::*;
+    use nix::sys::time::TimeVal;
+    use std::os::fd::AsRawFd;
+
+    let fd = socket(
+        AddressFamily::Netlink,
+        SockType::Raw,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkRoute,
+    )?;
+    let groups = libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR;
+    bind(fd.as_raw_fd(), &NetlinkAddr::new(0, groups as u32))?;
+    let timeout = TimeVal::new(0, RECV_TIMEOUT.as_micros() as _);
+    setsockopt(&fd, ReceiveTimeout, &timeout)?;
+    Ok(fd)
+}
+
+/// rtnetlink_wait returns true when a link or address change is reported, and drains the following messages.
+#[cfg(target_os = "linux")]
+fn rtnetlink_wait(netlink: &std::os::fd::OwnedFd) -> bool {
+    use nix::sys::socket::{recv, MsgFlags};
+    use std::os::fd::AsRawFd;
+
+    let mut buf = [0_u8; 8192];
+    if recv(netlink.as_raw_fd(), &mut buf, MsgFlags::empty()).is_err() {
+        return false;
+    }
+    while recv(netlink.as_raw_fd(), &mut buf, MsgFlags::MSG_DONTWAIT).is_ok() {}
+    true
+}
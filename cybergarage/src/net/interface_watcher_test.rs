@@ -0,0 +1,96 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+
+    use nix::libc;
+    use std::sync::{Arc, Mutex};
+    use std::time;
+
+    use crate::net::interface::*;
+    use crate::net::interface_observer::*;
+    use crate::net::interface_watcher::*;
+
+    struct TestInterfaceObserver {
+        events: Arc<Mutex<Vec<InterfaceEvent>>>,
+    }
+
+    impl InterfaceObserver for TestInterfaceObserver {
+        fn interface_changed(&mut self, event: &InterfaceEvent) {
+            self.events.lock().unwrap().push(event.clone());
+        }
+    }
+
+    fn test_interface(name: &str, index: u32, addrs: &[&str]) -> Interface {
+        let mut iface = Interface::new(name, index);
+        iface.set_flags((libc::IFF_UP | libc::IFF_MULTICAST) as u32);
+        for addr in addrs {
+            iface.add_address(addr.parse().unwrap());
+        }
+        iface
+    }
+
+    #[test]
+    fn interface_diff() {
+        let eth0 = test_interface("eth0", 2, &["192.0.2.2/24"]);
+        let wlan0 = test_interface("wlan0", 3, &["198.51.100.2/24"]);
+        let old_ifaces = vec![eth0.clone(), wlan0.clone()];
+
+        assert!(diff_interfaces(&old_ifaces, &old_ifaces).is_empty());
+
+        let mut new_eth0 = eth0.clone();
+        new_eth0.add_address("fd00::2/64".parse().unwrap());
+        let eth1 = test_interface("eth1", 4, &["203.0.113.2/24"]);
+        let new_ifaces = vec![new_eth0.clone(), eth1.clone()];
+        assert_eq!(
+            diff_interfaces(&old_ifaces, &new_ifaces),
+            vec![
+                InterfaceEvent::Removed(wlan0),
+                InterfaceEvent::Changed(new_eth0),
+                InterfaceEvent::Added(eth1),
+            ]
+        );
+
+        let mut down_eth0 = eth0.clone();
+        down_eth0.set_flags(libc::IFF_MULTICAST as u32);
+        assert_eq!(
+            diff_interfaces(&[eth0], &[down_eth0.clone()]),
+            vec![InterfaceEvent::Changed(down_eth0)]
+        );
+    }
+
+    #[test]
+    fn interface_watcher() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut watcher = InterfaceWatcher::new();
+        let observer = TestInterfaceObserver {
+            events: events.clone(),
+        };
+        let id = watcher.add_observer(Arc::new(Mutex::new(observer)));
+        watcher.set_interval(time::Duration::from_millis(100));
+        assert!(watcher.start());
+        assert!(watcher.is_running());
+        std::thread::sleep(time::Duration::from_millis(500));
+        let now = time::Instant::now();
+        assert!(watcher.stop());
+        assert!(now.elapsed() < time::Duration::from_secs(1));
+        assert!(!watcher.is_running());
+        // NOTE: The interfaces of the test host are not expected to change while the test is running.
+        assert!(events.lock().unwrap().is_empty());
+
+        assert!(watcher.remove_observer(id));
+        assert!(!watcher.remove_observer(id));
+    }
+}
@@ -12,34 +12,129 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub use self::error::{BindError, Error, Result, ScoketError};
+pub use self::broadcast_manager::BroadcastManager;
+pub use self::broadcast_server::BroadcastServer;
+pub use self::cidr::Cidr;
+pub use self::dispatch_options::{DispatchMode, DispatchOptions};
+pub use self::error::{Error, Result};
+pub use self::framer::{
+    DelimiterFramer, FixedSizeFramer, Framer, FramerObject, LengthPrefixedFramer,
+};
+pub use self::interface::{get_interfaces, Interface, InterfaceFilter};
+pub use self::interface_observer::{InterfaceEvent, InterfaceObserver, InterfaceObserverObject};
+pub use self::interface_watcher::InterfaceWatcher;
 pub use self::multicast_manager::MulticastManager;
+pub use self::multicast_options::MulticastOptions;
 pub use self::multicast_server::MulticastServer;
 pub use self::notify_manager::NotifytManager;
-pub use self::observer::{Observer, ObserverObject};
-pub use self::packet::Packet;
+pub use self::observer::{
+    fn_observer_new, Observer, ObserverId, ObserverObject, WeakObserverObject,
+};
+pub use self::observer_registry::ObserverRegistry;
+pub use self::packet::{Packet, PacketKind};
+pub use self::packet_channel::{OverflowPolicy, PacketReceiver};
+pub use self::packet_filter::{
+    AndFilter, DestinationGroupFilter, InterfaceIndexFilter, LengthFilter, NotFilter, OrFilter,
+    PacketFilter, PacketFilterObject, PredicateFilter, PrefixFilter, SourceCidrFilter,
+    SourcePortFilter,
+};
+pub use self::route::SendFallback;
+pub use self::send_report::SendReport;
+pub use self::tcp_connection::TcpConnection;
+pub use self::tcp_manager::TcpManager;
+pub use self::tcp_server::TcpServer;
+pub use self::transport_manager::{TransportConfig, TransportManager};
 pub use self::udp_socket::UdpSocket;
+pub use self::udp_socket_options::UdpSocketOptions;
 pub use self::unicast_manager::UnicastManager;
 pub use self::unicast_server::UnicastServer;
 
+#[cfg(feature = "async")]
+pub use self::async_multicast_manager::AsyncMulticastManager;
+#[cfg(feature = "async")]
+pub use self::async_multicast_server::AsyncMulticastServer;
+#[cfg(feature = "async")]
+pub use self::async_unicast_manager::AsyncUnicastManager;
+#[cfg(feature = "async")]
+pub use self::async_unicast_server::AsyncUnicastServer;
+#[cfg(feature = "async")]
+pub use self::packet_stream::PacketStream;
+
+mod broadcast_manager;
+mod broadcast_server;
+mod cidr;
 mod default;
+mod dispatch_options;
+mod dispatch_pool;
 mod error;
+mod framer;
 mod interface;
+mod interface_observer;
+mod interface_watcher;
 mod multicast_manager;
+mod multicast_options;
 mod multicast_server;
 mod notifier;
 mod notify_manager;
 mod observer;
+mod observer_registry;
 mod packet;
-mod result;
+mod packet_channel;
+mod packet_filter;
+mod route;
+mod send_report;
+mod tcp_connection;
+mod tcp_manager;
+mod tcp_server;
+mod transport_manager;
+mod udp_receiver;
 mod udp_socket;
+mod udp_socket_options;
 mod unicast_manager;
 mod unicast_server;
 
+#[cfg(feature = "async")]
+mod async_multicast_manager;
+#[cfg(feature = "async")]
+mod async_multicast_server;
+#[cfg(feature = "async")]
+mod async_udp_socket;
+#[cfg(feature = "async")]
+mod async_unicast_manager;
+#[cfg(feature = "async")]
+mod async_unicast_server;
+#[cfg(feature = "async")]
+mod packet_stream;
+
+mod broadcast_manager_test;
+mod broadcast_server_test;
+mod cidr_test;
 mod default_test;
+mod error_test;
+mod framer_test;
 mod interface_test;
+mod interface_watcher_test;
 mod multicast_manager_test;
 mod multicast_server_test;
 mod notify_manager_test;
+mod packet_channel_test;
+mod packet_filter_test;
+mod tcp_manager_test;
+mod tcp_server_test;
+mod transport_manager_test;
+mod udp_socket_test;
 mod unicast_manager_test;
 mod unicast_server_test;
+
+#[cfg(feature = "async")]
+mod async_multicast_manager_test;
+#[cfg(feature = "async")]
+mod async_multicast_server_test;
+#[cfg(feature = "async")]
+mod async_udp_socket_test;
+#[cfg(feature = "async")]
+mod async_unicast_manager_test;
+#[cfg(feature = "async")]
+mod async_unicast_server_test;
+#[cfg(feature = "async")]
+mod packet_stream_test;
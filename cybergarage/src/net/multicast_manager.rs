@@ -13,49 +13,314 @@
 // limitations under the License.
 
 use log::*;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
 use crate::net::interface::*;
+use crate::net::interface_observer::{InterfaceEvent, InterfaceObserver};
+use crate::net::multicast_options::MulticastOptions;
 use crate::net::multicast_server::MulticastServer;
-use crate::net::observer::ObserverObject;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
 use crate::net::packet::Packet;
-use crate::net::result::Result;
+use crate::net::send_report::SendReport;
+use crate::net::udp_socket_options::UdpSocketOptions;
 
 pub struct MulticastManager {
     mcast_servers: Vec<MulticastServer>,
+    observers: NotifytManager,
+    filter: InterfaceFilter,
+    options: MulticastOptions,
+    socket_options: UdpSocketOptions,
+    maddrs: Vec<IpAddr>,
+    source_groups: Vec<(IpAddr, IpAddr)>,
+    ifaddrs: Vec<IpAddr>,
+    port: Option<u16>,
+}
+
+impl Default for MulticastManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MulticastManager {
     pub fn new() -> MulticastManager {
         MulticastManager {
             mcast_servers: Vec::new(),
+            observers: NotifytManager::new(),
+            filter: InterfaceFilter::new(),
+            options: MulticastOptions::new(),
+            socket_options: UdpSocketOptions::new(),
+            maddrs: Vec::new(),
+            source_groups: Vec::new(),
+            ifaddrs: Vec::new(),
+            port: None,
         }
     }
 
-    pub fn add_observer(&mut self, observer: ObserverObject) -> bool {
+    /// set_interface_filter sets the filter which selects the interfaces to bind in start() and rebind().
+    pub fn set_interface_filter(&mut self, filter: InterfaceFilter) {
+        self.filter = filter;
+    }
+
+    pub fn interface_filter(&self) -> &InterfaceFilter {
+        &self.filter
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers by the servers which are bound after this call.
+    /// The servers share the worker threads of DispatchMode::Pool, which are started once for the manager.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.observers.set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> &DispatchOptions {
+        self.observers.dispatch_options()
+    }
+
+    /// set_multicast_options sets the send options of the servers which are bound after this call.
+    pub fn set_multicast_options(&mut self, options: MulticastOptions) {
+        self.options = options;
+    }
+
+    pub fn multicast_options(&self) -> &MulticastOptions {
+        &self.options
+    }
+
+    /// set_socket_options sets the socket options of the servers which are bound after this call.
+    pub fn set_socket_options(&mut self, options: UdpSocketOptions) {
+        self.socket_options = options;
+    }
+
+    pub fn socket_options(&self) -> &UdpSocketOptions {
+        &self.socket_options
+    }
+
+    /// remove_observer removes the observer from all servers, and returns false if it has not been added.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
         for mcast_server in self.mcast_servers.iter_mut() {
-            if !mcast_server.add_observer(observer.clone()) {
-                return false;
-            }
+            mcast_server.remove_observer(id);
         }
-        true
+        self.observers.remove_observer(id)
     }
 
+    /// notify sends the packet to the groups from all servers. It tries all servers even if some of them could not send it, and returns the first error.
     pub fn notify(&self, msg: &Packet) -> Result<()> {
+        for (_, result) in self.notify_all(msg).into_results() {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// notify_all sends the packet to the groups from all servers, and returns the outcome for each interface.
+    pub fn notify_all(&self, msg: &Packet) -> SendReport {
+        let mut report = SendReport::new();
         for mcast_server in self.mcast_servers.iter() {
-            let res = mcast_server.notify(msg);
-            if res.is_err() {
-                return Err(res.err().unwrap());
+            if let Ok(ifaddr) = mcast_server.ifaddr() {
+                report.add(ifaddr, mcast_server.notify(msg));
             }
         }
-        Ok(())
+        report
     }
 
-    pub fn is_running(&self) -> bool {
-        if self.mcast_servers.len() == 0 {
-            return false;
+    /// notify_with_filter sends the packet to the groups only from the servers whose interfaces are selected by the filter,
+    /// and returns the outcome for each interface.
+    pub fn notify_with_filter(&self, msg: &Packet, filter: &InterfaceFilter) -> SendReport {
+        let ifaces = get_interfaces();
+        let mut report = SendReport::new();
+        for mcast_server in self.mcast_servers.iter() {
+            if let Ok(ifaddr) = mcast_server.ifaddr() {
+                if !filter.is_interface_address_match(&ifaces, &ifaddr.ip()) {
+                    continue;
+                }
+                report.add(ifaddr, mcast_server.notify(msg));
+            }
+        }
+        report
+    }
+
+    /// reply sends the bytes back to the sender of the received packet, from the server on the interface which the packet came in on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        let mcast_server = self.mcast_servers.iter().find(|mcast_server| {
+            mcast_server.interface_index() == msg.interface_index()
+                && matches!(mcast_server.ifaddr(), Ok(ifaddr) if ifaddr.is_ipv4() == msg.from().is_ipv4())
+        });
+        match mcast_server {
+            Some(mcast_server) => mcast_server.reply(msg, bytes),
+            None => Err(Error::NoRoute { to: msg.from() }),
+        }
+    }
+
+    /// groups returns the multicast groups which the running servers join.
+    pub fn groups(&self) -> &[IpAddr] {
+        &self.maddrs
+    }
+
+    /// join_group joins the group on all interfaces of the same address family.
+    /// It tries all interfaces even if some of them could not join the group, and returns the first error.
+    pub fn join_group(&mut self, maddr: IpAddr) -> Result<()> {
+        if self.maddrs.contains(&maddr) {
+            return Ok(());
+        }
+        self.maddrs.push(maddr);
+
+        let mut ret = Ok(());
+        for mcast_server in self.mcast_servers.iter_mut() {
+            if !matches!(mcast_server.ifaddr(), Ok(ifaddr) if ifaddr.is_ipv4() == maddr.is_ipv4()) {
+                continue;
+            }
+            if let Err(e) = mcast_server.join_group(maddr) {
+                warn!("JOIN {} ({})", maddr, e);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+        }
+
+        // The interfaces which had no group of the same address family have no server yet.
+        let rebind_ret = self.rebind_with_interfaces(&self.ifaddrs.clone());
+        ret.and(rebind_ret)
+    }
+
+    /// leave_group leaves the group on all interfaces, and stops the servers which have no group left.
+    /// It tries all interfaces even if some of them could not leave the group, and returns the first error.
+    pub fn leave_group(&mut self, maddr: IpAddr) -> Result<()> {
+        if !self.maddrs.contains(&maddr) {
+            return Ok(());
+        }
+        self.maddrs.retain(|joined| *joined != maddr);
+
+        let mut ret = Ok(());
+        let mut mcast_servers = Vec::new();
+        for mut mcast_server in self.mcast_servers.drain(..) {
+            if let Err(e) = mcast_server.leave_group(maddr) {
+                warn!("LEAVE {} ({})", maddr, e);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+            if mcast_server.groups().is_empty() && mcast_server.source_groups().is_empty() {
+                info!("UNBIND {:?}", mcast_server.ifaddr());
+                let _ = mcast_server.stop();
+                continue;
+            }
+            mcast_servers.push(mcast_server);
+        }
+        self.mcast_servers = mcast_servers;
+        ret
+    }
+
+    /// source_groups returns the source-specific groups as (source, group) pairs.
+    pub fn source_groups(&self) -> &[(IpAddr, IpAddr)] {
+        &self.source_groups
+    }
+
+    /// join_source_group joins the source-specific group on all interfaces of the same address family.
+    /// The pairs joined before start() are also joined by it.
+    /// It tries all interfaces even if some of them could not join the group, and returns the first error.
+    pub fn join_source_group(&mut self, source: IpAddr, maddr: IpAddr) -> Result<()> {
+        if self.source_groups.contains(&(source, maddr)) {
+            return Ok(());
+        }
+        if source.is_ipv4() != maddr.is_ipv4() {
+            return Err(Error::AddressFamilyMismatch {
+                group: maddr,
+                iface: source,
+            });
+        }
+        self.source_groups.push((source, maddr));
+
+        let mut ret = Ok(());
+        for mcast_server in self.mcast_servers.iter_mut() {
+            if !matches!(mcast_server.ifaddr(), Ok(ifaddr) if ifaddr.is_ipv4() == maddr.is_ipv4()) {
+                continue;
+            }
+            if let Err(e) = mcast_server.join_source_group(source, maddr) {
+                warn!("JOIN {} from {} ({})", maddr, source, e);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+        }
+
+        let rebind_ret = self.rebind_with_interfaces(&self.ifaddrs.clone());
+        ret.and(rebind_ret)
+    }
+
+    /// leave_source_group leaves the source-specific group on all interfaces, and stops the servers which have no group left.
+    /// It tries all interfaces even if some of them could not leave the group, and returns the first error.
+    pub fn leave_source_group(&mut self, source: IpAddr, maddr: IpAddr) -> Result<()> {
+        if !self.source_groups.contains(&(source, maddr)) {
+            return Ok(());
+        }
+        self.source_groups
+            .retain(|joined| *joined != (source, maddr));
+
+        let mut ret = Ok(());
+        let mut mcast_servers = Vec::new();
+        for mut mcast_server in self.mcast_servers.drain(..) {
+            if let Err(e) = mcast_server.leave_source_group(source, maddr) {
+                warn!("LEAVE {} from {} ({})", maddr, source, e);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+            if mcast_server.groups().is_empty() && mcast_server.source_groups().is_empty() {
+                info!("UNBIND {:?}", mcast_server.ifaddr());
+                let _ = mcast_server.stop();
+                continue;
+            }
+            mcast_servers.push(mcast_server);
+        }
+        self.mcast_servers = mcast_servers;
+        ret
+    }
+
+    /// block_source stops receiving the datagrams from the source in the any-source group on all interfaces which joined the group.
+    pub fn block_source(&self, source: IpAddr, maddr: IpAddr) -> Result<()> {
+        let mut ret = Ok(());
+        for mcast_server in self.mcast_servers.iter() {
+            if !mcast_server.groups().contains(&maddr) {
+                continue;
+            }
+            if let Err(e) = mcast_server.block_source(source, maddr) {
+                warn!("BLOCK {} from {} ({})", maddr, source, e);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+        }
+        ret
+    }
+
+    pub fn unblock_source(&self, source: IpAddr, maddr: IpAddr) -> Result<()> {
+        let mut ret = Ok(());
+        for mcast_server in self.mcast_servers.iter() {
+            if !mcast_server.groups().contains(&maddr) {
+                continue;
+            }
+            if let Err(e) = mcast_server.unblock_source(source, maddr) {
+                warn!("UNBLOCK {} from {} ({})", maddr, source, e);
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
         }
-        true
+        ret
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.port.is_some()
+    }
+
+    /// ifaddrs returns the addresses of the bound servers.
+    pub fn ifaddrs(&self) -> Vec<SocketAddr> {
+        self.mcast_servers
+            .iter()
+            .filter_map(|server| server.ifaddr().ok())
+            .collect()
     }
 
     pub fn has_interface(&self, addr: IpAddr) -> bool {
@@ -70,73 +335,187 @@ impl MulticastManager {
         false
     }
 
+    /// start_server binds the groups and the source-specific groups which have the same address family as the interface, or returns None if there is no such group.
+    fn start_server(&self, ifaddr: IpAddr, port: u16) -> Result<Option<MulticastServer>> {
+        let mut mcast_server = MulticastServer::new();
+        mcast_server.share_dispatch_options(&self.observers);
+        mcast_server.set_multicast_options(self.options);
+        mcast_server.set_socket_options(self.socket_options.clone());
+        for (id, observer) in self.observers.entries() {
+            mcast_server.insert_observer(*id, observer.clone());
+        }
+        for maddr in self.maddrs.iter() {
+            if maddr.is_ipv4() != ifaddr.is_ipv4() {
+                continue;
+            }
+            if mcast_server.is_bound() {
+                mcast_server.join_group(*maddr)?;
+            } else {
+                mcast_server.bind(*maddr, port, ifaddr)?;
+            }
+            info!("BIND {}:{} to {}", maddr, port, ifaddr);
+        }
+        for (source, maddr) in self.source_groups.iter() {
+            if maddr.is_ipv4() != ifaddr.is_ipv4() {
+                continue;
+            }
+            if mcast_server.is_bound() {
+                mcast_server.join_source_group(*source, *maddr)?;
+            } else {
+                mcast_server.bind_source(*source, *maddr, port, ifaddr)?;
+            }
+            info!("BIND {}:{} to {} from {}", maddr, port, ifaddr, source);
+        }
+        if !mcast_server.is_bound() {
+            return Ok(None);
+        }
+        mcast_server.start()?;
+        Ok(Some(mcast_server))
+    }
+
     pub fn start(&mut self, maddrs: &[IpAddr], port: u16) -> Result<()> {
+        self.start_with_interfaces(maddrs, port, &self.filter.addresses(&get_interfaces()))
+    }
+
+    /// start_with_interfaces binds the multicast groups and the groups joined before to the specified interfaces which have the same address family.
+    pub fn start_with_interfaces(
+        &mut self,
+        maddrs: &[IpAddr],
+        port: u16,
+        ifaddrs: &[IpAddr],
+    ) -> Result<()> {
         if self.is_running() {
             return Ok(());
         }
 
-        for ifaddr in get_all_interfaces() {
-            let mut mcast_server = MulticastServer::new();
-            if ifaddr.is_ipv4() {
-                for maddr in maddrs {
-                    if !maddr.is_ipv4() {
-                        continue;
-                    }
+        if ifaddrs.is_empty() {
+            return Err(Error::NoInterfaces);
+        }
 
-                    let ret = mcast_server.bind(*maddr, port, ifaddr);
-                    if ret.is_err() {
-                        let _ = self.stop();
-                        return ret;
-                    }
-                    info!(
-                        "BIND {}:{} to {}",
-                        maddr.to_string(),
-                        port,
-                        ifaddr.to_string()
-                    );
+        // The groups joined by join_group() before start() are kept as well as the source-specific groups.
+        for maddr in maddrs.iter() {
+            if !self.maddrs.contains(maddr) {
+                self.maddrs.push(*maddr);
+            }
+        }
+        self.ifaddrs = ifaddrs.to_vec();
+        self.port = Some(port);
+        for ifaddr in ifaddrs.iter().copied() {
+            match self.start_server(ifaddr, port) {
+                Ok(Some(mcast_server)) => self.mcast_servers.push(mcast_server),
+                Ok(None) => continue,
+                Err(e) => {
+                    let _ = self.stop();
+                    return Err(e);
                 }
-            } else if ifaddr.is_ipv6() {
-                for maddr in maddrs {
-                    if !maddr.is_ipv6() {
-                        continue;
-                    }
-                    let ret = mcast_server.bind(*maddr, port, ifaddr);
-                    if ret.is_err() {
-                        let _ = self.stop();
-                        return ret;
+            }
+        }
+        Ok(())
+    }
+
+    /// rebind joins the groups on the interfaces which are newly selected by the interface filter, and stops the servers of the vanished interfaces.
+    pub fn rebind(&mut self) -> Result<()> {
+        self.rebind_with_interfaces(&self.filter.addresses(&get_interfaces()))
+    }
+
+    /// rebind_with_interfaces updates the running servers to the specified interfaces.
+    /// It tries all interfaces even if some of them could not be bound, and returns the first error.
+    pub fn rebind_with_interfaces(&mut self, ifaddrs: &[IpAddr]) -> Result<()> {
+        self.rebind_servers(ifaddrs, 0)
+    }
+
+    /// rebind_with_event rebinds the servers like rebind(), and joins the groups again on the kept server of the interface of the event,
+    /// since the interface may have been reconfigured with the same address. The servers of the other interfaces keep their groups.
+    pub fn rebind_with_event(&mut self, event: &InterfaceEvent) -> Result<()> {
+        let ifaddrs = self.filter.addresses(&get_interfaces());
+        self.rebind_servers(&ifaddrs, event.interface().index())
+    }
+
+    /// rebind_servers updates the running servers to the specified interfaces, and joins the groups again on the kept server of rejoin_index.
+    fn rebind_servers(&mut self, ifaddrs: &[IpAddr], rejoin_index: u32) -> Result<()> {
+        let port = match self.port {
+            Some(port) => port,
+            None => return Ok(()),
+        };
+        self.ifaddrs = ifaddrs.to_vec();
+
+        let mut ret = Ok(());
+        let mut mcast_servers = Vec::new();
+        for mut mcast_server in self.mcast_servers.drain(..) {
+            match mcast_server.ifaddr() {
+                // NOTE: The server of an interface which has been added again with another index is bound again.
+                Ok(addr)
+                    if ifaddrs.contains(&addr.ip())
+                        && get_interface_index(addr.ip()).unwrap_or(0)
+                            == mcast_server.interface_index() =>
+                {
+                    if rejoin_index != 0 && mcast_server.interface_index() == rejoin_index {
+                        if let Err(e) = mcast_server.rejoin_groups() {
+                            warn!("REJOIN {} ({})", addr, e);
+                            if ret.is_ok() {
+                                ret = Err(e);
+                            }
+                        }
                     }
-                    info!(
-                        "BIND {}:{} to {}",
-                        maddr.to_string(),
-                        port,
-                        ifaddr.to_string()
-                    );
+                    mcast_servers.push(mcast_server);
                 }
-            } else {
+                _ => {
+                    info!("UNBIND {:?}", mcast_server.ifaddr());
+                    let _ = mcast_server.stop();
+                }
+            }
+        }
+        self.mcast_servers = mcast_servers;
+
+        for ifaddr in ifaddrs.iter().copied() {
+            if self.has_interface(ifaddr) {
                 continue;
             }
-            let ret = mcast_server.start();
-            if ret.is_err() {
-                let _ = self.stop();
-                return ret;
+            match self.start_server(ifaddr, port) {
+                Ok(Some(mcast_server)) => self.mcast_servers.push(mcast_server),
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("BIND {}:{} ({})", ifaddr, port, e);
+                    if ret.is_ok() {
+                        ret = Err(e);
+                    }
+                }
             }
-            self.mcast_servers.push(mcast_server);
         }
-        Ok(())
+        ret
     }
 
     pub fn stop(&mut self) -> Result<()> {
         for mcast_server in self.mcast_servers.iter_mut() {
-            let ret = mcast_server.stop();
-            if ret.is_err() {
-                return ret;
-            }
+            mcast_server.stop()?;
         }
         self.mcast_servers.clear();
+        self.ifaddrs.clear();
+        self.port = None;
         Ok(())
     }
 }
 
+impl ObserverRegistry for MulticastManager {
+    /// insert_observer adds the observer to the running servers and the servers which are bound later.
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        let id = self.observers.insert_observer(id, observer.clone());
+        for mcast_server in self.mcast_servers.iter_mut() {
+            mcast_server.insert_observer(id, observer.clone());
+        }
+        id
+    }
+}
+
+impl InterfaceObserver for MulticastManager {
+    /// interface_changed rebinds the servers, so that a manager shared with InterfaceWatcher follows the interface changes.
+    fn interface_changed(&mut self, event: &InterfaceEvent) {
+        if let Err(e) = self.rebind_with_event(event) {
+            warn!("REBIND ({})", e);
+        }
+    }
+}
+
 impl Drop for MulticastManager {
     fn drop(&mut self) {
         let _ = self.stop();
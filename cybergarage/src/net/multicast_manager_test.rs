@@ -15,9 +15,21 @@
 #[cfg(test)]
 mod tests {
 
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time;
+
     use crate::log::Logger;
     use crate::net::default_test::*;
+    use crate::net::interface::{get_interface_index, get_interfaces, InterfaceFilter};
+    use crate::net::interface_observer::InterfaceEvent;
     use crate::net::multicast_manager::*;
+    use crate::net::notify_manager_test::*;
+    use crate::net::observer::ObserverObject;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+    use crate::net::udp_socket::UdpSocket;
 
     #[test]
     fn multicast_manager() {
@@ -30,4 +42,319 @@ mod tests {
         let ret = mgr.stop();
         assert!(ret.is_ok(), "{}", ret.err().unwrap());
     }
+
+    #[test]
+    fn multicast_manager_with_interfaces() {
+        fn test_multicast_manager(ifaddrs: &[IpAddr]) {
+            let maddrs = vec![TEST_MULTICAST_V4_ADDRESS, TEST_MULTICAST_V6_ADDRESS];
+            let mut mgr = MulticastManager::new();
+            let ret = mgr.start_with_interfaces(&maddrs, TEST_PORT, ifaddrs);
+            assert!(ret.is_ok(), "{}", ret.err().unwrap());
+            for ifaddr in ifaddrs {
+                assert!(mgr.has_interface(*ifaddr));
+            }
+            let ret = mgr.stop();
+            assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        }
+
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        test_multicast_manager(&[v4_ifaddr]);
+        test_multicast_manager(&[v6_ifaddr]);
+        test_multicast_manager(&[v4_ifaddr, v6_ifaddr]);
+
+        let mut mgr = MulticastManager::new();
+        assert!(mgr.start_with_interfaces(&[], TEST_PORT, &[]).is_err());
+    }
+
+    #[test]
+    fn multicast_manager_rebind() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+        let mut mgr = MulticastManager::new();
+        let ret = mgr.start_with_interfaces(&[TEST_MULTICAST_V6_ADDRESS], TEST_PORT, &[v6_ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        assert!(mgr.has_interface(v6_ifaddr));
+
+        // The IPv4 interface is skipped because there is no IPv4 group.
+        assert!(mgr.rebind_with_interfaces(&[v4_ifaddr, v6_ifaddr]).is_ok());
+        assert!(!mgr.has_interface(v4_ifaddr));
+        assert!(mgr.has_interface(v6_ifaddr));
+
+        assert!(mgr.rebind_with_interfaces(&[v4_ifaddr]).is_ok());
+        assert!(!mgr.has_interface(v6_ifaddr));
+        assert!(mgr.is_running());
+
+        assert!(mgr.stop().is_ok());
+        assert!(!mgr.is_running());
+    }
+
+    #[test]
+    fn multicast_manager_rebind_same_interfaces() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ssm_addr = IpAddr::V4(Ipv4Addr::new(232, 0, 23, 2));
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let if_index = get_interface_index(ifaddr).unwrap();
+        let iface = get_interfaces()
+            .into_iter()
+            .find(|iface| iface.index() == if_index)
+            .unwrap();
+
+        let mut mgr = MulticastManager::new();
+        mgr.set_interface_filter(
+            InterfaceFilter::new()
+                .include_name(iface.name())
+                .loopback(true)
+                .ipv6(false),
+        );
+        assert!(mgr.join_source_group(ifaddr, ssm_addr).is_ok());
+        let ret = mgr.start_with_interfaces(&[TEST_MULTICAST_V4_ADDRESS2], TEST_PORT, &[ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+
+        // The kept server keeps the groups, and joins them again when its interface has changed with the same address.
+        let ret = mgr.rebind_with_interfaces(&[ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        assert!(mgr.has_interface(ifaddr));
+        let ret = mgr.rebind_with_event(&InterfaceEvent::Changed(iface));
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        assert!(mgr.has_interface(ifaddr));
+        assert_eq!(mgr.groups(), &[TEST_MULTICAST_V4_ADDRESS2]);
+        assert_eq!(mgr.source_groups(), &[(ifaddr, ssm_addr)]);
+
+        // The other tests may send to the same group and port, so only the packets of this test are counted.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(b"multicast_manager_rebind_same_interfaces".to_vec());
+        assert!(mgr.notify(&pkt).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        let packets = packets.lock().unwrap();
+        for maddr in [TEST_MULTICAST_V4_ADDRESS2, ssm_addr] {
+            assert!(packets
+                .iter()
+                .any(|recv_pkt| recv_pkt.to().ip() == maddr && recv_pkt.bytes() == pkt.bytes()));
+        }
+    }
+
+    #[test]
+    fn multicast_manager_notify() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let maddrs = vec![TEST_MULTICAST_V4_ADDRESS, TEST_MULTICAST_V6_ADDRESS];
+
+        let mut mgr = MulticastManager::new();
+        let ret = mgr.start_with_interfaces(&maddrs, TEST_PORT, &[v4_ifaddr, v6_ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+
+        // notify_all tries all interfaces even if some of them fail.
+        let report = mgr.notify_all(&pkt);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report.successes().len() + report.failures().len(), 2);
+
+        let filter = InterfaceFilter::new().loopback(true).ipv6(false);
+        let report = mgr.notify_with_filter(&pkt, &filter);
+        assert_eq!(report.len(), 1);
+        assert!(report
+            .results()
+            .iter()
+            .all(|(ifaddr, _)| ifaddr.ip() == v4_ifaddr));
+
+        let filter = InterfaceFilter::new().loopback(true).include_name("none*");
+        assert!(mgr.notify_with_filter(&pkt, &filter).is_empty());
+
+        assert!(mgr.stop().is_ok());
+    }
+
+    #[test]
+    fn multicast_manager_groups() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+        let mut mgr = MulticastManager::new();
+        let ret = mgr.start_with_interfaces(
+            &[TEST_MULTICAST_V6_ADDRESS],
+            TEST_PORT,
+            &[v4_ifaddr, v6_ifaddr],
+        );
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        assert!(!mgr.has_interface(v4_ifaddr));
+
+        // Joining an IPv4 group starts the server of the IPv4 interface.
+        assert!(mgr.join_group(TEST_MULTICAST_V4_ADDRESS).is_ok());
+        assert!(mgr.join_group(TEST_MULTICAST_V6_ADDRESS2).is_ok());
+        assert_eq!(
+            mgr.groups(),
+            &[
+                TEST_MULTICAST_V6_ADDRESS,
+                TEST_MULTICAST_V4_ADDRESS,
+                TEST_MULTICAST_V6_ADDRESS2
+            ]
+        );
+        assert!(mgr.has_interface(v4_ifaddr));
+        assert!(mgr.has_interface(v6_ifaddr));
+
+        // Leaving the last group of an interface stops its server.
+        assert!(mgr.leave_group(TEST_MULTICAST_V4_ADDRESS).is_ok());
+        assert!(!mgr.has_interface(v4_ifaddr));
+        assert!(mgr.leave_group(TEST_MULTICAST_V6_ADDRESS).is_ok());
+        assert!(mgr.has_interface(v6_ifaddr));
+        assert_eq!(mgr.groups(), &[TEST_MULTICAST_V6_ADDRESS2]);
+        assert!(mgr.leave_group(TEST_MULTICAST_V6_ADDRESS2).is_ok());
+        assert!(!mgr.has_interface(v6_ifaddr));
+        assert!(mgr.is_running());
+
+        assert!(mgr.stop().is_ok());
+    }
+
+    #[test]
+    fn multicast_manager_join_before_start() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        // The group joined before start() is bound by start() with the specified groups.
+        let mut mgr = MulticastManager::new();
+        assert!(mgr.join_group(TEST_MULTICAST_V4_ADDRESS2).is_ok());
+        assert!(!mgr.is_running());
+        let ret = mgr.start_with_interfaces(
+            &[TEST_MULTICAST_V4_ADDRESS, TEST_MULTICAST_V4_ADDRESS2],
+            TEST_PORT,
+            &[ifaddr],
+        );
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        assert_eq!(
+            mgr.groups(),
+            &[TEST_MULTICAST_V4_ADDRESS2, TEST_MULTICAST_V4_ADDRESS]
+        );
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+
+        // The other tests may send to the same group and port, so only the packets of this test are counted.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(b"multicast_manager_join_before_start".to_vec());
+        assert!(mgr.notify(&pkt).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        let packets = packets.lock().unwrap();
+        for maddr in [TEST_MULTICAST_V4_ADDRESS, TEST_MULTICAST_V4_ADDRESS2] {
+            assert!(packets
+                .iter()
+                .any(|recv_pkt| recv_pkt.to().ip() == maddr && recv_pkt.bytes() == pkt.bytes()));
+        }
+    }
+
+    #[test]
+    fn multicast_manager_source_groups() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other_addr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+        let ssm_addr = IpAddr::V4(Ipv4Addr::new(232, 0, 23, 1));
+        let asm_addr = TEST_MULTICAST_V4_ADDRESS2;
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = MulticastManager::new();
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(mgr.join_source_group(ifaddr, ssm_addr).is_ok());
+        assert!(mgr
+            .join_source_group(ifaddr, TEST_MULTICAST_V6_ADDRESS)
+            .is_err());
+        let ret = mgr.start_with_interfaces(&[], TEST_PORT, &[ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        assert!(mgr.has_interface(ifaddr));
+        assert_eq!(mgr.source_groups(), &[(ifaddr, ssm_addr)]);
+        assert!(mgr.join_group(asm_addr).is_ok());
+        assert!(mgr.block_source(other_addr, asm_addr).is_ok());
+
+        let is_received = |from: IpAddr, maddr: IpAddr| {
+            let mut sender = UdpSocket::new();
+            assert!(sender.bind(SocketAddr::new(from, 0)).is_ok());
+            assert!(sender.set_multicast_interface(&ifaddr).is_ok());
+            packets.lock().unwrap().clear();
+            assert!(sender
+                .send_to(&[0_u8; 1], SocketAddr::new(maddr, TEST_PORT))
+                .is_ok());
+            thread::sleep(time::Duration::from_millis(200));
+            packets
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|pkt| pkt.to().ip() == maddr && pkt.from().ip() == from)
+        };
+
+        // The source-specific group receives only from the source, and the any-source group from all but the blocked source.
+        assert!(is_received(ifaddr, ssm_addr));
+        assert!(!is_received(other_addr, ssm_addr));
+        assert!(is_received(ifaddr, asm_addr));
+        assert!(!is_received(other_addr, asm_addr));
+        assert!(mgr.unblock_source(other_addr, asm_addr).is_ok());
+        assert!(is_received(other_addr, asm_addr));
+
+        assert!(mgr.leave_source_group(ifaddr, ssm_addr).is_ok());
+        assert!(mgr.source_groups().is_empty());
+        assert!(!is_received(ifaddr, ssm_addr));
+        assert!(mgr.leave_group(asm_addr).is_ok());
+        assert!(!mgr.has_interface(ifaddr));
+
+        assert!(mgr.stop().is_ok());
+    }
+
+    #[test]
+    fn multicast_manager_observer_order() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let before_packets = Arc::new(Mutex::new(Vec::new()));
+        let after_packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = MulticastManager::new();
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(before_packets.clone())));
+        mgr.add_observer(observer);
+        let ret = mgr.start_with_interfaces(&[TEST_MULTICAST_V4_ADDRESS], TEST_PORT, &[ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(after_packets.clone())));
+        mgr.add_observer(observer);
+
+        // Both observers are attached to the server which is bound again after an interface change.
+        assert!(mgr.rebind_with_interfaces(&[]).is_ok());
+        assert!(!mgr.has_interface(ifaddr));
+        assert!(mgr.rebind_with_interfaces(&[ifaddr]).is_ok());
+        assert!(mgr.has_interface(ifaddr));
+
+        // The other tests may send to the same group and port, so only the packets of this test are counted.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(b"multicast_manager_observer_order".to_vec());
+        assert!(mgr.notify(&pkt).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        for packets in [before_packets, after_packets] {
+            let packets = packets.lock().unwrap();
+            assert!(packets
+                .iter()
+                .any(|recv_pkt| recv_pkt.bytes() == pkt.bytes()));
+        }
+    }
 }
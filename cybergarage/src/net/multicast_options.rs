@@ -0,0 +1,70 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::IpAddr;
+
+use crate::net::error::Result;
+use crate::net::udp_socket::UdpSocket;
+
+/// MulticastOptions holds the send options of the multicast sockets, which MulticastServer sets when it binds an interface.
+/// The options which are not specified are left to the kernel defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MulticastOptions {
+    ttl: Option<u8>,
+    loopback: Option<bool>,
+}
+
+impl MulticastOptions {
+    pub fn new() -> MulticastOptions {
+        MulticastOptions {
+            ttl: None,
+            loopback: None,
+        }
+    }
+
+    /// mdns returns the options for mDNS, which sends with the TTL or hop limit 255 (RFC 6762).
+    pub fn mdns() -> MulticastOptions {
+        MulticastOptions::new().ttl(255)
+    }
+
+    /// ssdp returns the options for SSDP, which sends with the TTL 2 (UPnP Device Architecture 1.1).
+    /// UPnP 1.0 devices use ssdp().ttl(4).
+    pub fn ssdp() -> MulticastOptions {
+        MulticastOptions::new().ttl(2)
+    }
+
+    /// ttl sets IP_MULTICAST_TTL or IPV6_MULTICAST_HOPS.
+    pub fn ttl(mut self, ttl: u8) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// loopback sets IP_MULTICAST_LOOP or IPV6_MULTICAST_LOOP.
+    pub fn loopback(mut self, on: bool) -> Self {
+        self.loopback = Some(on);
+        self
+    }
+
+    /// apply sets the outgoing interface to the specified interface, and the specified options to the bound socket.
+    pub fn apply(&self, socket: &UdpSocket, ifaddr: &IpAddr) -> Result<()> {
+        socket.set_multicast_interface(ifaddr)?;
+        if let Some(ttl) = self.ttl {
+            socket.set_multicast_ttl(ttl)?;
+        }
+        if let Some(on) = self.loopback {
+            socket.set_multicast_loop(on)?;
+        }
+        Ok(())
+    }
+}
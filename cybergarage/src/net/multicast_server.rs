@@ -15,23 +15,50 @@
 #![allow(dead_code)]
 
 use log::*;
-use std::io;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
 use std::sync::{Arc, RwLock};
-use std::thread;
 
-use crate::net::default::*;
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::interface::get_interface_index;
+use crate::net::multicast_options::MulticastOptions;
 use crate::net::notifier::*;
-use crate::net::observer::ObserverObject;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
 use crate::net::packet::Packet;
-use crate::net::result::Result;
+use crate::net::udp_receiver::UdpReceiver;
 use crate::net::udp_socket::UdpSocket;
+use crate::net::udp_socket_options::UdpSocketOptions;
+
+/// disable_multicast_all disables IP_MULTICAST_ALL, since Linux otherwise delivers the datagrams of the groups
+/// joined by the other sockets on the same port, even on the other interfaces or after leave_group().
+pub(crate) fn disable_multicast_all(socket: &UdpSocket) {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if let Err(e) = socket.set_multicast_all(false) {
+        warn!("IP_MULTICAST_ALL is not supported ({})", e);
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = socket;
+}
 
 pub struct MulticastServer {
     socket: Arc<RwLock<UdpSocket>>,
     notifier: Notifier,
-    maddr: IpAddr,
+    receiver: UdpReceiver,
+    maddrs: Vec<IpAddr>,
+    source_groups: Vec<(IpAddr, IpAddr)>,
+    ifaddr: IpAddr,
     port: u16,
+    scope_id: u32,
+    options: MulticastOptions,
+    socket_options: UdpSocketOptions,
+}
+
+impl Default for MulticastServer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MulticastServer {
@@ -39,140 +66,389 @@ impl MulticastServer {
         MulticastServer {
             socket: Arc::new(RwLock::new(UdpSocket::new())),
             notifier: notifier_new(),
-            maddr: std::net::IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+            receiver: UdpReceiver::new(),
+            maddrs: Vec::new(),
+            source_groups: Vec::new(),
+            ifaddr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             port: 0,
+            scope_id: 0,
+            options: MulticastOptions::new(),
+            socket_options: UdpSocketOptions::new(),
         }
     }
 
-    pub fn add_observer(&mut self, observer: ObserverObject) -> bool {
-        self.notifier.lock().unwrap().add_observer(observer)
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        self.notifier.lock().unwrap().remove_observer(id)
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.notifier.lock().unwrap().set_dispatch_options(options);
     }
 
+    pub fn dispatch_options(&self) -> DispatchOptions {
+        *self.notifier.lock().unwrap().dispatch_options()
+    }
+
+    /// share_dispatch_options sets the dispatch options of the manager, and shares its worker threads instead of starting new ones.
+    pub(crate) fn share_dispatch_options(&mut self, manager: &NotifytManager) {
+        self.notifier
+            .lock()
+            .unwrap()
+            .share_dispatch_options(manager);
+    }
+
+    /// notify sends the packet to all joined groups, including the source-specific groups.
+    /// It tries all groups even if some of them could not be sent to, and returns the first error.
     pub fn notify(&self, msg: &Packet) -> Result<usize> {
-        let to_addr_str = format!("{}:{}", self.maddr, self.port);
-        let to_addr: SocketAddr = to_addr_str.parse().unwrap();
+        let mut maddrs = self.maddrs.clone();
+        for (_, maddr) in self.source_groups.iter() {
+            if !maddrs.contains(maddr) {
+                maddrs.push(*maddr);
+            }
+        }
+        if maddrs.is_empty() {
+            return Err(Error::NotBound);
+        }
+        let mut ret = Ok(0);
+        for maddr in maddrs.iter() {
+            let res = self.notify_group(maddr, msg);
+            if ret.is_ok() {
+                ret = res;
+            }
+        }
+        ret
+    }
+
+    /// notify_group sends the packet to the specified group.
+    pub fn notify_group(&self, maddr: &IpAddr, msg: &Packet) -> Result<usize> {
+        let to_addr = self.multicast_addr(maddr);
         let msg_bytes = msg.bytes();
         let addr = to_addr.ip();
         let port = to_addr.port();
+        info!("MCST {} -> {}:{} ({})", self.ifaddr()?, addr, port, msg,);
+        let ret = self.socket.read().unwrap().send_to(msg_bytes, to_addr);
+        if ret.is_err() {
+            warn!("Couldn't notify Packet to {} {}", addr, port);
+        }
+        ret
+    }
+
+    /// set_multicast_options sets the send options, which are applied to the socket with the outgoing interface when it is bound.
+    pub fn set_multicast_options(&mut self, options: MulticastOptions) {
+        self.options = options;
+    }
+
+    pub fn multicast_options(&self) -> &MulticastOptions {
+        &self.options
+    }
+
+    /// set_socket_options sets the socket options, which are applied when the socket is bound.
+    pub fn set_socket_options(&mut self, options: UdpSocketOptions) {
+        self.socket_options = options;
+    }
+
+    pub fn socket_options(&self) -> &UdpSocketOptions {
+        &self.socket_options
+    }
+
+    /// set_recv_buffer_size sets the size of the buffer which received datagrams are read into. Datagrams larger than it are notified as truncated Packets.
+    pub fn set_recv_buffer_size(&mut self, size: usize) {
+        self.receiver.set_buffer_size(size);
+    }
+
+    pub fn recv_buffer_size(&self) -> usize {
+        self.receiver.buffer_size()
+    }
+
+    /// set_socket_recv_buffer_size sets SO_RCVBUF of the bound socket.
+    pub fn set_socket_recv_buffer_size(&self, size: usize) -> Result<()> {
+        self.socket.read().unwrap().set_recv_buffer_size(size)
+    }
+
+    /// set_socket_send_buffer_size sets SO_SNDBUF of the bound socket.
+    pub fn set_socket_send_buffer_size(&self, size: usize) -> Result<()> {
+        self.socket.read().unwrap().set_send_buffer_size(size)
+    }
+
+    /// reply sends the bytes back to the sender of the received packet, out of the interface the packet came in on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        let to_addr = msg.from();
         info!(
-            "MCST {} -> {}:{} ({})",
-            self.socket.read().unwrap().addr().unwrap(),
-            addr,
-            port,
-            msg,
+            "SEND {} -> {} ({})",
+            self.socket.read().unwrap().addr()?,
+            to_addr,
+            hex::encode_upper(bytes),
         );
-        let ret = self.socket.read().unwrap().send_to(&msg_bytes, to_addr);
+        let ret =
+            self.socket
+                .read()
+                .unwrap()
+                .send_to_interface(bytes, to_addr, msg.interface_index());
         if ret.is_err() {
-            warn!("Couldn't notify Packet to {} {}", addr, port);
+            warn!("Couldn't send Packet to {}", to_addr);
         }
         ret
     }
 
-    pub fn ifaddr(&self) -> io::Result<SocketAddr> {
-        self.socket.read().unwrap().addr()
+    /// interface_index returns the index of the bound interface, or 0 if it is unknown.
+    pub fn interface_index(&self) -> u32 {
+        self.scope_id
+    }
+
+    /// ifaddr returns the address of the interface which the groups are joined on, with the bound port.
+    pub fn ifaddr(&self) -> Result<SocketAddr> {
+        let port = self.socket.read().unwrap().addr()?.port();
+        Ok(SocketAddr::new(self.ifaddr, port))
     }
 
     pub fn is_bound(&self) -> bool {
         self.socket.read().unwrap().addr().is_ok()
     }
 
+    /// bind_socket binds a new socket to the unspecified address of the family of the group.
+    fn bind_socket(&mut self, maddr: IpAddr, port: u16, ifaddr: IpAddr) -> Result<()> {
+        let addr = match (maddr, ifaddr) {
+            (IpAddr::V4(_), IpAddr::V4(_)) => {
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)
+            }
+            (IpAddr::V6(_), IpAddr::V6(_)) => {
+                SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port)
+            }
+            _ => {
+                error!("BIND MCT {}:{} -> {}", ifaddr, port, maddr);
+                return Err(Error::AddressFamilyMismatch {
+                    group: maddr,
+                    iface: ifaddr,
+                });
+            }
+        };
+        debug!("BIND MCT {}", addr);
+        self.socket
+            .write()
+            .unwrap()
+            .bind_with_options(addr, &self.socket_options)?;
+        let ret = self.options.apply(&self.socket.read().unwrap(), &ifaddr);
+        if ret.is_err() {
+            let _ = self.close();
+            return ret;
+        }
+        disable_multicast_all(&self.socket.read().unwrap());
+        self.maddrs.clear();
+        self.source_groups.clear();
+        self.ifaddr = ifaddr;
+        self.port = port;
+        self.scope_id = get_interface_index(ifaddr).unwrap_or(0);
+        // NOTE: The sockets of the other interfaces are bound to the same wildcard address and port, so the datagrams are filtered by the interface.
+        self.receiver.set_interface_index(self.scope_id);
+        Ok(())
+    }
+
+    /// bind binds a new socket to the port, and joins the group on the interface.
+    /// The other groups can be joined to the bound socket by join_group().
     pub fn bind(&mut self, maddr: IpAddr, port: u16, ifaddr: IpAddr) -> Result<()> {
-        let mut addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
-        if ifaddr.is_ipv6() {
-            addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)), port);
+        self.bind_socket(maddr, port, ifaddr)?;
+        let ret = self.socket.read().unwrap().join_multicast(&maddr, &ifaddr);
+        if ret.is_err() {
+            let _ = self.close();
+            return ret;
         }
-        debug!("BIND MCT {}", addr);
-        let ret = self.socket.write().unwrap().bind(addr);
+        debug!("BIND MCT {}:{} -> {}", ifaddr, port, maddr);
+        self.maddrs.push(maddr);
+        Ok(())
+    }
+
+    /// bind_source binds a new socket to the port, and joins the source-specific group on the interface.
+    pub fn bind_source(
+        &mut self,
+        source: IpAddr,
+        maddr: IpAddr,
+        port: u16,
+        ifaddr: IpAddr,
+    ) -> Result<()> {
+        self.bind_socket(maddr, port, ifaddr)?;
+        let ret = self
+            .socket
+            .read()
+            .unwrap()
+            .join_source_multicast(&source, &maddr, &ifaddr);
         if ret.is_err() {
+            let _ = self.close();
             return ret;
         }
-        match ifaddr {
-            IpAddr::V4(ifaddr_v4) => match maddr {
-                IpAddr::V4(maddr_v4) => {
-                    let ret = self
-                        .socket
-                        .write()
-                        .unwrap()
-                        .join_multicast_v4(&maddr_v4, &ifaddr_v4);
-                    if ret.is_err() {
-                        let _ = self.close();
-                        return ret;
-                    }
-                    debug!("BIND MCT {}:{} -> {}:{}", ifaddr, port, maddr_v4, ifaddr_v4);
-                }
-                IpAddr::V6(maddr_v6) => {
-                    error!("BIND MCT {}:{} -> {}:{}", ifaddr, port, maddr_v6, ifaddr_v4);
-                }
-            },
-            IpAddr::V6(ifaddr_v6) => match maddr {
-                IpAddr::V4(maddr_v4) => {
-                    error!("BIND MCT {}:{} -> {}:{}", ifaddr, port, maddr_v4, ifaddr_v6);
-                }
-                IpAddr::V6(maddr_v6) => {
-                    let ret = self
-                        .socket
-                        .write()
-                        .unwrap()
-                        .join_multicast_v6(&maddr_v6, &ifaddr_v6);
-                    if ret.is_err() {
-                        let _ = self.close();
-                        return ret;
-                    }
-                    debug!("BIND MCT {}:{} -> {}:{}", ifaddr, port, maddr_v6, ifaddr_v6);
-                }
-            },
+        debug!("BIND MCT {}:{} -> {} from {}", ifaddr, port, maddr, source);
+        self.source_groups.push((source, maddr));
+        Ok(())
+    }
+
+    /// join_group joins the group on the bound socket. It does nothing if the group has already been joined.
+    pub fn join_group(&mut self, maddr: IpAddr) -> Result<()> {
+        if !self.is_bound() {
+            return Err(Error::NotBound);
         }
-        self.maddr = maddr;
-        self.port = port;
+        if self.maddrs.contains(&maddr) {
+            return Ok(());
+        }
+        self.socket
+            .read()
+            .unwrap()
+            .join_multicast(&maddr, &self.ifaddr)?;
+        debug!("JOIN MCT {}:{} -> {}", self.ifaddr, self.port, maddr);
+        self.maddrs.push(maddr);
         Ok(())
     }
 
-    pub fn close(&self) -> Result<()> {
-        self.socket.read().unwrap().close();
+    /// leave_group leaves the group on the bound socket. It does nothing if the group has not been joined.
+    pub fn leave_group(&mut self, maddr: IpAddr) -> Result<()> {
+        if !self.is_bound() {
+            return Err(Error::NotBound);
+        }
+        if !self.maddrs.contains(&maddr) {
+            return Ok(());
+        }
+        self.socket
+            .read()
+            .unwrap()
+            .leave_multicast(&maddr, &self.ifaddr)?;
+        debug!("LEAVE MCT {}:{} -> {}", self.ifaddr, self.port, maddr);
+        self.maddrs.retain(|joined| *joined != maddr);
         Ok(())
     }
 
-    pub fn start(&mut self) -> Result<()> {
-        let socket = self.socket.clone();
-        let notifier = self.notifier.clone();
-        thread::spawn(move || {
-            let mut buf = [0 as u8; MAX_PACKET_SIZE];
-            loop {
-                let recv_res = socket.read().unwrap().recv_from(&mut buf);
-                match &recv_res {
-                    Ok((n_bytes, remote_addr)) => {
-                        let recv_msg = &buf[0..*n_bytes];
-                        let mut msg = Packet::from_bytes(&recv_msg.to_vec());
-                        info!(
-                            "RECV {} -> {} ({})",
-                            remote_addr,
-                            socket.read().unwrap().addr().ok().unwrap(),
-                            msg
-                        );
-                        msg.set_from(remote_addr.clone());
-                        notifier.lock().unwrap().notify(&msg);
-                    }
-                    Err(e) => {
-                        warn!(
-                            "RECV {} ({})",
-                            socket.read().unwrap().addr().ok().unwrap(),
-                            e
-                        );
-                        break;
-                    }
+    /// rejoin_groups leaves and joins all groups and source-specific groups again on the bound socket,
+    /// such as after the interface has been reconfigured with the same address.
+    /// It tries all groups even if some of them could not be joined, and returns the first error.
+    pub fn rejoin_groups(&mut self) -> Result<()> {
+        if !self.is_bound() {
+            return Err(Error::NotBound);
+        }
+        let socket = self.socket.read().unwrap();
+        let mut ret = Ok(());
+        for maddr in self.maddrs.iter() {
+            let _ = socket.leave_multicast(maddr, &self.ifaddr);
+            if let Err(e) = socket.join_multicast(maddr, &self.ifaddr) {
+                warn!(
+                    "JOIN MCT {}:{} -> {} ({})",
+                    self.ifaddr, self.port, maddr, e
+                );
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+        }
+        for (source, maddr) in self.source_groups.iter() {
+            let _ = socket.leave_source_multicast(source, maddr, &self.ifaddr);
+            if let Err(e) = socket.join_source_multicast(source, maddr, &self.ifaddr) {
+                warn!(
+                    "JOIN MCT {}:{} -> {} from {} ({})",
+                    self.ifaddr, self.port, maddr, source, e
+                );
+                if ret.is_ok() {
+                    ret = Err(e);
                 }
             }
-        });
+        }
+        debug!("REJOIN MCT {}:{}", self.ifaddr, self.port);
+        ret
+    }
+
+    /// groups returns the joined groups.
+    pub fn groups(&self) -> &[IpAddr] {
+        &self.maddrs
+    }
+
+    /// join_source_group joins the source-specific group, which only receives the datagrams from the source, on the bound socket.
+    /// It does nothing if the pair has already been joined.
+    pub fn join_source_group(&mut self, source: IpAddr, maddr: IpAddr) -> Result<()> {
+        if !self.is_bound() {
+            return Err(Error::NotBound);
+        }
+        if self.source_groups.contains(&(source, maddr)) {
+            return Ok(());
+        }
+        self.socket
+            .read()
+            .unwrap()
+            .join_source_multicast(&source, &maddr, &self.ifaddr)?;
+        debug!(
+            "JOIN MCT {}:{} -> {} from {}",
+            self.ifaddr, self.port, maddr, source
+        );
+        self.source_groups.push((source, maddr));
         Ok(())
     }
 
-    pub fn stop(&self) -> Result<()> {
-        let ret = self.close();
-        if ret.is_err() {
-            return ret;
+    /// leave_source_group leaves the source-specific group on the bound socket. It does nothing if the pair has not been joined.
+    pub fn leave_source_group(&mut self, source: IpAddr, maddr: IpAddr) -> Result<()> {
+        if !self.is_bound() {
+            return Err(Error::NotBound);
+        }
+        if !self.source_groups.contains(&(source, maddr)) {
+            return Ok(());
+        }
+        self.socket
+            .read()
+            .unwrap()
+            .leave_source_multicast(&source, &maddr, &self.ifaddr)?;
+        debug!(
+            "LEAVE MCT {}:{} -> {} from {}",
+            self.ifaddr, self.port, maddr, source
+        );
+        self.source_groups
+            .retain(|joined| *joined != (source, maddr));
+        Ok(())
+    }
+
+    /// source_groups returns the joined source-specific groups as (source, group) pairs.
+    pub fn source_groups(&self) -> &[(IpAddr, IpAddr)] {
+        &self.source_groups
+    }
+
+    /// block_source stops receiving the datagrams from the source in the joined any-source group.
+    pub fn block_source(&self, source: IpAddr, maddr: IpAddr) -> Result<()> {
+        self.socket
+            .read()
+            .unwrap()
+            .block_source(&source, &maddr, &self.ifaddr)
+    }
+
+    pub fn unblock_source(&self, source: IpAddr, maddr: IpAddr) -> Result<()> {
+        self.socket
+            .read()
+            .unwrap()
+            .unblock_source(&source, &maddr, &self.ifaddr)
+    }
+
+    /// multicast_addr returns the group address to notify, which is scoped to the bound interface for IPv6.
+    fn multicast_addr(&self, maddr: &IpAddr) -> SocketAddr {
+        match maddr {
+            IpAddr::V4(_) => SocketAddr::new(*maddr, self.port),
+            IpAddr::V6(maddr_v6) => {
+                SocketAddr::V6(SocketAddrV6::new(*maddr_v6, self.port, 0, self.scope_id))
+            }
         }
+    }
+
+    pub fn close(&self) -> Result<()> {
+        self.socket.write().unwrap().close();
         Ok(())
     }
+
+    pub fn start(&mut self) -> Result<()> {
+        self.receiver
+            .start(self.socket.clone(), self.notifier.clone())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        self.receiver.stop();
+        self.close()
+    }
+}
+
+impl ObserverRegistry for MulticastServer {
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        self.notifier.lock().unwrap().insert_observer(id, observer)
+    }
 }
 
 impl Drop for MulticastServer {
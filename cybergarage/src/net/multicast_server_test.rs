@@ -14,16 +14,20 @@
 
 #[cfg(test)]
 mod tests {
-    use std::net::IpAddr;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
     use std::sync::Arc;
     use std::sync::Mutex;
     use std::thread;
     use std::time;
 
     use crate::net::default_test::*;
+    use crate::net::error::Error;
     use crate::net::interface::*;
+    use crate::net::multicast_options::MulticastOptions;
     use crate::net::multicast_server::*;
+    use crate::net::observer_registry::ObserverRegistry;
     use crate::net::packet::Packet;
+    use crate::net::udp_socket::UdpSocket;
 
     use crate::log::Logger;
     use crate::net::notify_manager_test::*;
@@ -38,7 +42,7 @@ mod tests {
             let mut server = MulticastServer::new();
 
             let observer = TestNotifyCounter::new(counter.clone());
-            assert!(server.add_observer(Arc::new(Mutex::new(observer))));
+            server.add_observer(Arc::new(Mutex::new(observer)));
 
             if ifaddr.is_ipv4() {
                 assert!(server
@@ -54,7 +58,7 @@ mod tests {
             thread::sleep(time::Duration::from_secs(5));
 
             let mut pkt = Packet::new();
-            pkt.set_bytes(vec![0 as u8; 1]);
+            pkt.set_bytes(vec![0_u8; 1]);
             for _ in 0..TEST_OBSERVER_COUNT {
                 let server_addr = server.ifaddr();
                 assert!(server_addr.is_ok());
@@ -65,8 +69,8 @@ mod tests {
             let counter = counter.lock();
             // NOTE: GitHub Action is slow and may drop to send UDP packets.
             // assert_eq!(*counter.unwrap(), TEST_OBSERVER_COUNT);
-            if counter.is_ok() {
-                assert!(0 < *counter.unwrap());
+            if let Ok(counter) = counter {
+                assert!(0 < *counter);
             }
 
             assert!(server.stop().is_ok());
@@ -76,4 +80,266 @@ mod tests {
             test_multicast_server(ifaddr);
         }
     }
+
+    #[test]
+    fn multicast_server_packet_info() {
+        fn test_multicast_server(maddr: IpAddr, ifaddr: IpAddr) {
+            Logger::init();
+            let packets = Arc::new(Mutex::new(Vec::new()));
+
+            let mut server = MulticastServer::new();
+            let observer = TestPacketRecorder::new(packets.clone());
+            server.add_observer(Arc::new(Mutex::new(observer)));
+            assert!(server.bind(maddr, TEST_PORT, ifaddr).is_ok());
+            assert!(server.start().is_ok());
+
+            // The other tests may send to the same group and port, so only the packets of this test are checked.
+            let mut pkt = Packet::new();
+            pkt.set_bytes(b"multicast_server_packet_info".to_vec());
+            assert!(server.notify(&pkt).is_ok());
+            thread::sleep(time::Duration::from_secs(1));
+
+            assert!(server.stop().is_ok());
+            let packets = packets.lock().unwrap();
+            let received = packets
+                .iter()
+                .find(|recv_pkt| recv_pkt.bytes() == pkt.bytes());
+            assert!(received.is_some(), "no packet on {}", ifaddr);
+            let received = received.unwrap();
+            assert_eq!(received.to().ip(), maddr);
+            assert_eq!(received.to().port(), TEST_PORT);
+            assert!(received.is_multicast());
+            assert!(0 < received.interface_index());
+            assert!(received.interface_name().is_some());
+            assert!(received.ttl().is_some());
+        }
+
+        for ifaddr in get_all_interfaces() {
+            if ifaddr.is_ipv4() {
+                test_multicast_server(TEST_MULTICAST_V4_ADDRESS, ifaddr);
+            } else {
+                test_multicast_server(TEST_MULTICAST_V6_ADDRESS, ifaddr);
+            }
+        }
+    }
+
+    #[test]
+    fn multicast_server_groups() {
+        fn test_multicast_server(maddr: IpAddr, maddr2: IpAddr, ifaddr: IpAddr) {
+            Logger::init();
+            let packets = Arc::new(Mutex::new(Vec::new()));
+
+            let mut server = MulticastServer::new();
+            let observer = TestPacketRecorder::new(packets.clone());
+            server.add_observer(Arc::new(Mutex::new(observer)));
+            assert!(server.join_group(maddr2).is_err());
+            assert!(server.bind(maddr, TEST_PORT, ifaddr).is_ok());
+            assert!(server.join_group(maddr2).is_ok());
+            assert!(server.join_group(maddr2).is_ok());
+            assert_eq!(server.groups(), &[maddr, maddr2]);
+            assert!(server.start().is_ok());
+
+            let mut pkt = Packet::new();
+            pkt.set_bytes(vec![0_u8; 1]);
+            assert!(server.notify_group(&maddr2, &pkt).is_ok());
+            thread::sleep(time::Duration::from_millis(500));
+            assert!(packets
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|pkt| pkt.to().ip() == maddr2));
+
+            // The left group is not received any more, but the other group stays joined.
+            assert!(server.leave_group(maddr2).is_ok());
+            assert!(server.leave_group(maddr2).is_ok());
+            assert_eq!(server.groups(), &[maddr]);
+            packets.lock().unwrap().clear();
+            assert!(server.notify_group(&maddr2, &pkt).is_ok());
+            assert!(server.notify_group(&maddr, &pkt).is_ok());
+            thread::sleep(time::Duration::from_millis(500));
+            let received = packets.lock().unwrap();
+            assert!(!received.iter().any(|pkt| pkt.to().ip() == maddr2));
+            assert!(received.iter().any(|pkt| pkt.to().ip() == maddr));
+
+            let other_maddr = if maddr.is_ipv4() {
+                TEST_MULTICAST_V6_ADDRESS
+            } else {
+                TEST_MULTICAST_V4_ADDRESS
+            };
+            assert!(matches!(
+                server.join_group(other_maddr),
+                Err(Error::AddressFamilyMismatch { .. })
+            ));
+
+            assert!(server.stop().is_ok());
+        }
+
+        for ifaddr in get_all_interfaces() {
+            if ifaddr.is_ipv4() {
+                test_multicast_server(
+                    TEST_MULTICAST_V4_ADDRESS,
+                    TEST_MULTICAST_V4_ADDRESS2,
+                    ifaddr,
+                );
+            } else {
+                test_multicast_server(
+                    TEST_MULTICAST_V6_ADDRESS,
+                    TEST_MULTICAST_V6_ADDRESS2,
+                    ifaddr,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn multicast_server_options() {
+        fn test_multicast_server(options: MulticastOptions, ttl: u8, loopback: bool) {
+            Logger::init();
+            let packets = Arc::new(Mutex::new(Vec::new()));
+            let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+            let mut server = MulticastServer::new();
+            server.set_multicast_options(options);
+            assert_eq!(server.multicast_options(), &options);
+            let observer = TestPacketRecorder::new(packets.clone());
+            server.add_observer(Arc::new(Mutex::new(observer)));
+            assert!(server
+                .bind(TEST_MULTICAST_V4_ADDRESS2, TEST_PORT, ifaddr)
+                .is_ok());
+            assert!(server.start().is_ok());
+
+            // The outgoing interface is the loopback interface which the server is bound to.
+            let mut pkt = Packet::new();
+            pkt.set_bytes(vec![0_u8; 1]);
+            assert!(server.notify(&pkt).is_ok());
+            thread::sleep(time::Duration::from_millis(500));
+            assert!(server.stop().is_ok());
+
+            let packets = packets.lock().unwrap();
+            assert!(!packets.is_empty());
+            for pkt in packets.iter() {
+                assert_eq!(pkt.to().ip(), TEST_MULTICAST_V4_ADDRESS2);
+                assert_eq!(pkt.from().ip(), ifaddr);
+                assert_eq!(pkt.ttl(), Some(ttl));
+            }
+
+            // NOTE: The loopback interface delivers the datagrams by itself regardless of IP_MULTICAST_LOOP,
+            // so the options applied to the sockets of both address families are read back from them.
+            for ifaddr in [
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ] {
+                let mut socket = UdpSocket::new();
+                assert!(socket.bind(SocketAddr::new(ifaddr, 0)).is_ok());
+                let ret = options.apply(&socket, &ifaddr);
+                assert!(ret.is_ok(), "{}", ret.err().unwrap());
+                assert_eq!(socket.multicast_ttl().unwrap(), ttl);
+                assert_eq!(socket.multicast_loop().unwrap(), loopback);
+            }
+        }
+
+        // The kernel defaults are the TTL or hop limit 1 and the loopback enabled.
+        test_multicast_server(MulticastOptions::new(), 1, true);
+        test_multicast_server(MulticastOptions::mdns(), 255, true);
+        test_multicast_server(MulticastOptions::ssdp(), 2, true);
+        test_multicast_server(MulticastOptions::ssdp().ttl(4), 4, true);
+        test_multicast_server(MulticastOptions::mdns().loopback(true), 255, true);
+        test_multicast_server(MulticastOptions::mdns().loopback(false), 255, false);
+    }
+
+    #[test]
+    fn multicast_server_interfaces() {
+        Logger::init();
+
+        let lo_addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ifaddr = match get_all_interfaces()
+            .into_iter()
+            .find(|ifaddr| ifaddr.is_ipv4() && !ifaddr.is_loopback())
+        {
+            Some(ifaddr) => ifaddr,
+            None => return,
+        };
+        let lo_packets = Arc::new(Mutex::new(Vec::new()));
+        let if_packets = Arc::new(Mutex::new(Vec::new()));
+
+        // The servers of the two interfaces are bound to the same wildcard address and port.
+        let mut lo_server = MulticastServer::new();
+        let observer = TestPacketRecorder::new(lo_packets.clone());
+        lo_server.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(lo_server
+            .bind(TEST_MULTICAST_V4_ADDRESS2, TEST_PORT, lo_addr)
+            .is_ok());
+        assert!(lo_server.start().is_ok());
+        let mut if_server = MulticastServer::new();
+        let observer = TestPacketRecorder::new(if_packets.clone());
+        if_server.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(if_server
+            .bind(TEST_MULTICAST_V4_ADDRESS2, TEST_PORT, ifaddr)
+            .is_ok());
+        assert!(if_server.start().is_ok());
+
+        // The datagram on the loopback interface is notified only once by the server of the interface.
+        // The other tests may send to the same group and port, so only the packets of this test are counted.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(b"multicast_server_interfaces".to_vec());
+        assert!(lo_server.notify(&pkt).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(lo_server.stop().is_ok());
+        assert!(if_server.stop().is_ok());
+
+        let count = |packets: &Arc<Mutex<Vec<Packet>>>| {
+            packets
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|recv_pkt| recv_pkt.bytes() == pkt.bytes())
+                .count()
+        };
+        assert_eq!(count(&lo_packets), 1);
+        assert_eq!(count(&if_packets), 0);
+    }
+
+    #[test]
+    fn multicast_server_leave_shared_group() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+        let other_packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut server = MulticastServer::new();
+        let observer = TestPacketRecorder::new(packets.clone());
+        server.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(server
+            .bind(TEST_MULTICAST_V4_ADDRESS, TEST_PORT, ifaddr)
+            .is_ok());
+        assert!(server.join_group(TEST_MULTICAST_V4_ADDRESS2).is_ok());
+        assert!(server.start().is_ok());
+        let mut other_server = MulticastServer::new();
+        let observer = TestPacketRecorder::new(other_packets.clone());
+        other_server.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(other_server
+            .bind(TEST_MULTICAST_V4_ADDRESS2, TEST_PORT, ifaddr)
+            .is_ok());
+        assert!(other_server.start().is_ok());
+
+        // The left group is not received any more even though the other server on the same port still joins it.
+        assert!(server.leave_group(TEST_MULTICAST_V4_ADDRESS2).is_ok());
+        let mut pkt = Packet::new();
+        pkt.set_bytes(b"multicast_server_leave_shared_group".to_vec());
+        assert!(other_server.notify(&pkt).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(server.stop().is_ok());
+        assert!(other_server.stop().is_ok());
+
+        let is_received = |packets: &Arc<Mutex<Vec<Packet>>>| {
+            packets
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|recv_pkt| recv_pkt.bytes() == pkt.bytes())
+        };
+        assert!(!is_received(&packets));
+        assert!(is_received(&other_packets));
+    }
 }
@@ -12,10 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
 
 use crate::net::notify_manager::NotifytManager;
+use crate::net::packet::Packet;
 
 /// Notifier notifies recieved transport Packets to the observers.
 pub type Notifier = Arc<Mutex<NotifytManager>>;
@@ -23,3 +26,32 @@ pub type Notifier = Arc<Mutex<NotifytManager>>;
 pub fn notifier_new() -> Notifier {
     Arc::new(Mutex::new(NotifytManager::new()))
 }
+
+thread_local! {
+    /// DISPATCH_RUNNING is the running flag of the receive loop which is dispatching on the current thread.
+    static DISPATCH_RUNNING: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// notifier_notify dispatches the packet to a snapshot of the observers, so that the observers can be added and removed while they are called.
+pub fn notifier_notify(notifier: &Notifier, msg: &Packet) {
+    let dispatcher = notifier.lock().unwrap().dispatcher();
+    dispatcher.dispatch(msg);
+}
+
+/// notifier_notify_while dispatches the packet as notifier_notify(), and lets the observers which wait on the receive loop,
+/// such as a PacketReceiver with OverflowPolicy::Block, give up once the running flag of the loop is cleared.
+pub(crate) fn notifier_notify_while(notifier: &Notifier, msg: &Packet, running: &Arc<AtomicBool>) {
+    let prev = DISPATCH_RUNNING.with(|current| current.replace(Some(running.clone())));
+    notifier_notify(notifier, msg);
+    DISPATCH_RUNNING.with(|current| current.replace(prev));
+}
+
+/// is_dispatch_running returns false if the receive loop which is dispatching on the current thread has been stopped.
+pub(crate) fn is_dispatch_running() -> bool {
+    DISPATCH_RUNNING.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .is_none_or(|running| running.load(Ordering::SeqCst))
+    })
+}
@@ -12,40 +12,125 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::net::observer::ObserverObject;
+use log::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+use crate::net::dispatch_options::{DispatchMode, DispatchOptions};
+use crate::net::dispatch_pool::DispatchPool;
+use crate::net::observer::{ObserverEntry, ObserverId, ObserverObject};
+use crate::net::observer_registry::ObserverRegistry;
 use crate::net::packet::Packet;
+use crate::net::packet_filter::PacketFilterObject;
+
+pub(crate) type Observers = Vec<(ObserverId, ObserverEntry)>;
 
-pub type Observers = Vec<ObserverObject>;
+type Failures = Arc<Mutex<Vec<ObserverId>>>;
 
 /// NotifytManager notifies recieved transport Packets to the observers.
 pub struct NotifytManager {
     observers: Observers,
+    options: DispatchOptions,
+    pool: Option<Arc<DispatchPool>>,
+    failures: Failures,
+}
+
+impl Default for NotifytManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NotifytManager {
     pub fn new() -> NotifytManager {
         NotifytManager {
             observers: Vec::new(),
+            options: DispatchOptions::new(),
+            pool: None,
+            failures: Arc::new(Mutex::new(Vec::new())),
         }
     }
-    pub fn add_observer(&mut self, observer: ObserverObject) -> bool {
-        self.observers.push(observer);
-        true
+
+    /// set_dispatch_options sets how the packets are dispatched to the observers.
+    /// The worker threads of DispatchMode::Pool are started by this call, and exit when the manager is dropped.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        if options.dispatch_mode() != self.options.dispatch_mode() {
+            self.pool = match options.dispatch_mode() {
+                DispatchMode::Inline => None,
+                DispatchMode::Pool(workers) => Some(Arc::new(DispatchPool::new(workers))),
+            };
+        }
+        self.options = options;
+    }
+
+    pub fn dispatch_options(&self) -> &DispatchOptions {
+        &self.options
     }
 
-    pub fn observers(&self) -> &Observers {
-        return &self.observers;
+    /// share_dispatch_options sets the dispatch options of the other manager, and shares its worker threads.
+    pub(crate) fn share_dispatch_options(&mut self, other: &NotifytManager) {
+        self.options = other.options;
+        self.pool = other.pool.clone();
     }
 
+    /// remove_observer removes the observer, and returns false if it has not been added.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        let n = self.observers.len();
+        self.observers.retain(|(added, _)| *added != id);
+        self.observers.len() != n
+    }
+
+    /// entries returns the live observers with their ids, so that the dropped and closed observers are not attached to new servers.
+    pub(crate) fn entries(&self) -> impl Iterator<Item = &(ObserverId, ObserverEntry)> {
+        self.observers
+            .iter()
+            .filter(|(_, observer)| observer.is_alive())
+    }
+
+    /// observers returns the live observers.
+    pub fn observers(&self) -> Vec<ObserverObject> {
+        self.observers
+            .iter()
+            .filter_map(|(_, observer)| observer.upgrade())
+            .collect()
+    }
+
+    /// notify dispatches the packet to the observers. The observers which panic are logged, and removed if DispatchOptions::remove_failing() is set.
     pub fn notify(&mut self, msg: &Packet) -> bool {
-        for observer in self.observers.iter() {
-            let mut observer = observer.lock().unwrap();
-            observer.packet_received(msg);
-        }
+        self.dispatcher().dispatch(msg);
+        self.prune();
         true
     }
 
+    /// dispatcher returns a snapshot of the live observers, which dispatches the packets without holding the manager.
+    pub(crate) fn dispatcher(&mut self) -> Dispatcher {
+        self.prune();
+        Dispatcher {
+            observers: self
+                .observers
+                .iter()
+                .filter_map(|(id, entry)| {
+                    entry
+                        .upgrade()
+                        .map(|observer| (*id, observer, entry.packet_filter().cloned()))
+                })
+                .collect(),
+            pool: self.pool.clone(),
+            failures: self.failures.clone(),
+        }
+    }
+
+    /// prune removes the weak observers which have been dropped, the closed observers, and the observers which have failed if they should be removed.
+    fn prune(&mut self) {
+        let failures: Vec<ObserverId> = self.failures.lock().unwrap().drain(..).collect();
+        if self.options.removes_failing() {
+            self.observers.retain(|(id, _)| !failures.contains(id));
+        }
+        self.observers.retain(|(_, observer)| observer.is_alive());
+    }
+
     pub fn num_observers(&mut self) -> usize {
+        self.prune();
         self.observers.len()
     }
 
@@ -57,3 +142,77 @@ impl NotifytManager {
         true
     }
 }
+
+impl ObserverRegistry for NotifytManager {
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        if let Some((id, _)) = self
+            .observers
+            .iter()
+            .find(|(_, added)| added.is_same(&observer))
+        {
+            return *id;
+        }
+        self.observers.push((id, observer));
+        id
+    }
+}
+
+/// Dispatcher is a snapshot of the observers, which calls each observer in isolation from the panics of the others.
+pub(crate) struct Dispatcher {
+    observers: Vec<(ObserverId, ObserverObject, Option<PacketFilterObject>)>,
+    pool: Option<Arc<DispatchPool>>,
+    failures: Failures,
+}
+
+impl Dispatcher {
+    /// dispatch notifies the packet to the observers whose filters match it. The filters are evaluated on the calling thread even in DispatchMode::Pool.
+    pub fn dispatch(self, msg: &Packet) {
+        let shared_msg = self.pool.as_ref().map(|_| Arc::new(msg.clone()));
+        for (id, observer, filter) in self.observers {
+            if let Some(filter) = filter {
+                if !filter_matches(id, &filter, msg, &self.failures) {
+                    continue;
+                }
+            }
+            match (&self.pool, &shared_msg) {
+                (Some(pool), Some(msg)) => {
+                    let msg = msg.clone();
+                    let failures = self.failures.clone();
+                    pool.execute(Box::new(move || {
+                        dispatch_observer(id, &observer, &msg, &failures);
+                    }));
+                }
+                _ => dispatch_observer(id, &observer, msg, &self.failures),
+            }
+        }
+    }
+}
+
+/// filter_matches evaluates the filter, and treats a panicking filter as a failure of the observer.
+fn filter_matches(
+    id: ObserverId,
+    filter: &PacketFilterObject,
+    msg: &Packet,
+    failures: &Failures,
+) -> bool {
+    match panic::catch_unwind(AssertUnwindSafe(|| filter.matches(msg))) {
+        Ok(matched) => matched,
+        Err(_) => {
+            error!("OBSERVER {:?} filter panicked ({})", id, msg);
+            failures.lock().unwrap().push(id);
+            false
+        }
+    }
+}
+
+fn dispatch_observer(id: ObserverId, observer: &ObserverObject, msg: &Packet, failures: &Failures) {
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        // The observer which has panicked before is called again unless it has been removed.
+        let mut observer = observer.lock().unwrap_or_else(|e| e.into_inner());
+        observer.packet_received(msg);
+    }));
+    if ret.is_err() {
+        error!("OBSERVER {:?} panicked ({})", id, msg);
+        failures.lock().unwrap().push(id);
+    }
+}
@@ -16,6 +16,8 @@
 
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 
 use crate::net::packet::Packet;
 use crate::net::Observer;
@@ -26,7 +28,7 @@ pub struct TestNotifyCounter {
 
 impl TestNotifyCounter {
     pub fn new(counter: Arc<Mutex<i32>>) -> TestNotifyCounter {
-        TestNotifyCounter { counter: counter }
+        TestNotifyCounter { counter }
     }
 }
 
@@ -37,12 +39,53 @@ impl Observer for TestNotifyCounter {
     }
 }
 
+pub struct TestPacketRecorder {
+    pub packets: Arc<Mutex<Vec<Packet>>>,
+}
+
+impl TestPacketRecorder {
+    pub fn new(packets: Arc<Mutex<Vec<Packet>>>) -> TestPacketRecorder {
+        TestPacketRecorder { packets }
+    }
+}
+
+impl Observer for TestPacketRecorder {
+    fn packet_received(&mut self, msg: &Packet) {
+        self.packets.lock().unwrap().push(msg.clone());
+    }
+}
+
+pub struct TestPanicObserver {}
+
+impl Observer for TestPanicObserver {
+    fn packet_received(&mut self, _msg: &Packet) {
+        panic!("TestPanicObserver");
+    }
+}
+
+pub struct TestSlowObserver {
+    pub delay: Duration,
+}
+
+impl Observer for TestSlowObserver {
+    fn packet_received(&mut self, _msg: &Packet) {
+        thread::sleep(self.delay);
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
+    use std::time::Instant;
+
+    use crate::net::dispatch_options::DispatchOptions;
     use crate::net::notify_manager::*;
     use crate::net::notify_manager_test::*;
+    use crate::net::observer::ObserverObject;
+    use crate::net::observer_registry::ObserverRegistry;
     use crate::net::packet::Packet;
+    use crate::net::packet_channel::*;
+    use crate::net::packet_filter::*;
 
     #[test]
     fn notify_manager_add() {
@@ -52,9 +95,12 @@ mod tests {
         let observer = Arc::new(Mutex::new(observer));
         let mut mgr = NotifytManager::new();
         assert!(mgr.start());
+        // The same observer is added only once.
+        let id = mgr.add_observer(observer.clone());
         for _ in 0..TEST_OBSERVER_COUNT {
-            assert!(mgr.add_observer(observer.clone()));
+            assert_eq!(mgr.add_observer(observer.clone()), id);
         }
+        assert_eq!(mgr.num_observers(), 1);
         assert!(mgr.stop());
     }
 
@@ -68,7 +114,7 @@ mod tests {
 
         for _ in 0..TEST_OBSERVER_COUNT {
             let observer = TestNotifyCounter::new(counter.clone());
-            assert!(mgr.add_observer(Arc::new(Mutex::new(observer))));
+            mgr.add_observer(Arc::new(Mutex::new(observer)));
         }
 
         let msg = Packet::new();
@@ -77,4 +123,142 @@ mod tests {
 
         assert!(mgr.stop());
     }
+
+    #[test]
+    fn notify_manager_remove() {
+        let counter = Arc::new(Mutex::new(0));
+        let mut mgr = NotifytManager::new();
+        let id = mgr.add_observer(Arc::new(Mutex::new(TestNotifyCounter::new(
+            counter.clone(),
+        ))));
+        let other_id = mgr.add_observer(Arc::new(Mutex::new(TestNotifyCounter::new(
+            counter.clone(),
+        ))));
+        assert_ne!(id, other_id);
+
+        assert!(mgr.remove_observer(id));
+        assert!(!mgr.remove_observer(id));
+        assert!(mgr.notify(&Packet::new()));
+        assert_eq!(*counter.lock().unwrap(), 1);
+        assert_eq!(mgr.num_observers(), 1);
+    }
+
+    #[test]
+    fn notify_manager_weak() {
+        let counter = Arc::new(Mutex::new(0));
+        let mut mgr = NotifytManager::new();
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestNotifyCounter::new(counter.clone())));
+        let id = mgr.add_weak_observer(Arc::downgrade(&observer));
+        assert_eq!(mgr.add_weak_observer(Arc::downgrade(&observer)), id);
+        assert_eq!(mgr.add_observer(observer.clone()), id);
+        assert_eq!(mgr.observers().len(), 1);
+
+        assert!(mgr.notify(&Packet::new()));
+        assert_eq!(*counter.lock().unwrap(), 1);
+
+        // The weak observer is pruned once the owner drops it.
+        drop(observer);
+        assert!(mgr.notify(&Packet::new()));
+        assert_eq!(*counter.lock().unwrap(), 1);
+        assert_eq!(mgr.num_observers(), 0);
+        assert!(!mgr.remove_observer(id));
+    }
+
+    #[test]
+    fn notify_manager_closed_entries() {
+        let mut mgr = NotifytManager::new();
+        let (receiver, observer) = packet_receiver_new(1, OverflowPolicy::DropOldest);
+        mgr.add_observer(observer);
+        let (rx, observer) = packet_channel_new();
+        mgr.add_observer(observer);
+        assert_eq!(mgr.entries().count(), 2);
+
+        // The closed observers are not attached to the servers which are bound later, even before they are pruned.
+        drop(receiver);
+        assert_eq!(mgr.entries().count(), 1);
+        drop(rx);
+        assert!(mgr.notify(&Packet::new()));
+        assert_eq!(mgr.entries().count(), 0);
+    }
+
+    #[test]
+    fn notify_manager_panic() {
+        let counter = Arc::new(Mutex::new(0));
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(Arc::new(Mutex::new(TestPanicObserver {})));
+        mgr.add_observer(Arc::new(Mutex::new(TestNotifyCounter::new(
+            counter.clone(),
+        ))));
+
+        // The panicking observer neither stops the others nor is removed by default.
+        assert!(mgr.notify(&Packet::new()));
+        assert!(mgr.notify(&Packet::new()));
+        assert_eq!(*counter.lock().unwrap(), 2);
+        assert_eq!(mgr.num_observers(), 2);
+
+        mgr.set_dispatch_options(DispatchOptions::new().remove_failing(true));
+        assert!(mgr.notify(&Packet::new()));
+        assert_eq!(*counter.lock().unwrap(), 3);
+        assert_eq!(mgr.num_observers(), 1);
+    }
+
+    #[test]
+    fn notify_manager_pool() {
+        let counter = Arc::new(Mutex::new(0));
+        let mut mgr = NotifytManager::new();
+        mgr.set_dispatch_options(DispatchOptions::pool(2).remove_failing(true));
+        mgr.add_observer(Arc::new(Mutex::new(TestSlowObserver {
+            delay: Duration::from_secs(2),
+        })));
+        mgr.add_observer(Arc::new(Mutex::new(TestPanicObserver {})));
+        mgr.add_observer(Arc::new(Mutex::new(TestNotifyCounter::new(
+            counter.clone(),
+        ))));
+
+        // The slow observer delays neither the receive thread nor the other observers.
+        let started = Instant::now();
+        assert!(mgr.notify(&Packet::new()));
+        while *counter.lock().unwrap() == 0 {
+            assert!(started.elapsed() < Duration::from_secs(1));
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // The panicking observer is removed once its failure has been reported by the worker.
+        while mgr.num_observers() != 2 {
+            assert!(started.elapsed() < Duration::from_secs(1));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn notify_manager_filter() {
+        let counter = Arc::new(Mutex::new(0));
+        let filtered_counter = Arc::new(Mutex::new(0));
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(Arc::new(Mutex::new(TestNotifyCounter::new(
+            counter.clone(),
+        ))));
+        mgr.add_observer_with_filter(
+            Arc::new(Mutex::new(TestNotifyCounter::new(filtered_counter.clone()))),
+            Arc::new(PrefixFilter::new(&[0x10, 0x81]).and(LengthFilter::max(4))),
+        );
+
+        assert!(mgr.notify(&Packet::from_bytes(&[0x10, 0x81, 0x00])));
+        assert!(mgr.notify(&Packet::from_bytes(&[0x10, 0x82, 0x00])));
+        assert!(mgr.notify(&Packet::from_bytes(&[0x10, 0x81, 0x00, 0x00, 0x00])));
+        assert_eq!(*counter.lock().unwrap(), 3);
+        assert_eq!(*filtered_counter.lock().unwrap(), 1);
+
+        // The observer whose filter panics is treated as failing.
+        mgr.set_dispatch_options(DispatchOptions::new().remove_failing(true));
+        mgr.add_observer_with_filter(
+            Arc::new(Mutex::new(TestNotifyCounter::new(counter.clone()))),
+            Arc::new(PredicateFilter::new(|_| panic!("PredicateFilter"))),
+        );
+        assert_eq!(mgr.num_observers(), 3);
+        assert!(mgr.notify(&Packet::new()));
+        assert_eq!(*counter.lock().unwrap(), 4);
+        assert_eq!(mgr.num_observers(), 2);
+    }
 }
@@ -12,14 +12,137 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::sync::{Arc, Weak};
 
 use crate::net::packet::Packet;
+use crate::net::packet_filter::PacketFilterObject;
 
 /// Observer defines a messaging packet interface between ECHONET-Lite nodes.
 pub trait Observer {
     fn packet_received(&mut self, msg: &Packet);
+
+    /// is_closed returns true once the observer can no longer receive packets, so that it is removed.
+    fn is_closed(&self) -> bool {
+        false
+    }
 }
 
 pub type ObserverObject = Arc<Mutex<dyn Observer + Send>>;
+
+pub type WeakObserverObject = Weak<Mutex<dyn Observer + Send>>;
+
+/// FnObserver calls the closure with the received Packets.
+struct FnObserver<F> {
+    f: F,
+}
+
+impl<F: FnMut(&Packet)> Observer for FnObserver<F> {
+    fn packet_received(&mut self, msg: &Packet) {
+        (self.f)(msg);
+    }
+}
+
+/// fn_observer_new returns an observer which calls the closure, so that it can be added with the other observer methods such as add_observer_with_filter().
+pub fn fn_observer_new<F>(f: F) -> ObserverObject
+where
+    F: FnMut(&Packet) + Send + 'static,
+{
+    Arc::new(Mutex::new(FnObserver { f }))
+}
+
+/// ObserverId is the handle of an added observer, which removes the observer with remove_observer().
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+impl ObserverId {
+    /// next returns a new id which is unique in the process.
+    pub(crate) fn next() -> ObserverId {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        ObserverId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// ObserverRef is an observer held by a strong or a weak reference.
+/// The weak observers are pruned once they have been dropped by the owners.
+#[derive(Clone)]
+enum ObserverRef {
+    Strong(ObserverObject),
+    Weak(WeakObserverObject),
+}
+
+impl ObserverRef {
+    /// upgrade returns the observer, or None if the weak observer has been dropped.
+    pub fn upgrade(&self) -> Option<ObserverObject> {
+        match self {
+            ObserverRef::Strong(observer) => Some(observer.clone()),
+            ObserverRef::Weak(observer) => observer.upgrade(),
+        }
+    }
+
+    /// is_same returns true if both refer to the same observer.
+    pub fn is_same(&self, other: &ObserverRef) -> bool {
+        self.as_ptr() == other.as_ptr()
+    }
+
+    fn as_ptr(&self) -> *const () {
+        match self {
+            ObserverRef::Strong(observer) => Arc::as_ptr(observer) as *const (),
+            ObserverRef::Weak(observer) => Weak::as_ptr(observer) as *const (),
+        }
+    }
+}
+
+/// ObserverEntry is an added observer with the filter which selects the packets to notify to it.
+// NOTE: ObserverEntry is public for ObserverRegistry::insert_observer(), but it is not exported,
+// so that ObserverRegistry can be implemented only in this crate.
+#[derive(Clone)]
+pub struct ObserverEntry {
+    observer: ObserverRef,
+    filter: Option<PacketFilterObject>,
+}
+
+impl ObserverEntry {
+    pub fn strong(observer: ObserverObject) -> ObserverEntry {
+        ObserverEntry {
+            observer: ObserverRef::Strong(observer),
+            filter: None,
+        }
+    }
+
+    pub fn weak(observer: WeakObserverObject) -> ObserverEntry {
+        ObserverEntry {
+            observer: ObserverRef::Weak(observer),
+            filter: None,
+        }
+    }
+
+    pub fn filter(mut self, filter: PacketFilterObject) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn upgrade(&self) -> Option<ObserverObject> {
+        self.observer.upgrade()
+    }
+
+    /// is_alive returns false if the weak observer has been dropped or the observer has been closed.
+    /// The observer which is being notified on another thread is regarded as alive.
+    pub fn is_alive(&self) -> bool {
+        match self.upgrade() {
+            Some(observer) => !observer
+                .try_lock()
+                .is_ok_and(|observer| observer.is_closed()),
+            None => false,
+        }
+    }
+
+    pub fn packet_filter(&self) -> Option<&PacketFilterObject> {
+        self.filter.as_ref()
+    }
+
+    pub fn is_same(&self, other: &ObserverEntry) -> bool {
+        self.observer.is_same(&other.observer)
+    }
+}
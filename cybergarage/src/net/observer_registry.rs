@@ -0,0 +1,89 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::mpsc::Receiver;
+
+use crate::net::observer::{
+    fn_observer_new, ObserverEntry, ObserverId, ObserverObject, WeakObserverObject,
+};
+use crate::net::packet::Packet;
+use crate::net::packet_channel::{
+    packet_channel_new, packet_receiver_new, OverflowPolicy, PacketReceiver,
+};
+use crate::net::packet_filter::PacketFilterObject;
+
+/// ObserverRegistry adds the observers which are notified of the received Packets.
+/// The servers and the managers implement only insert_observer(), and the other methods are built on it.
+/// It is exported from the net module, so that the glob import of the module brings the methods into scope.
+///
+/// ```
+/// use cybergarage::net::*;
+///
+/// let mut server = UnicastServer::new();
+/// let id = server.add_observer_fn(|msg| println!("{}", msg));
+/// assert!(server.remove_observer(id));
+/// ```
+pub trait ObserverRegistry {
+    /// insert_observer adds the observer with the specified id, so that the managers can share an id among their servers.
+    #[doc(hidden)]
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId;
+
+    /// add_observer adds the observer, and returns the id to remove it.
+    /// The observer which has already been added is not added again, and its id is returned.
+    fn add_observer(&mut self, observer: ObserverObject) -> ObserverId {
+        self.insert_observer(ObserverId::next(), ObserverEntry::strong(observer))
+    }
+
+    /// add_observer_with_filter adds the observer which is notified only of the packets matched by the filter.
+    /// The filter is not replaced if the observer has already been added.
+    fn add_observer_with_filter(
+        &mut self,
+        observer: ObserverObject,
+        filter: PacketFilterObject,
+    ) -> ObserverId {
+        self.insert_observer(
+            ObserverId::next(),
+            ObserverEntry::strong(observer).filter(filter),
+        )
+    }
+
+    /// add_weak_observer adds the observer without keeping it alive. It is removed automatically once the owner drops it.
+    fn add_weak_observer(&mut self, observer: WeakObserverObject) -> ObserverId {
+        self.insert_observer(ObserverId::next(), ObserverEntry::weak(observer))
+    }
+
+    /// add_observer_fn adds the closure as an observer, and returns the id to remove it.
+    fn add_observer_fn<F>(&mut self, f: F) -> ObserverId
+    where
+        F: FnMut(&Packet) + Send + 'static,
+        Self: Sized,
+    {
+        self.add_observer(fn_observer_new(f))
+    }
+
+    /// subscribe returns a Receiver of the received Packets, which can be received on another thread.
+    /// It is unsubscribed at the first packet after the Receiver is dropped.
+    fn subscribe(&mut self) -> Receiver<Packet> {
+        let (rx, observer) = packet_channel_new();
+        self.add_observer(observer);
+        rx
+    }
+
+    /// subscribe_bounded returns a PacketReceiver which queues up to the capacity of the received Packets. Dropping it unsubscribes it.
+    fn subscribe_bounded(&mut self, capacity: usize, policy: OverflowPolicy) -> PacketReceiver {
+        let (receiver, observer) = packet_receiver_new(capacity, policy);
+        self.add_observer(observer);
+        receiver
+    }
+}
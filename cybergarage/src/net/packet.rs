@@ -12,13 +12,39 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use nix::net::if_::if_indextoname;
 use std::fmt;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::SystemTime;
+
+use crate::net::tcp_connection::TcpConnection;
+
+/// PacketKind represents whether a packet was sent to a unicast, a multicast or a broadcast address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketKind {
+    Unicast,
+    Multicast,
+    Broadcast,
+}
 
 /// Packet represents a transport packet.
+#[derive(Clone)]
 pub struct Packet {
     data: Vec<u8>,
     from: SocketAddr,
+    to: SocketAddr,
+    interface_index: u32,
+    kind: PacketKind,
+    ttl: Option<u8>,
+    timestamp: SystemTime,
+    truncated: bool,
+    connection: Option<TcpConnection>,
+}
+
+impl Default for Packet {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Packet {
@@ -26,14 +52,20 @@ impl Packet {
         Packet {
             data: Vec::new(),
             from: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            to: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            interface_index: 0,
+            kind: PacketKind::Unicast,
+            ttl: None,
+            timestamp: SystemTime::now(),
+            truncated: false,
+            connection: None,
         }
     }
 
-    pub fn from_bytes(data: &Vec<u8>) -> Packet {
-        Packet {
-            data: data.clone(),
-            from: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
-        }
+    pub fn from_bytes(data: &[u8]) -> Packet {
+        let mut pkt = Packet::new();
+        pkt.data = data.to_vec();
+        pkt
     }
 
     pub fn set_from(&mut self, addr: SocketAddr) -> &mut Self {
@@ -52,24 +84,103 @@ impl Packet {
     pub fn bytes(&self) -> &Vec<u8> {
         &self.data
     }
-}
 
-impl Clone for Packet {
-    fn clone(&self) -> Packet {
-        Packet {
-            data: self.data.clone(),
-            from: self.from,
+    pub fn set_to(&mut self, addr: SocketAddr) -> &mut Self {
+        self.to = addr;
+        self
+    }
+
+    /// to returns the destination address of the received datagram, which is a multicast group address for multicast packets.
+    pub fn to(&self) -> SocketAddr {
+        self.to
+    }
+
+    pub fn set_interface_index(&mut self, index: u32) -> &mut Self {
+        self.interface_index = index;
+        self
+    }
+
+    /// interface_index returns the index of the interface which the packet arrived on, or 0 if it is unknown.
+    pub fn interface_index(&self) -> u32 {
+        self.interface_index
+    }
+
+    /// interface_name returns the name of the interface which the packet arrived on.
+    pub fn interface_name(&self) -> Option<String> {
+        if self.interface_index == 0 {
+            return None;
         }
+        let name = if_indextoname(self.interface_index).ok()?;
+        name.into_string().ok()
+    }
+
+    pub fn set_kind(&mut self, kind: PacketKind) -> &mut Self {
+        self.kind = kind;
+        self
+    }
+
+    /// kind returns whether the received datagram was sent to a unicast, a multicast or a broadcast address.
+    pub fn kind(&self) -> PacketKind {
+        self.kind
+    }
+
+    pub fn is_unicast(&self) -> bool {
+        self.kind == PacketKind::Unicast
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.kind == PacketKind::Multicast
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        self.kind == PacketKind::Broadcast
+    }
+
+    pub fn set_ttl(&mut self, ttl: u8) -> &mut Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// ttl returns the IPv4 TTL or the IPv6 hop limit which the received datagram carried.
+    pub fn ttl(&self) -> Option<u8> {
+        self.ttl
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: SystemTime) -> &mut Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// timestamp returns the time when the packet was received, or created if it was not received.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    pub fn set_truncated(&mut self, truncated: bool) -> &mut Self {
+        self.truncated = truncated;
+        self
+    }
+
+    /// is_truncated returns true if the datagram was larger than the receive buffer and its tail was discarded.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn set_connection(&mut self, connection: TcpConnection) -> &mut Self {
+        self.connection = Some(connection);
+        self
+    }
+
+    /// connection returns the TCP connection which the message was received on, or None for the datagrams.
+    pub fn connection(&self) -> Option<&TcpConnection> {
+        self.connection.as_ref()
     }
 }
 
 impl fmt::Display for Packet {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for b in self.bytes() {
-            let res = f.write_fmt(format_args!("{:02X}", b));
-            if res.is_err() {
-                return res;
-            }
+            f.write_fmt(format_args!("{:02X}", b))?;
         }
         Ok(())
     }
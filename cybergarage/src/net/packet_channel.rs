@@ -0,0 +1,233 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::net::default::RECV_TIMEOUT;
+use crate::net::notifier::is_dispatch_running;
+use crate::net::observer::{Observer, ObserverObject};
+use crate::net::packet::Packet;
+
+/// OverflowPolicy selects what a bounded PacketReceiver does with a received packet when it is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// DropOldest discards the oldest queued packet to make room for the new one.
+    #[default]
+    DropOldest,
+    /// DropNewest discards the new packet.
+    DropNewest,
+    /// Block waits until the consumer takes a packet. It stops the thread which dispatches the packets,
+    /// so it is intended for DispatchMode::Pool or for consumers which keep receiving.
+    /// The packet is discarded if the server is stopped while it waits.
+    Block,
+}
+
+/// ChannelObserver forwards the received Packets to a Receiver.
+struct ChannelObserver {
+    tx: Sender<Packet>,
+    closed: bool,
+}
+
+impl Observer for ChannelObserver {
+    fn packet_received(&mut self, msg: &Packet) {
+        if self.tx.send(msg.clone()).is_err() {
+            self.closed = true;
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+/// packet_channel_new returns a new Receiver and the observer which feeds it.
+/// The observer is closed by the first packet after the Receiver is dropped, and the notifier removes it.
+pub fn packet_channel_new() -> (Receiver<Packet>, ObserverObject) {
+    let (tx, rx) = channel();
+    let observer: ObserverObject = Arc::new(Mutex::new(ChannelObserver { tx, closed: false }));
+    (rx, observer)
+}
+
+struct ChannelState {
+    queue: VecDeque<Packet>,
+    closed: bool,
+    disconnected: bool,
+    dropped: u64,
+}
+
+struct BoundedChannel {
+    state: Mutex<ChannelState>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+impl BoundedChannel {
+    fn push(&self, msg: &Packet) {
+        let mut state = self.state.lock().unwrap();
+        while self.capacity <= state.queue.len() && !state.closed {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    state.queue.pop_front();
+                    state.dropped += 1;
+                }
+                OverflowPolicy::DropNewest => {
+                    state.dropped += 1;
+                    return;
+                }
+                OverflowPolicy::Block => {
+                    // NOTE: The wait is polled, since the server waits for the receive loop blocked here when it stops.
+                    if !is_dispatch_running() {
+                        state.dropped += 1;
+                        return;
+                    }
+                    state = self.not_full.wait_timeout(state, RECV_TIMEOUT).unwrap().0;
+                }
+            }
+        }
+        if state.closed {
+            return;
+        }
+        state.queue.push_back(msg.clone());
+        self.not_empty.notify_one();
+    }
+}
+
+/// BoundedObserver forwards the received Packets to a PacketReceiver.
+struct BoundedObserver {
+    channel: Arc<BoundedChannel>,
+}
+
+impl Observer for BoundedObserver {
+    fn packet_received(&mut self, msg: &Packet) {
+        self.channel.push(msg);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.channel.state.lock().unwrap().closed
+    }
+}
+
+impl Drop for BoundedObserver {
+    fn drop(&mut self) {
+        // Release the consumer which waits for a packet, since no more packets arrive.
+        self.channel.state.lock().unwrap().disconnected = true;
+        self.channel.not_empty.notify_all();
+    }
+}
+
+/// PacketReceiver is a bounded queue of the received Packets, which handles the overflow as specified by the OverflowPolicy.
+/// Its observer is closed once the receiver is dropped, so that dropping the receiver unsubscribes it.
+pub struct PacketReceiver {
+    channel: Arc<BoundedChannel>,
+}
+
+impl PacketReceiver {
+    /// recv waits for a packet, and returns None once the queue is empty and the observer has been removed or dropped with the servers.
+    pub fn recv(&self) -> Option<Packet> {
+        let mut state = self.channel.state.lock().unwrap();
+        loop {
+            if let Some(msg) = state.queue.pop_front() {
+                self.channel.not_full.notify_one();
+                return Some(msg);
+            }
+            if state.disconnected {
+                return None;
+            }
+            state = self.channel.not_empty.wait(state).unwrap();
+        }
+    }
+
+    /// recv_timeout waits for a packet, and returns None if no packet has been received within the timeout or the observer has been removed.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Packet> {
+        let state = self.channel.state.lock().unwrap();
+        let (mut state, _) = self
+            .channel
+            .not_empty
+            .wait_timeout_while(state, timeout, |state| {
+                state.queue.is_empty() && !state.disconnected
+            })
+            .unwrap();
+        let msg = state.queue.pop_front();
+        if msg.is_some() {
+            self.channel.not_full.notify_one();
+        }
+        msg
+    }
+
+    /// try_recv returns a queued packet without waiting, or None if there is no packet.
+    pub fn try_recv(&self) -> Option<Packet> {
+        let msg = self.channel.state.lock().unwrap().queue.pop_front();
+        if msg.is_some() {
+            self.channel.not_full.notify_one();
+        }
+        msg
+    }
+
+    pub fn len(&self) -> usize {
+        self.channel.state.lock().unwrap().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.channel.capacity
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.channel.policy
+    }
+
+    /// dropped returns the number of the packets which have been discarded by the overflow policy.
+    pub fn dropped(&self) -> u64 {
+        self.channel.state.lock().unwrap().dropped
+    }
+}
+
+impl Drop for PacketReceiver {
+    fn drop(&mut self) {
+        // Release the dispatching thread which is blocked by OverflowPolicy::Block.
+        self.channel.state.lock().unwrap().closed = true;
+        self.channel.not_full.notify_all();
+    }
+}
+
+/// packet_receiver_new returns a new PacketReceiver which queues up to the capacity, at least one, of the packets, and the observer which feeds it.
+pub fn packet_receiver_new(
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> (PacketReceiver, ObserverObject) {
+    let channel = Arc::new(BoundedChannel {
+        state: Mutex::new(ChannelState {
+            queue: VecDeque::new(),
+            closed: false,
+            disconnected: false,
+            dropped: 0,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        capacity: capacity.max(1),
+        policy,
+    });
+    let observer: ObserverObject = Arc::new(Mutex::new(BoundedObserver {
+        channel: channel.clone(),
+    }));
+    (PacketReceiver { channel }, observer)
+}
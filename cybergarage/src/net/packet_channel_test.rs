@@ -0,0 +1,155 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::net::notify_manager::NotifytManager;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+    use crate::net::packet_channel::*;
+
+    fn notify_bytes(mgr: &mut NotifytManager, bytes: &[&[u8]]) {
+        for bytes in bytes {
+            assert!(mgr.notify(&Packet::from_bytes(bytes)));
+        }
+    }
+
+    #[test]
+    fn packet_channel() {
+        let (rx, observer) = packet_channel_new();
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(observer);
+        notify_bytes(&mut mgr, &[b"1", b"2"]);
+        assert_eq!(rx.recv().unwrap().bytes(), b"1");
+        assert_eq!(rx.recv().unwrap().bytes(), b"2");
+        assert!(rx.try_recv().is_err());
+
+        // The observer is unsubscribed by the first packet to the dropped receiver.
+        assert_eq!(mgr.num_observers(), 1);
+        drop(rx);
+        assert_eq!(mgr.num_observers(), 1);
+        notify_bytes(&mut mgr, &[b"3"]);
+        assert_eq!(mgr.num_observers(), 0);
+    }
+
+    #[test]
+    fn packet_channel_subscribe() {
+        let mut mgr = NotifytManager::new();
+        let rx = mgr.subscribe();
+        notify_bytes(&mut mgr, &[b"1"]);
+        assert_eq!(rx.recv().unwrap().bytes(), b"1");
+
+        // Dropping the receiver unsubscribes it.
+        drop(rx);
+        notify_bytes(&mut mgr, &[b"2"]);
+        assert_eq!(mgr.num_observers(), 0);
+    }
+
+    #[test]
+    fn packet_receiver_drop_oldest() {
+        let (receiver, observer) = packet_receiver_new(2, OverflowPolicy::DropOldest);
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(observer);
+        notify_bytes(&mut mgr, &[b"1", b"2", b"3"]);
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(receiver.dropped(), 1);
+        assert_eq!(receiver.recv().unwrap().bytes(), b"2");
+        assert_eq!(receiver.try_recv().unwrap().bytes(), b"3");
+        assert!(receiver.try_recv().is_none());
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_none());
+
+        // The receiver is unsubscribed once it is dropped.
+        assert_eq!(mgr.num_observers(), 1);
+        drop(receiver);
+        assert_eq!(mgr.num_observers(), 0);
+    }
+
+    #[test]
+    fn packet_receiver_drop_newest() {
+        let (receiver, observer) = packet_receiver_new(2, OverflowPolicy::DropNewest);
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(observer);
+        notify_bytes(&mut mgr, &[b"1", b"2", b"3"]);
+        assert_eq!(receiver.dropped(), 1);
+        assert_eq!(receiver.recv().unwrap().bytes(), b"1");
+        assert_eq!(receiver.recv().unwrap().bytes(), b"2");
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn packet_receiver_block() {
+        let (receiver, observer) = packet_receiver_new(1, OverflowPolicy::Block);
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(observer);
+
+        // The second packet waits until the first one has been received.
+        let notifier = thread::spawn(move || {
+            notify_bytes(&mut mgr, &[b"1", b"2"]);
+        });
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(receiver.len(), 1);
+        assert!(!notifier.is_finished());
+        assert_eq!(receiver.recv().unwrap().bytes(), b"1");
+        assert_eq!(
+            receiver
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .bytes(),
+            b"2"
+        );
+        assert!(notifier.join().is_ok());
+        assert_eq!(receiver.dropped(), 0);
+    }
+
+    #[test]
+    fn packet_receiver_block_drop() {
+        let (receiver, observer) = packet_receiver_new(1, OverflowPolicy::Block);
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(observer);
+        let notifier = thread::spawn(move || {
+            notify_bytes(&mut mgr, &[b"1", b"2"]);
+        });
+        thread::sleep(Duration::from_millis(200));
+
+        // Dropping the receiver releases the blocked notifier.
+        drop(receiver);
+        assert!(notifier.join().is_ok());
+    }
+
+    #[test]
+    fn packet_receiver_disconnect() {
+        // The queued packets are received after the observer has been removed, and then recv() returns None.
+        let (receiver, observer) = packet_receiver_new(2, OverflowPolicy::DropOldest);
+        let mut mgr = NotifytManager::new();
+        let id = mgr.add_observer(observer);
+        notify_bytes(&mut mgr, &[b"1"]);
+        assert!(mgr.remove_observer(id));
+        assert_eq!(receiver.recv().unwrap().bytes(), b"1");
+        assert!(receiver.recv().is_none());
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_none());
+
+        // Dropping the manager releases the consumer which waits for a packet.
+        let (receiver, observer) = packet_receiver_new(2, OverflowPolicy::DropOldest);
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(observer);
+        let consumer = thread::spawn(move || receiver.recv());
+        thread::sleep(Duration::from_millis(200));
+        drop(mgr);
+        assert!(consumer.join().unwrap().is_none());
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use crate::net::cidr::Cidr;
+use crate::net::packet::Packet;
+
+/// PacketFilter selects the received packets which are notified to an observer.
+pub trait PacketFilter {
+    fn matches(&self, msg: &Packet) -> bool;
+
+    /// and returns the filter which matches the packets matched by both filters.
+    fn and<F>(self, other: F) -> AndFilter
+    where
+        Self: Sized + Send + Sync + 'static,
+        F: PacketFilter + Send + Sync + 'static,
+    {
+        AndFilter::new(vec![Arc::new(self), Arc::new(other)])
+    }
+
+    /// or returns the filter which matches the packets matched by either filter.
+    fn or<F>(self, other: F) -> OrFilter
+    where
+        Self: Sized + Send + Sync + 'static,
+        F: PacketFilter + Send + Sync + 'static,
+    {
+        OrFilter::new(vec![Arc::new(self), Arc::new(other)])
+    }
+
+    /// not returns the filter which matches the packets not matched by this filter.
+    fn not(self) -> NotFilter
+    where
+        Self: Sized + Send + Sync + 'static,
+    {
+        NotFilter::new(Arc::new(self))
+    }
+}
+
+pub type PacketFilterObject = Arc<dyn PacketFilter + Send + Sync>;
+
+/// SourceCidrFilter matches the packets sent from the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceCidrFilter {
+    network: Cidr,
+}
+
+impl SourceCidrFilter {
+    pub fn new(network: Cidr) -> SourceCidrFilter {
+        SourceCidrFilter { network }
+    }
+}
+
+impl PacketFilter for SourceCidrFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        self.network.contains(&msg.from().ip())
+    }
+}
+
+/// SourcePortFilter matches the packets sent from the port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourcePortFilter {
+    port: u16,
+}
+
+impl SourcePortFilter {
+    pub fn new(port: u16) -> SourcePortFilter {
+        SourcePortFilter { port }
+    }
+}
+
+impl PacketFilter for SourcePortFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        msg.from().port() == self.port
+    }
+}
+
+/// DestinationGroupFilter matches the multicast packets sent to the group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DestinationGroupFilter {
+    group: IpAddr,
+}
+
+impl DestinationGroupFilter {
+    pub fn new(group: IpAddr) -> DestinationGroupFilter {
+        DestinationGroupFilter { group }
+    }
+}
+
+impl PacketFilter for DestinationGroupFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        msg.is_multicast() && msg.to().ip() == self.group
+    }
+}
+
+/// InterfaceIndexFilter matches the packets received on the interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterfaceIndexFilter {
+    if_index: u32,
+}
+
+impl InterfaceIndexFilter {
+    pub fn new(if_index: u32) -> InterfaceIndexFilter {
+        InterfaceIndexFilter { if_index }
+    }
+}
+
+impl PacketFilter for InterfaceIndexFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        msg.interface_index() == self.if_index
+    }
+}
+
+/// PrefixFilter matches the packets which start with the bytes, such as a magic number or a protocol header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrefixFilter {
+    prefix: Vec<u8>,
+}
+
+impl PrefixFilter {
+    pub fn new(prefix: &[u8]) -> PrefixFilter {
+        PrefixFilter {
+            prefix: prefix.to_vec(),
+        }
+    }
+}
+
+impl PacketFilter for PrefixFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        msg.bytes().starts_with(&self.prefix)
+    }
+}
+
+/// LengthFilter matches the packets whose lengths are in the range, including both ends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LengthFilter {
+    min: usize,
+    max: usize,
+}
+
+impl LengthFilter {
+    pub fn new(min: usize, max: usize) -> LengthFilter {
+        LengthFilter { min, max }
+    }
+
+    pub fn min(min: usize) -> LengthFilter {
+        LengthFilter::new(min, usize::MAX)
+    }
+
+    pub fn max(max: usize) -> LengthFilter {
+        LengthFilter::new(0, max)
+    }
+}
+
+impl PacketFilter for LengthFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        (self.min..=self.max).contains(&msg.bytes().len())
+    }
+}
+
+/// PredicateFilter matches the packets for which the closure returns true.
+pub struct PredicateFilter {
+    predicate: Box<dyn Fn(&Packet) -> bool + Send + Sync>,
+}
+
+impl PredicateFilter {
+    pub fn new<F>(predicate: F) -> PredicateFilter
+    where
+        F: Fn(&Packet) -> bool + Send + Sync + 'static,
+    {
+        PredicateFilter {
+            predicate: Box::new(predicate),
+        }
+    }
+}
+
+impl PacketFilter for PredicateFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        (self.predicate)(msg)
+    }
+}
+
+/// AndFilter matches the packets matched by all filters. It matches all packets if it has no filter.
+#[derive(Clone)]
+pub struct AndFilter {
+    filters: Vec<PacketFilterObject>,
+}
+
+impl AndFilter {
+    pub fn new(filters: Vec<PacketFilterObject>) -> AndFilter {
+        AndFilter { filters }
+    }
+}
+
+impl PacketFilter for AndFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        self.filters.iter().all(|filter| filter.matches(msg))
+    }
+}
+
+/// OrFilter matches the packets matched by any filter. It matches no packet if it has no filter.
+#[derive(Clone)]
+pub struct OrFilter {
+    filters: Vec<PacketFilterObject>,
+}
+
+impl OrFilter {
+    pub fn new(filters: Vec<PacketFilterObject>) -> OrFilter {
+        OrFilter { filters }
+    }
+}
+
+impl PacketFilter for OrFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        self.filters.iter().any(|filter| filter.matches(msg))
+    }
+}
+
+/// NotFilter matches the packets not matched by the filter.
+#[derive(Clone)]
+pub struct NotFilter {
+    filter: PacketFilterObject,
+}
+
+impl NotFilter {
+    pub fn new(filter: PacketFilterObject) -> NotFilter {
+        NotFilter { filter }
+    }
+}
+
+impl PacketFilter for NotFilter {
+    fn matches(&self, msg: &Packet) -> bool {
+        !self.filter.matches(msg)
+    }
+}
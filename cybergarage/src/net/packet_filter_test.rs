@@ -0,0 +1,107 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::{IpAddr, SocketAddr};
+
+    use crate::net::cidr::Cidr;
+    use crate::net::packet::{Packet, PacketKind};
+    use crate::net::packet_filter::*;
+
+    fn test_packet(from: &str, to: &str, bytes: &[u8]) -> Packet {
+        let mut pkt = Packet::from_bytes(bytes);
+        pkt.set_from(from.parse::<SocketAddr>().unwrap());
+        pkt.set_to(to.parse::<SocketAddr>().unwrap());
+        pkt.set_interface_index(2);
+        pkt
+    }
+
+    #[test]
+    fn packet_filter_source() {
+        let pkt = test_packet("192.0.2.2:3610", "192.0.2.1:3610", b"EL");
+
+        let cidr: Cidr = "192.0.2.0/24".parse().unwrap();
+        assert!(SourceCidrFilter::new(cidr).matches(&pkt));
+        let cidr: Cidr = "198.51.100.0/24".parse().unwrap();
+        assert!(!SourceCidrFilter::new(cidr).matches(&pkt));
+
+        assert!(SourcePortFilter::new(3610).matches(&pkt));
+        assert!(!SourcePortFilter::new(5353).matches(&pkt));
+
+        assert!(InterfaceIndexFilter::new(2).matches(&pkt));
+        assert!(!InterfaceIndexFilter::new(1).matches(&pkt));
+    }
+
+    #[test]
+    fn packet_filter_group() {
+        let group: IpAddr = "224.0.23.0".parse().unwrap();
+        let mut pkt = test_packet("192.0.2.2:3610", "224.0.23.0:3610", b"EL");
+
+        // The packets sent to the group address are not matched unless they have been received as multicast.
+        assert!(!DestinationGroupFilter::new(group).matches(&pkt));
+        pkt.set_kind(PacketKind::Multicast);
+        assert!(DestinationGroupFilter::new(group).matches(&pkt));
+        let other_group: IpAddr = "224.0.0.251".parse().unwrap();
+        assert!(!DestinationGroupFilter::new(other_group).matches(&pkt));
+    }
+
+    #[test]
+    fn packet_filter_bytes() {
+        let pkt = test_packet(
+            "192.0.2.2:3610",
+            "192.0.2.1:3610",
+            &[0x10, 0x81, 0x00, 0x01],
+        );
+
+        assert!(PrefixFilter::new(&[0x10, 0x81]).matches(&pkt));
+        assert!(PrefixFilter::new(&[]).matches(&pkt));
+        assert!(!PrefixFilter::new(&[0x10, 0x82]).matches(&pkt));
+        assert!(!PrefixFilter::new(&[0x10, 0x81, 0x00, 0x01, 0x00]).matches(&pkt));
+
+        assert!(LengthFilter::min(4).matches(&pkt));
+        assert!(!LengthFilter::min(5).matches(&pkt));
+        assert!(LengthFilter::max(4).matches(&pkt));
+        assert!(!LengthFilter::max(3).matches(&pkt));
+        assert!(LengthFilter::new(2, 8).matches(&pkt));
+
+        assert!(PredicateFilter::new(|pkt| pkt.bytes()[3] == 0x01).matches(&pkt));
+        assert!(!PredicateFilter::new(|pkt| pkt.is_truncated()).matches(&pkt));
+    }
+
+    #[test]
+    fn packet_filter_compose() {
+        let pkt = test_packet("192.0.2.2:3610", "192.0.2.1:3610", &[0x10, 0x81]);
+
+        let filter = PrefixFilter::new(&[0x10, 0x81]).and(SourcePortFilter::new(3610));
+        assert!(filter.matches(&pkt));
+        let filter = PrefixFilter::new(&[0x10, 0x81]).and(SourcePortFilter::new(5353));
+        assert!(!filter.matches(&pkt));
+
+        let filter = SourcePortFilter::new(5353).or(LengthFilter::max(2));
+        assert!(filter.matches(&pkt));
+        let filter = SourcePortFilter::new(5353).or(LengthFilter::min(3));
+        assert!(!filter.matches(&pkt));
+
+        assert!(!SourcePortFilter::new(3610).not().matches(&pkt));
+        assert!(SourcePortFilter::new(5353)
+            .or(SourcePortFilter::new(3610).not())
+            .not()
+            .matches(&pkt));
+
+        assert!(AndFilter::new(Vec::new()).matches(&pkt));
+        assert!(!OrFilter::new(Vec::new()).matches(&pkt));
+    }
+}
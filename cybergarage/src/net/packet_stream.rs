@@ -0,0 +1,58 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_stream::Stream;
+
+use crate::net::observer::{Observer, ObserverObject};
+use crate::net::packet::Packet;
+
+/// PacketStream is a Stream of the Packets received by the async servers.
+pub struct PacketStream {
+    rx: UnboundedReceiver<Packet>,
+}
+
+impl Stream for PacketStream {
+    type Item = Packet;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Packet>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// StreamObserver forwards the received Packets to a PacketStream.
+struct StreamObserver {
+    tx: UnboundedSender<Packet>,
+}
+
+impl Observer for StreamObserver {
+    fn packet_received(&mut self, msg: &Packet) {
+        let _ = self.tx.send(msg.clone());
+    }
+
+    fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+/// packet_stream_new returns a new PacketStream and the observer which feeds it. The observer is closed once the PacketStream is dropped.
+pub fn packet_stream_new() -> (PacketStream, ObserverObject) {
+    let (tx, rx) = unbounded_channel();
+    let observer: ObserverObject = Arc::new(Mutex::new(StreamObserver { tx }));
+    (PacketStream { rx }, observer)
+}
@@ -0,0 +1,38 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+
+    use tokio_stream::StreamExt;
+
+    use crate::net::notify_manager::NotifytManager;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+    use crate::net::packet_stream::*;
+
+    #[tokio::test]
+    async fn packet_stream() {
+        let (mut stream, observer) = packet_stream_new();
+        let mut mgr = NotifytManager::new();
+        mgr.add_observer(observer);
+        assert!(mgr.notify(&Packet::from_bytes(b"1")));
+        assert_eq!(stream.next().await.unwrap().bytes(), b"1");
+
+        // Dropping the stream unsubscribes it.
+        assert_eq!(mgr.num_observers(), 1);
+        drop(stream);
+        assert_eq!(mgr.num_observers(), 0);
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+
+use crate::net::cidr::Cidr;
+
+/// SendFallback decides how a destination is sent to when it is not on the subnet of any bound interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SendFallback {
+    /// Send from the first bound interface of the same address family, and let the kernel route the datagram.
+    #[default]
+    FirstInterface,
+    /// Send from all bound interfaces of the same address family.
+    AllInterfaces,
+    /// Do not send, and return Error::NoRoute.
+    Fail,
+}
+
+/// Route is a bound interface which a datagram can be sent from.
+pub struct Route {
+    pub ifaddr: SocketAddr,
+    pub network: Option<Cidr>,
+    pub if_index: u32,
+}
+
+/// find_route returns the position of the route which reaches the destination directly.
+/// A scoped IPv6 destination is routed by its scope id, a local destination by its address,
+/// and any other destination by the longest matching interface prefix.
+pub fn find_route(routes: &[Route], to_addr: &SocketAddr) -> Option<usize> {
+    let to_ip = to_addr.ip();
+    if let SocketAddr::V6(to_addr_v6) = to_addr {
+        if to_addr_v6.scope_id() != 0 {
+            if let Some(n) = routes.iter().position(|route| {
+                is_same_family(&route.ifaddr, to_addr) && route.if_index == to_addr_v6.scope_id()
+            }) {
+                return Some(n);
+            }
+        }
+    }
+
+    if let Some(n) = routes.iter().position(|route| route.ifaddr.ip() == to_ip) {
+        return Some(n);
+    }
+
+    routes
+        .iter()
+        .enumerate()
+        .filter(|(_, route)| is_same_family(&route.ifaddr, to_addr))
+        .filter_map(|(n, route)| match route.network {
+            Some(network) if network.contains(&to_ip) => Some((n, network.prefix_len())),
+            _ => None,
+        })
+        .max_by(|(a_n, a_len), (b_n, b_len)| a_len.cmp(b_len).then(b_n.cmp(a_n)))
+        .map(|(n, _)| n)
+}
+
+/// is_same_family returns true if the interface address can send to the destination.
+pub fn is_same_family(ifaddr: &SocketAddr, to_addr: &SocketAddr) -> bool {
+    ifaddr.is_ipv4() == to_addr.is_ipv4()
+}
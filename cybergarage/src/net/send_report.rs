@@ -0,0 +1,79 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+
+use crate::net::error::{Error, Result};
+
+/// SendReport is the outcome of sending a datagram from each of the bound interfaces.
+#[derive(Debug, Default)]
+pub struct SendReport {
+    results: Vec<(SocketAddr, Result<usize>)>,
+}
+
+impl SendReport {
+    pub fn new() -> SendReport {
+        SendReport {
+            results: Vec::new(),
+        }
+    }
+
+    /// add records the outcome of sending from the interface address.
+    pub fn add(&mut self, ifaddr: SocketAddr, result: Result<usize>) {
+        self.results.push((ifaddr, result));
+    }
+
+    /// results returns the outcome for each interface in the order they were sent from.
+    pub fn results(&self) -> &[(SocketAddr, Result<usize>)] {
+        &self.results
+    }
+
+    /// successes returns the interface addresses which sent the datagram.
+    pub fn successes(&self) -> Vec<SocketAddr> {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_ok())
+            .map(|(ifaddr, _)| *ifaddr)
+            .collect()
+    }
+
+    /// failures returns the interface addresses which could not send the datagram, with their errors.
+    pub fn failures(&self) -> Vec<(SocketAddr, &Error)> {
+        self.results
+            .iter()
+            .filter_map(|(ifaddr, result)| match result {
+                Ok(_) => None,
+                Err(e) => Some((*ifaddr, e)),
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// is_ok returns true if the datagram was sent from at least one interface and no interface failed.
+    pub fn is_ok(&self) -> bool {
+        !self.results.is_empty() && self.results.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// into_results returns the outcome for each interface, consuming the report.
+    pub fn into_results(self) -> Vec<(SocketAddr, Result<usize>)> {
+        self.results
+    }
+}
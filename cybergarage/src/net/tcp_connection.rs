@@ -0,0 +1,108 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use std::fmt;
+use std::io::Write;
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use crate::net::error::{Error, Result};
+use crate::net::framer::FramerObject;
+
+/// TcpConnection is a handle of an accepted or connected TCP stream, which is attached to the received Packets to send the replies.
+/// The clones share the same stream.
+#[derive(Clone)]
+pub struct TcpConnection {
+    inner: Arc<ConnectionInner>,
+}
+
+struct ConnectionInner {
+    stream: TcpStream,
+    writer: Mutex<()>,
+    framer: FramerObject,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+}
+
+impl TcpConnection {
+    pub(crate) fn new(stream: TcpStream, framer: FramerObject) -> Result<TcpConnection> {
+        let local_addr = stream.local_addr()?;
+        let peer_addr = stream.peer_addr()?;
+        Ok(TcpConnection {
+            inner: Arc::new(ConnectionInner {
+                stream,
+                writer: Mutex::new(()),
+                framer,
+                local_addr,
+                peer_addr,
+            }),
+        })
+    }
+
+    pub(crate) fn stream(&self) -> &TcpStream {
+        &self.inner.stream
+    }
+
+    pub(crate) fn framer(&self) -> &FramerObject {
+        &self.inner.framer
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.inner.local_addr
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.inner.peer_addr
+    }
+
+    /// send writes the message framed by the framer of the connection, and returns the length of the message.
+    pub fn send(&self, msg: &[u8]) -> Result<usize> {
+        let frame = self.inner.framer.encode(msg)?;
+        info!(
+            "SEND {} -> {} ({})",
+            self.inner.local_addr,
+            self.inner.peer_addr,
+            hex::encode_upper(msg),
+        );
+        let _writer = self.inner.writer.lock().unwrap();
+        (&self.inner.stream)
+            .write_all(&frame)
+            .map_err(|source| Error::Send {
+                to: self.inner.peer_addr,
+                source,
+            })?;
+        Ok(msg.len())
+    }
+
+    /// close shuts down the stream, which also stops the receive thread of the connection.
+    pub fn close(&self) {
+        let _ = self.inner.stream.shutdown(Shutdown::Both);
+    }
+
+    /// is_same returns true if both handles refer to the same connection.
+    pub fn is_same(&self, other: &TcpConnection) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl fmt::Debug for TcpConnection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TcpConnection({} -> {})",
+            self.inner.local_addr, self.inner.peer_addr
+        )
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::framer::{FramerObject, LengthPrefixedFramer};
+use crate::net::interface::*;
+use crate::net::interface_observer::{InterfaceEvent, InterfaceObserver};
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::route::{find_route, is_same_family, Route};
+use crate::net::tcp_connection::TcpConnection;
+use crate::net::tcp_server::TcpServer;
+
+/// TcpManager runs a TcpServer on each interface, and opens the outgoing connections from the interface which reaches the destination.
+pub struct TcpManager {
+    tcp_servers: Vec<TcpServer>,
+    observers: NotifytManager,
+    framer: FramerObject,
+    filter: InterfaceFilter,
+    port: Option<u16>,
+}
+
+impl Default for TcpManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TcpManager {
+    pub fn new() -> TcpManager {
+        TcpManager {
+            tcp_servers: Vec::new(),
+            observers: NotifytManager::new(),
+            framer: Arc::new(LengthPrefixedFramer::new(4)),
+            filter: InterfaceFilter::new(),
+            port: None,
+        }
+    }
+
+    /// set_interface_filter sets the filter which selects the interfaces to bind in start() and rebind().
+    pub fn set_interface_filter(&mut self, filter: InterfaceFilter) {
+        self.filter = filter;
+    }
+
+    pub fn interface_filter(&self) -> &InterfaceFilter {
+        &self.filter
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers by the servers which are bound after this call.
+    /// The servers share the worker threads of DispatchMode::Pool, which are started once for the manager.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.observers.set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> &DispatchOptions {
+        self.observers.dispatch_options()
+    }
+
+    /// set_framer sets the framer of the servers which are bound after this call.
+    pub fn set_framer(&mut self, framer: FramerObject) {
+        self.framer = framer;
+    }
+
+    pub fn framer(&self) -> &FramerObject {
+        &self.framer
+    }
+
+    /// remove_observer removes the observer from all servers, and returns false if it has not been added.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        for tcp_server in self.tcp_servers.iter_mut() {
+            tcp_server.remove_observer(id);
+        }
+        self.observers.remove_observer(id)
+    }
+
+    /// connect opens a new connection from the server whose interface subnet reaches the destination,
+    /// or from the first server of the same address family.
+    pub fn connect(&self, to_addr: SocketAddr) -> Result<TcpConnection> {
+        let mut tcp_servers = Vec::new();
+        let mut routes = Vec::new();
+        for tcp_server in self.tcp_servers.iter() {
+            if let Ok(ifaddr) = tcp_server.ifaddr() {
+                tcp_servers.push(tcp_server);
+                routes.push(Route {
+                    ifaddr,
+                    network: tcp_server.network(),
+                    if_index: tcp_server.interface_index(),
+                });
+            }
+        }
+        let n = find_route(&routes, &to_addr).or_else(|| {
+            routes
+                .iter()
+                .position(|route| is_same_family(&route.ifaddr, &to_addr))
+        });
+        match n {
+            Some(n) => tcp_servers[n].connect(to_addr),
+            None => Err(Error::NoRoute { to: to_addr }),
+        }
+    }
+
+    /// reply sends the bytes back on the connection which the packet was received on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        match msg.connection() {
+            Some(connection) => connection.send(bytes),
+            None => Err(Error::NoRoute { to: msg.from() }),
+        }
+    }
+
+    /// connections returns the open connections of all servers.
+    pub fn connections(&self) -> Vec<TcpConnection> {
+        self.tcp_servers
+            .iter()
+            .flat_map(|tcp_server| tcp_server.connections())
+            .collect()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.port.is_some()
+    }
+
+    /// ifaddrs returns the addresses of the bound servers.
+    pub fn ifaddrs(&self) -> Vec<SocketAddr> {
+        self.tcp_servers
+            .iter()
+            .filter_map(|server| server.ifaddr().ok())
+            .collect()
+    }
+
+    pub fn has_interface(&self, addr: IpAddr) -> bool {
+        self.tcp_servers
+            .iter()
+            .any(|server| matches!(server.ifaddr(), Ok(ifaddr) if ifaddr.ip() == addr))
+    }
+
+    fn start_server(&self, ifaddr: IpAddr, port: u16) -> Result<TcpServer> {
+        let mut tcp_server = TcpServer::new();
+        tcp_server.share_dispatch_options(&self.observers);
+        tcp_server.set_framer(self.framer.clone());
+        for (id, observer) in self.observers.entries() {
+            tcp_server.insert_observer(*id, observer.clone());
+        }
+        tcp_server.bind(ifaddr, port)?;
+        tcp_server.start()?;
+        Ok(tcp_server)
+    }
+
+    pub fn start(&mut self, port: u16) -> Result<()> {
+        self.start_with_interfaces(port, &self.filter.addresses(&get_interfaces()))
+    }
+
+    /// start_with_interfaces binds a server to each of the specified interfaces.
+    pub fn start_with_interfaces(&mut self, port: u16, ifaddrs: &[IpAddr]) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+
+        if ifaddrs.is_empty() {
+            return Err(Error::NoInterfaces);
+        }
+
+        self.port = Some(port);
+        for ifaddr in ifaddrs.iter().copied() {
+            match self.start_server(ifaddr, port) {
+                Ok(tcp_server) => self.tcp_servers.push(tcp_server),
+                Err(e) => {
+                    let _ = self.stop();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// rebind binds the interfaces which are newly selected by the interface filter, and stops the servers of the vanished interfaces.
+    pub fn rebind(&mut self) -> Result<()> {
+        self.rebind_with_interfaces(&self.filter.addresses(&get_interfaces()))
+    }
+
+    /// rebind_with_interfaces updates the running servers to the specified interfaces.
+    /// It tries all interfaces even if some of them could not be bound, and returns the first error.
+    pub fn rebind_with_interfaces(&mut self, ifaddrs: &[IpAddr]) -> Result<()> {
+        let port = match self.port {
+            Some(port) => port,
+            None => return Ok(()),
+        };
+
+        let mut tcp_servers = Vec::new();
+        for mut tcp_server in self.tcp_servers.drain(..) {
+            match tcp_server.ifaddr() {
+                Ok(addr) if ifaddrs.contains(&addr.ip()) => tcp_servers.push(tcp_server),
+                _ => {
+                    info!("UNBIND {:?}", tcp_server.ifaddr());
+                    let _ = tcp_server.stop();
+                }
+            }
+        }
+        self.tcp_servers = tcp_servers;
+
+        let mut ret = Ok(());
+        for ifaddr in ifaddrs.iter().copied() {
+            if self.has_interface(ifaddr) {
+                continue;
+            }
+            match self.start_server(ifaddr, port) {
+                Ok(tcp_server) => {
+                    info!("BIND {}:{}", ifaddr, port);
+                    self.tcp_servers.push(tcp_server);
+                }
+                Err(e) => {
+                    warn!("BIND {}:{} ({})", ifaddr, port, e);
+                    if ret.is_ok() {
+                        ret = Err(e);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        for tcp_server in self.tcp_servers.iter_mut() {
+            tcp_server.stop()?;
+        }
+        self.tcp_servers.clear();
+        self.port = None;
+        Ok(())
+    }
+}
+
+impl ObserverRegistry for TcpManager {
+    /// insert_observer adds the observer to the running servers and the servers which are bound later.
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        let id = self.observers.insert_observer(id, observer.clone());
+        for tcp_server in self.tcp_servers.iter_mut() {
+            tcp_server.insert_observer(id, observer.clone());
+        }
+        id
+    }
+}
+
+impl InterfaceObserver for TcpManager {
+    /// interface_changed rebinds the servers, so that a manager shared with InterfaceWatcher follows the interface changes.
+    fn interface_changed(&mut self, _event: &InterfaceEvent) {
+        if let Err(e) = self.rebind() {
+            warn!("REBIND ({})", e);
+        }
+    }
+}
+
+impl Drop for TcpManager {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time;
+
+    use crate::log::Logger;
+    use crate::net::error::Error;
+    use crate::net::framer::FixedSizeFramer;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::tcp_manager::*;
+
+    use crate::net::notify_manager_test::*;
+
+    #[test]
+    fn tcp_manager() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = TcpManager::new();
+        mgr.set_framer(Arc::new(FixedSizeFramer::new(2)));
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(mgr.start_with_interfaces(0, &[v4_ifaddr]).is_ok());
+        assert!(mgr.has_interface(v4_ifaddr));
+        let server_addr = mgr.ifaddrs()[0];
+
+        // The connection from the manager is accepted by the manager itself.
+        let connection = mgr.connect(server_addr);
+        assert!(connection.is_ok(), "{:?}", connection);
+        let connection = connection.unwrap();
+        assert_eq!(connection.local_addr().ip(), v4_ifaddr);
+        assert!(connection.send(b"ab").is_ok());
+        assert!(connection.send(b"abc").is_err());
+        thread::sleep(time::Duration::from_millis(500));
+
+        let received = packets.lock().unwrap().first().cloned();
+        assert!(received.is_some());
+        let received = received.unwrap();
+        assert_eq!(received.bytes(), b"ab");
+        assert!(mgr.reply(&received, b"cd").is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(packets
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|pkt| pkt.bytes() == b"cd" && pkt.from() == server_addr));
+        assert_eq!(mgr.connections().len(), 2);
+
+        let v6_addr = SocketAddr::new(v6_ifaddr, server_addr.port());
+        assert!(matches!(mgr.connect(v6_addr), Err(Error::NoRoute { .. })));
+
+        assert!(mgr.rebind_with_interfaces(&[v6_ifaddr]).is_ok());
+        assert!(!mgr.has_interface(v4_ifaddr));
+        assert!(mgr.connections().is_empty());
+        assert!(mgr.stop().is_ok());
+        assert!(mgr.start_with_interfaces(0, &[]).is_err());
+    }
+}
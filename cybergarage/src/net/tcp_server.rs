@@ -0,0 +1,407 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use nix::sys::socket::sockopt::{Ipv6V6Only, ReceiveTimeout, ReuseAddr};
+use nix::sys::socket::{
+    bind, connect, listen, setsockopt, socket, AddressFamily, Backlog, SockType, SockaddrStorage,
+};
+use nix::sys::time::{TimeVal, TimeValLike};
+use std::io::{self, Read};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::os::unix::io::{AsRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::net::cidr::Cidr;
+use crate::net::default::{MAX_PACKET_SIZE, RECV_TIMEOUT};
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
+use crate::net::framer::{FramerObject, LengthPrefixedFramer};
+use crate::net::interface::{find_interface_index, find_interface_network, get_interfaces};
+use crate::net::notifier::*;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::tcp_connection::TcpConnection;
+use crate::net::udp_socket::{with_scope_id, SOCK_FLAGS};
+
+/// TCP_BACKLOG is the length of the queue of the pending connections.
+const TCP_BACKLOG: i32 = 128;
+
+type Connections = Arc<Mutex<Vec<(TcpConnection, Option<thread::JoinHandle<()>>)>>>;
+
+/// TcpServer accepts the TCP connections on an interface, splits each stream into messages with the framer,
+/// and notifies the messages to the observers as Packets which carry the connection to reply on.
+pub struct TcpServer {
+    listener: Option<Arc<TcpListener>>,
+    notifier: Notifier,
+    framer: FramerObject,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+    connections: Connections,
+    recv_buffer_size: usize,
+    if_index: u32,
+    network: Option<Cidr>,
+}
+
+impl Default for TcpServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// bind_listener creates a new listening socket with SO_REUSEADDR and IPV6_V6ONLY, and polls it with RECV_TIMEOUT so that stop() can wake the accept thread up.
+fn bind_listener(addr: SocketAddr) -> io::Result<TcpListener> {
+    let fd = new_socket(&addr)?;
+    setsockopt(&fd, ReuseAddr, &true)?;
+    if addr.is_ipv6() {
+        setsockopt(&fd, Ipv6V6Only, &true)?;
+    }
+    bind(fd.as_raw_fd(), &SockaddrStorage::from(addr))?;
+    listen(&fd, Backlog::new(TCP_BACKLOG)?)?;
+    let timeout = TimeVal::microseconds(RECV_TIMEOUT.as_micros() as i64);
+    setsockopt(&fd, ReceiveTimeout, &timeout)?;
+    Ok(TcpListener::from(fd))
+}
+
+fn new_socket(addr: &SocketAddr) -> io::Result<OwnedFd> {
+    let family = if addr.is_ipv4() {
+        AddressFamily::Inet
+    } else {
+        AddressFamily::Inet6
+    };
+    Ok(socket(family, SockType::Stream, SOCK_FLAGS, None)?)
+}
+
+impl TcpServer {
+    pub fn new() -> TcpServer {
+        TcpServer {
+            listener: None,
+            notifier: notifier_new(),
+            framer: Arc::new(LengthPrefixedFramer::new(4)),
+            running: Arc::new(AtomicBool::new(false)),
+            thread: None,
+            connections: Arc::new(Mutex::new(Vec::new())),
+            recv_buffer_size: MAX_PACKET_SIZE,
+            if_index: 0,
+            network: None,
+        }
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        self.notifier.lock().unwrap().remove_observer(id)
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.notifier.lock().unwrap().set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> DispatchOptions {
+        *self.notifier.lock().unwrap().dispatch_options()
+    }
+
+    /// share_dispatch_options sets the dispatch options of the manager, and shares its worker threads instead of starting new ones.
+    pub(crate) fn share_dispatch_options(&mut self, manager: &NotifytManager) {
+        self.notifier
+            .lock()
+            .unwrap()
+            .share_dispatch_options(manager);
+    }
+
+    /// set_framer sets the framer of the connections which are accepted or connected after this call. The default framer has a 4-byte length header.
+    pub fn set_framer(&mut self, framer: FramerObject) {
+        self.framer = framer;
+    }
+
+    pub fn framer(&self) -> &FramerObject {
+        &self.framer
+    }
+
+    /// set_recv_buffer_size sets the size of the buffer which each connection reads the stream into.
+    pub fn set_recv_buffer_size(&mut self, size: usize) {
+        self.recv_buffer_size = size;
+    }
+
+    pub fn recv_buffer_size(&self) -> usize {
+        self.recv_buffer_size
+    }
+
+    /// interface_index returns the index of the bound interface, or 0 if it is unknown.
+    pub fn interface_index(&self) -> u32 {
+        self.if_index
+    }
+
+    /// network returns the address and the prefix length of the bound interface, or None if it is unknown.
+    pub fn network(&self) -> Option<Cidr> {
+        self.network
+    }
+
+    pub fn ifaddr(&self) -> Result<SocketAddr> {
+        match &self.listener {
+            Some(listener) => Ok(listener.local_addr()?),
+            None => Err(Error::NotBound),
+        }
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.listener.is_some()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.is_some()
+    }
+
+    /// connections returns the open connections.
+    pub fn connections(&self) -> Vec<TcpConnection> {
+        self.connections
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(connection, _)| connection.clone())
+            .collect()
+    }
+
+    /// reply sends the bytes back on the connection which the packet was received on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        match msg.connection() {
+            Some(connection) => connection.send(bytes),
+            None => Err(Error::NoRoute { to: msg.from() }),
+        }
+    }
+
+    pub fn bind(&mut self, ifaddr: IpAddr, port: u16) -> Result<()> {
+        if self.is_bound() {
+            self.close()?;
+        }
+        let addr = SocketAddr::new(ifaddr, port);
+        debug!("BIND TCP {}", addr);
+        let listener =
+            bind_listener(with_scope_id(addr)).map_err(|source| Error::Bind { addr, source })?;
+        self.listener = Some(Arc::new(listener));
+        let ifaces = get_interfaces();
+        self.if_index = find_interface_index(&ifaces, ifaddr).unwrap_or(0);
+        self.network = find_interface_network(&ifaces, ifaddr);
+        Ok(())
+    }
+
+    /// connect opens a new connection from the bound interface to the address.
+    /// The messages received on it are notified to the observers as well as the accepted connections, so the server has to be started.
+    pub fn connect(&self, to_addr: SocketAddr) -> Result<TcpConnection> {
+        if !self.is_running() {
+            return Err(Error::NotBound);
+        }
+        let ifaddr = self.ifaddr()?;
+        let connect_to = |to_addr: SocketAddr| -> io::Result<TcpStream> {
+            let fd = new_socket(&to_addr)?;
+            bind(
+                fd.as_raw_fd(),
+                &SockaddrStorage::from(with_scope_id(SocketAddr::new(ifaddr.ip(), 0))),
+            )?;
+            connect(
+                fd.as_raw_fd(),
+                &SockaddrStorage::from(with_scope_id(to_addr)),
+            )?;
+            Ok(TcpStream::from(fd))
+        };
+        debug!("CONNECT {} -> {}", ifaddr, to_addr);
+        let stream = connect_to(to_addr).map_err(|source| Error::Connect {
+            to: to_addr,
+            source,
+        })?;
+        self.open_connection(stream)
+    }
+
+    fn open_connection(&self, stream: TcpStream) -> Result<TcpConnection> {
+        open_connection(
+            stream,
+            &self.framer,
+            &self.connections,
+            &self.notifier,
+            &self.running,
+            self.recv_buffer_size,
+            self.if_index,
+        )
+    }
+
+    /// close closes the listening socket and all connections.
+    pub fn close(&mut self) -> Result<()> {
+        self.listener = None;
+        let connections: Vec<_> = self.connections.lock().unwrap().drain(..).collect();
+        for (connection, thread) in connections {
+            connection.close();
+            if let Some(thread) = thread {
+                if thread.join().is_err() {
+                    warn!("RECV thread panicked");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+        let listener = match &self.listener {
+            Some(listener) => listener.clone(),
+            None => return Err(Error::NotBound),
+        };
+        self.running.store(true, Ordering::SeqCst);
+        let framer = self.framer.clone();
+        let connections = self.connections.clone();
+        let notifier = self.notifier.clone();
+        let running = self.running.clone();
+        let buffer_size = self.recv_buffer_size;
+        let if_index = self.if_index;
+        self.thread = Some(thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, peer_addr)) => {
+                        debug!("ACCEPT {}", peer_addr);
+                        let ret = open_connection(
+                            stream,
+                            &framer,
+                            &connections,
+                            &notifier,
+                            &running,
+                            buffer_size,
+                            if_index,
+                        );
+                        if let Err(e) = ret {
+                            warn!("ACCEPT {} ({})", peer_addr, e);
+                        }
+                    }
+                    Err(e) if is_timeout(&e) => continue,
+                    Err(e) => {
+                        warn!("ACCEPT ({})", e);
+                        break;
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("ACCEPT thread panicked");
+            }
+        }
+        self.close()
+    }
+}
+
+impl ObserverRegistry for TcpServer {
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        self.notifier.lock().unwrap().insert_observer(id, observer)
+    }
+}
+
+impl Drop for TcpServer {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+    )
+}
+
+/// open_connection registers the connection, and spawns its receive thread.
+fn open_connection(
+    stream: TcpStream,
+    framer: &FramerObject,
+    connections: &Connections,
+    notifier: &Notifier,
+    running: &Arc<AtomicBool>,
+    buffer_size: usize,
+    if_index: u32,
+) -> Result<TcpConnection> {
+    stream.set_read_timeout(Some(RECV_TIMEOUT))?;
+    let connection = TcpConnection::new(stream, framer.clone())?;
+    let mut connections_lock = connections.lock().unwrap();
+    let thread = {
+        let connection = connection.clone();
+        let connections = connections.clone();
+        let notifier = notifier.clone();
+        let running = running.clone();
+        thread::spawn(move || {
+            recv_loop(&connection, &notifier, &running, buffer_size, if_index);
+            connection.close();
+            connections
+                .lock()
+                .unwrap()
+                .retain(|(other, _)| !other.is_same(&connection));
+        })
+    };
+    connections_lock.push((connection.clone(), Some(thread)));
+    Ok(connection)
+}
+
+/// recv_loop reads the stream until it is closed, and notifies each message split by the framer.
+fn recv_loop(
+    connection: &TcpConnection,
+    notifier: &Notifier,
+    running: &Arc<AtomicBool>,
+    buffer_size: usize,
+    if_index: u32,
+) {
+    let mut buf = vec![0_u8; buffer_size];
+    let mut pending = Vec::new();
+    while running.load(Ordering::SeqCst) {
+        let n_bytes = match connection.stream().read(&mut buf) {
+            Ok(0) => break,
+            Ok(n_bytes) => n_bytes,
+            Err(e) if is_timeout(&e) => continue,
+            Err(e) => {
+                warn!("RECV {} ({})", connection.peer_addr(), e);
+                break;
+            }
+        };
+        pending.extend_from_slice(&buf[..n_bytes]);
+        loop {
+            let frame = match connection.framer().decode(&mut pending) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("RECV {} ({})", connection.peer_addr(), e);
+                    return;
+                }
+            };
+            let mut msg = Packet::from_bytes(&frame);
+            msg.set_from(connection.peer_addr());
+            msg.set_to(connection.local_addr());
+            msg.set_interface_index(if_index);
+            msg.set_timestamp(SystemTime::now());
+            msg.set_connection(connection.clone());
+            info!(
+                "RECV {} -> {} ({})",
+                connection.peer_addr(),
+                connection.local_addr(),
+                msg
+            );
+            notifier_notify_while(notifier, &msg, running);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time;
+
+    use crate::log::Logger;
+    use crate::net::error::Error;
+    use crate::net::framer::{DelimiterFramer, Framer, LengthPrefixedFramer};
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+    use crate::net::tcp_server::*;
+
+    use crate::net::notify_manager_test::*;
+
+    #[test]
+    fn tcp_server() {
+        fn test_tcp_server(ifaddr: IpAddr) {
+            let server_packets = Arc::new(Mutex::new(Vec::new()));
+            let client_packets = Arc::new(Mutex::new(Vec::new()));
+            let framer = Arc::new(DelimiterFramer::new(b"\r\n"));
+
+            let mut server = TcpServer::new();
+            server.set_framer(framer.clone());
+            let observer = TestPacketRecorder::new(server_packets.clone());
+            server.add_observer(Arc::new(Mutex::new(observer)));
+            let ret = server.bind(ifaddr, 0);
+            assert!(ret.is_ok(), "{:?}", ret);
+            assert!(server.start().is_ok());
+            let server_addr = server.ifaddr().unwrap();
+
+            let mut client = TcpServer::new();
+            client.set_framer(framer);
+            let observer = TestPacketRecorder::new(client_packets.clone());
+            client.add_observer(Arc::new(Mutex::new(observer)));
+            assert!(client.bind(ifaddr, 0).is_ok());
+            assert!(matches!(client.connect(server_addr), Err(Error::NotBound)));
+            assert!(client.start().is_ok());
+
+            let connection = client.connect(server_addr);
+            assert!(connection.is_ok(), "{:?}", connection);
+            let connection = connection.unwrap();
+            assert_eq!(connection.peer_addr(), server_addr);
+            assert!(connection.send(b"hello").is_ok());
+            assert!(connection.send(b"world").is_ok());
+            thread::sleep(time::Duration::from_millis(500));
+
+            // The messages are split by the framer, and carry the connection to reply on.
+            let received: Vec<Packet> = server_packets.lock().unwrap().clone();
+            assert_eq!(received.len(), 2);
+            assert_eq!(received[0].bytes(), b"hello");
+            assert_eq!(received[1].bytes(), b"world");
+            assert_eq!(received[0].from(), connection.local_addr());
+            assert_eq!(received[0].to(), server_addr);
+            assert!(received[0].connection().is_some());
+            assert_eq!(server.connections().len(), 1);
+
+            assert!(server.reply(&received[0], b"hi").is_ok());
+            thread::sleep(time::Duration::from_millis(500));
+            let replies = client_packets.lock().unwrap().clone();
+            assert_eq!(replies.len(), 1);
+            assert_eq!(replies[0].bytes(), b"hi");
+            assert!(replies[0].connection().unwrap().is_same(&connection));
+
+            // A closed connection is removed from both ends.
+            connection.close();
+            thread::sleep(time::Duration::from_millis(500));
+            assert!(server.connections().is_empty());
+            assert!(client.connections().is_empty());
+
+            assert!(client.stop().is_ok());
+            assert!(server.stop().is_ok());
+            assert!(!server.is_bound());
+        }
+
+        Logger::init();
+
+        test_tcp_server(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        test_tcp_server(IpAddr::V6(Ipv6Addr::LOCALHOST));
+    }
+
+    #[test]
+    fn tcp_server_stream() {
+        Logger::init();
+
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut server = TcpServer::new();
+        let observer = TestPacketRecorder::new(packets.clone());
+        server.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(server.bind(IpAddr::V4(Ipv4Addr::LOCALHOST), 0).is_ok());
+        assert!(server.start().is_ok());
+
+        // The default framer reassembles the frames split across the writes.
+        let frame = LengthPrefixedFramer::new(4).encode(b"abc").unwrap();
+        let mut stream = TcpStream::connect(server.ifaddr().unwrap()).unwrap();
+        assert!(stream.write_all(&frame[..2]).is_ok());
+        thread::sleep(time::Duration::from_millis(200));
+        assert!(stream.write_all(&frame[2..]).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert_eq!(packets.lock().unwrap().len(), 1);
+        assert_eq!(packets.lock().unwrap()[0].bytes(), b"abc");
+
+        // stop() closes the accepted connections without waiting for the peers.
+        let now = time::Instant::now();
+        assert!(server.stop().is_ok());
+        assert!(now.elapsed() < time::Duration::from_secs(1));
+        assert!(server.connections().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::*;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::Result;
+use crate::net::interface::*;
+use crate::net::interface_observer::{InterfaceEvent, InterfaceObserver};
+use crate::net::multicast_manager::MulticastManager;
+use crate::net::multicast_options::MulticastOptions;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
+use crate::net::packet::Packet;
+use crate::net::send_report::SendReport;
+use crate::net::udp_socket_options::UdpSocketOptions;
+use crate::net::unicast_manager::UnicastManager;
+
+/// TransportConfig is the configuration of TransportManager.
+#[derive(Clone, Debug)]
+pub struct TransportConfig {
+    groups: Vec<IpAddr>,
+    unicast_port: u16,
+    multicast_port: u16,
+    filter: InterfaceFilter,
+    multicast_options: MulticastOptions,
+    socket_options: UdpSocketOptions,
+    dispatch_options: DispatchOptions,
+}
+
+impl TransportConfig {
+    /// new returns a configuration which binds the unicast and multicast sockets to the same port.
+    pub fn new(port: u16) -> TransportConfig {
+        TransportConfig {
+            groups: Vec::new(),
+            unicast_port: port,
+            multicast_port: port,
+            filter: InterfaceFilter::new(),
+            multicast_options: MulticastOptions::new(),
+            socket_options: UdpSocketOptions::new(),
+            dispatch_options: DispatchOptions::new(),
+        }
+    }
+
+    /// group adds a multicast group to join on the interfaces of the same address family.
+    pub fn group(mut self, maddr: IpAddr) -> Self {
+        self.groups.push(maddr);
+        self
+    }
+
+    pub fn unicast_port(mut self, port: u16) -> Self {
+        self.unicast_port = port;
+        self
+    }
+
+    pub fn multicast_port(mut self, port: u16) -> Self {
+        self.multicast_port = port;
+        self
+    }
+
+    pub fn interface_filter(mut self, filter: InterfaceFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// multicast_options sets the send options of the multicast sockets, such as MulticastOptions::mdns().
+    pub fn multicast_options(mut self, options: MulticastOptions) -> Self {
+        self.multicast_options = options;
+        self
+    }
+
+    /// socket_options sets the options of the unicast and multicast sockets.
+    pub fn socket_options(mut self, options: UdpSocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    /// dispatch_options sets how the received packets are dispatched to the observers.
+    pub fn dispatch_options(mut self, options: DispatchOptions) -> Self {
+        self.dispatch_options = options;
+        self
+    }
+}
+
+/// TransportManager runs a UnicastManager and a MulticastManager on the same interfaces.
+/// The received packets are notified to the observers with their PacketKind, and can be replied with reply().
+pub struct TransportManager {
+    config: TransportConfig,
+    unicast_mgr: UnicastManager,
+    multicast_mgr: MulticastManager,
+}
+
+impl TransportManager {
+    pub fn new(config: TransportConfig) -> TransportManager {
+        let mut unicast_mgr = UnicastManager::new();
+        unicast_mgr.set_interface_filter(config.filter.clone());
+        unicast_mgr.set_socket_options(config.socket_options.clone());
+        unicast_mgr.set_dispatch_options(config.dispatch_options);
+        let mut multicast_mgr = MulticastManager::new();
+        multicast_mgr.set_interface_filter(config.filter.clone());
+        multicast_mgr.set_multicast_options(config.multicast_options);
+        multicast_mgr.set_socket_options(config.socket_options.clone());
+        multicast_mgr.set_dispatch_options(config.dispatch_options);
+        TransportManager {
+            config,
+            unicast_mgr,
+            multicast_mgr,
+        }
+    }
+
+    pub fn config(&self) -> &TransportConfig {
+        &self.config
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        let removed = self.unicast_mgr.remove_observer(id);
+        self.multicast_mgr.remove_observer(id) || removed
+    }
+
+    pub fn unicast_manager(&self) -> &UnicastManager {
+        &self.unicast_mgr
+    }
+
+    pub fn multicast_manager(&self) -> &MulticastManager {
+        &self.multicast_mgr
+    }
+
+    /// send sends the packet from the unicast server whose interface subnet reaches the destination, and returns the address of the interface which sent it.
+    pub fn send(&self, to_addr: SocketAddr, msg: &Packet) -> Result<SocketAddr> {
+        self.unicast_mgr.send(to_addr, msg)
+    }
+
+    /// send_all sends the packet from all unicast servers of the same address family as the destination, and returns the outcome for each interface.
+    pub fn send_all(&self, to_addr: SocketAddr, msg: &Packet) -> SendReport {
+        self.unicast_mgr.send_all(to_addr, msg)
+    }
+
+    pub fn notify(&self, msg: &Packet) -> Result<()> {
+        self.multicast_mgr.notify(msg)
+    }
+
+    /// notify_all sends the packet to the groups from all multicast servers, and returns the outcome for each interface.
+    pub fn notify_all(&self, msg: &Packet) -> SendReport {
+        self.multicast_mgr.notify_all(msg)
+    }
+
+    /// notify_with_filter sends the packet to the groups only from the multicast servers whose interfaces are selected by the filter.
+    pub fn notify_with_filter(&self, msg: &Packet, filter: &InterfaceFilter) -> SendReport {
+        self.multicast_mgr.notify_with_filter(msg, filter)
+    }
+
+    /// reply sends the bytes back to the sender of the received packet, from the socket and the interface which the packet came in on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        if msg.is_multicast() {
+            self.multicast_mgr.reply(msg, bytes)
+        } else {
+            self.unicast_mgr.reply(msg, bytes)
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.unicast_mgr.is_running()
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        let ifaddrs = self.config.filter.addresses(&get_interfaces());
+        self.start_with_interfaces(&ifaddrs)
+    }
+
+    /// start_with_interfaces binds the unicast and multicast sockets to the specified interfaces.
+    pub fn start_with_interfaces(&mut self, ifaddrs: &[IpAddr]) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+        self.unicast_mgr
+            .start_with_interfaces(self.config.unicast_port, ifaddrs)?;
+        if self.config.groups.is_empty() {
+            return Ok(());
+        }
+        let ret = self.multicast_mgr.start_with_interfaces(
+            &self.config.groups,
+            self.config.multicast_port,
+            ifaddrs,
+        );
+        if ret.is_err() {
+            let _ = self.unicast_mgr.stop();
+        }
+        ret
+    }
+
+    /// rebind updates the sockets of both managers to the interfaces selected by the interface filter.
+    pub fn rebind(&mut self) -> Result<()> {
+        let ifaddrs = self.config.filter.addresses(&get_interfaces());
+        let unicast_ret = self.unicast_mgr.rebind_with_interfaces(&ifaddrs);
+        let multicast_ret = self.multicast_mgr.rebind_with_interfaces(&ifaddrs);
+        unicast_ret.and(multicast_ret)
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        let unicast_ret = self.unicast_mgr.stop();
+        let multicast_ret = self.multicast_mgr.stop();
+        unicast_ret.and(multicast_ret)
+    }
+}
+
+impl ObserverRegistry for TransportManager {
+    /// insert_observer adds the observer to both managers with the same id.
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        let id = self.unicast_mgr.insert_observer(id, observer.clone());
+        self.multicast_mgr.insert_observer(id, observer)
+    }
+}
+
+impl InterfaceObserver for TransportManager {
+    fn interface_changed(&mut self, event: &InterfaceEvent) {
+        let ifaddrs = self.config.filter.addresses(&get_interfaces());
+        let unicast_ret = self.unicast_mgr.rebind_with_interfaces(&ifaddrs);
+        let multicast_ret = self.multicast_mgr.rebind_with_event(event);
+        if let Err(e) = unicast_ret.and(multicast_ret) {
+            warn!("REBIND ({})", e);
+        }
+    }
+}
+
+impl Drop for TransportManager {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time;
+
+    use crate::log::Logger;
+    use crate::net::default_test::*;
+    use crate::net::interface::*;
+    use crate::net::notify_manager_test::*;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+    use crate::net::transport_manager::*;
+    use crate::net::udp_socket::UdpSocket;
+
+    #[test]
+    fn transport_manager() {
+        fn test_transport_manager(ifaddr: IpAddr, maddr: IpAddr) {
+            let packets = Arc::new(Mutex::new(Vec::new()));
+
+            let config = TransportConfig::new(TEST_PORT).group(maddr);
+            let mut mgr = TransportManager::new(config);
+            let observer = TestPacketRecorder::new(packets.clone());
+            mgr.add_observer(Arc::new(Mutex::new(observer)));
+            let ret = mgr.start_with_interfaces(&[ifaddr]);
+            assert!(ret.is_ok(), "{}", ret.err().unwrap());
+
+            let mut client = UdpSocket::new();
+            assert!(client.bind(SocketAddr::new(ifaddr, 0)).is_ok());
+            assert!(client
+                .set_read_timeout(Some(time::Duration::from_secs(1)))
+                .is_ok());
+            let server_addr = mgr.unicast_manager().ifaddrs()[0];
+            assert!(client.send_to(b"U", server_addr).is_ok());
+            let maddr = SocketAddr::new(maddr, TEST_PORT);
+            assert!(client
+                .send_to_interface(b"M", maddr, server_interface_index(ifaddr))
+                .is_ok());
+            thread::sleep(time::Duration::from_millis(500));
+
+            let packets = packets.lock().unwrap().clone();
+            let find_packet = |bytes: &[u8]| {
+                packets
+                    .iter()
+                    .find(|pkt| pkt.bytes().as_slice() == bytes)
+                    .cloned()
+            };
+
+            let mut buf = [0_u8; 16];
+            // NOTE: GitHub Action is slow and may drop to send UDP packets.
+            if let Some(pkt) = find_packet(b"U") {
+                assert!(pkt.is_unicast());
+                assert!(mgr.reply(&pkt, b"u").is_ok());
+                let reply = client.recv_packet(&mut buf).unwrap();
+                assert_eq!(reply.bytes().as_slice(), b"u");
+                assert_eq!(reply.from(), server_addr);
+            }
+            if let Some(pkt) = find_packet(b"M") {
+                assert!(pkt.is_multicast());
+                assert_eq!(pkt.to(), maddr);
+                assert!(mgr.reply(&pkt, b"m").is_ok());
+                let reply = client.recv_packet(&mut buf).unwrap();
+                assert_eq!(reply.bytes().as_slice(), b"m");
+                assert_eq!(reply.from().port(), TEST_PORT);
+            }
+
+            assert!(mgr.stop().is_ok());
+        }
+
+        fn server_interface_index(ifaddr: IpAddr) -> u32 {
+            get_interface_index(ifaddr).unwrap_or(0)
+        }
+
+        Logger::init();
+
+        for ifaddr in get_all_interfaces() {
+            if ifaddr.is_ipv4() {
+                test_transport_manager(ifaddr, TEST_MULTICAST_V4_ADDRESS);
+            } else {
+                test_transport_manager(ifaddr, TEST_MULTICAST_V6_ADDRESS);
+            }
+        }
+    }
+
+    #[test]
+    fn transport_manager_interfaces() {
+        Logger::init();
+
+        let lo_addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ifaddr = match get_all_interfaces()
+            .into_iter()
+            .find(|ifaddr| ifaddr.is_ipv4() && !ifaddr.is_loopback())
+        {
+            Some(ifaddr) => ifaddr,
+            None => return,
+        };
+        let maddr = SocketAddr::new(TEST_MULTICAST_V4_ADDRESS2, TEST_PORT);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let config = TransportConfig::new(TEST_PORT).group(maddr.ip());
+        let mut mgr = TransportManager::new(config);
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+        let ret = mgr.start_with_interfaces(&[lo_addr, ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+
+        // The observer is added to the multicast servers of both interfaces, but each group datagram is notified once.
+        // The other tests may send to the same group and port, so only the packets of this test are counted.
+        let mut client = UdpSocket::new();
+        assert!(client.bind(SocketAddr::new(lo_addr, 0)).is_ok());
+        assert!(client.set_multicast_interface(&lo_addr).is_ok());
+        assert!(client
+            .send_to(b"transport_manager_interfaces", maddr)
+            .is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        let packets = packets.lock().unwrap();
+        let received: Vec<&Packet> = packets
+            .iter()
+            .filter(|pkt| pkt.bytes() == b"transport_manager_interfaces")
+            .collect();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].is_multicast());
+        assert_eq!(received[0].to(), maddr);
+        assert_eq!(
+            received[0].interface_index(),
+            get_interface_index(lo_addr).unwrap_or(0)
+        );
+    }
+
+    #[test]
+    fn transport_manager_reply_without_route() {
+        let config = TransportConfig::new(TEST_PORT);
+        let mgr = TransportManager::new(config);
+        assert!(mgr.reply(&Packet::new(), b"").is_err());
+    }
+}
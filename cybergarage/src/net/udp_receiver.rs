@@ -0,0 +1,167 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use log::*;
+use std::io;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use crate::net::default::*;
+use crate::net::error::{Error, Result};
+use crate::net::notifier::*;
+use crate::net::packet::PacketKind;
+use crate::net::udp_socket::UdpSocket;
+
+/// UdpReceiver runs the receive loop of a UdpSocket on its own thread and joins it when stopped.
+pub struct UdpReceiver {
+    socket: Option<Arc<RwLock<UdpSocket>>>,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+    buffer_size: usize,
+    if_index: u32,
+    broadcast: Option<IpAddr>,
+}
+
+impl Default for UdpReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UdpReceiver {
+    pub fn new() -> UdpReceiver {
+        UdpReceiver {
+            socket: None,
+            running: Arc::new(AtomicBool::new(false)),
+            thread: None,
+            buffer_size: MAX_PACKET_SIZE,
+            if_index: 0,
+            broadcast: None,
+        }
+    }
+
+    /// set_buffer_size sets the size of the receive buffer, which is applied from the next start().
+    pub fn set_buffer_size(&mut self, size: usize) {
+        self.buffer_size = size;
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// set_interface_index restricts the notified packets to those which came in on the interface, which is applied from the next start().
+    /// The index 0 notifies the packets from all interfaces.
+    pub fn set_interface_index(&mut self, if_index: u32) {
+        self.if_index = if_index;
+    }
+
+    /// set_broadcast_addr sets the directed broadcast address, whose packets are notified as PacketKind::Broadcast from the next start().
+    pub fn set_broadcast_addr(&mut self, broadcast: Option<IpAddr>) {
+        self.broadcast = broadcast;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.is_some()
+    }
+
+    /// start spawns the receive thread. The socket is polled with RECV_TIMEOUT so that stop() can wake the thread up.
+    pub fn start(&mut self, socket: Arc<RwLock<UdpSocket>>, notifier: Notifier) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+        socket
+            .read()
+            .unwrap()
+            .set_read_timeout(Some(RECV_TIMEOUT))?;
+        self.running.store(true, Ordering::SeqCst);
+        self.socket = Some(socket.clone());
+        let running = self.running.clone();
+        let buffer_size = self.buffer_size;
+        let if_index = self.if_index;
+        let broadcast = self.broadcast;
+        self.thread = Some(thread::spawn(move || {
+            recv_loop(socket, notifier, running, buffer_size, if_index, broadcast);
+        }));
+        Ok(())
+    }
+
+    /// stop signals the receive thread to exit, wakes it up, and waits until it has finished.
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(socket) = self.socket.take() {
+            socket.read().unwrap().shutdown();
+        }
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("RECV thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for UdpReceiver {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn recv_loop(
+    socket: Arc<RwLock<UdpSocket>>,
+    notifier: Notifier,
+    running: Arc<AtomicBool>,
+    buffer_size: usize,
+    if_index: u32,
+    broadcast: Option<IpAddr>,
+) {
+    // NOTE: The bound address is taken once, since close() may clear it while the loop is running.
+    let local_addr = match socket.read().unwrap().addr() {
+        Ok(addr) => addr.to_string(),
+        Err(_) => "-".to_string(),
+    };
+    let mut buf = vec![0_u8; buffer_size];
+    while running.load(Ordering::SeqCst) {
+        let recv_res = socket.read().unwrap().recv_packet(&mut buf);
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        match recv_res {
+            Ok(mut msg) => {
+                if if_index != 0 && msg.interface_index() != if_index {
+                    continue;
+                }
+                if broadcast == Some(msg.to().ip()) {
+                    msg.set_kind(PacketKind::Broadcast);
+                }
+                info!("RECV {} -> {} ({})", msg.from(), local_addr, msg);
+                if msg.is_truncated() {
+                    warn!("RECV {} truncated to {} bytes", msg.from(), buffer_size);
+                }
+                notifier_notify_while(&notifier, &msg, &running);
+            }
+            Err(e) if is_timeout(&e) => continue,
+            Err(e) => {
+                warn!("RECV {} ({})", local_addr, e);
+                break;
+            }
+        }
+    }
+}
+
+fn is_timeout(e: &Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+    )
+}
@@ -12,37 +12,113 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-// NOTE: Standard UdpSocket could not enable SO_REUSEADDR
-// use nix::sys::socket::sockopt::{IpMulticastLoop, ReuseAddr, ReusePort};
-use crate::net::error::{BindError, ScoketError};
-use crate::net::result::Result;
+use crate::net::error::{Error, Result};
+use crate::net::interface::get_interface_index;
+use crate::net::packet::{Packet, PacketKind};
+use crate::net::udp_socket_options::UdpSocketOptions;
 use log::warn;
-use net2::unix::UnixUdpBuilderExt;
-use net2::UdpBuilder;
-use nix::sys::socket::{shutdown, Shutdown};
-use nix::unistd::close;
-use std::io;
-use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
-use std::os::unix::io::AsRawFd;
-use std::{thread, time};
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::socket::sockopt::{
+    Ipv4PacketInfo, Ipv6MulticastHops, Ipv6RecvPacketInfo, RcvBuf, SndBuf,
+};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use nix::sys::socket::sockopt::{Ipv4RecvTtl, Ipv6RecvHopLimit, ReceiveTimestampns};
+use nix::sys::socket::{
+    bind, getsockopt, recvmsg, sendmsg, setsockopt, shutdown, socket, AddressFamily,
+    ControlMessage, ControlMessageOwned, MsgFlags, Shutdown, SockFlag, SockType, SockaddrStorage,
+};
+use std::io::{self, IoSlice, IoSliceMut};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub struct UdpSocket {
     sock: Option<std::net::UdpSocket>,
     addr: Option<SocketAddr>,
 }
 
-fn create_socket_v4(ifaddr: SocketAddr) -> io::Result<std::net::UdpSocket> {
-    UdpBuilder::new_v4()?
-        .reuse_address(true)?
-        .reuse_port(true)?
-        .bind(ifaddr)
+// NOTE: libc does not define IPV6_MULTICAST_ALL, which is available since Linux 4.20.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const IPV6_MULTICAST_ALL: libc::c_int = 29;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) const SOCK_FLAGS: SockFlag = SockFlag::SOCK_CLOEXEC;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) const SOCK_FLAGS: SockFlag = SockFlag::empty();
+
+/// bind_socket creates a new socket of the address family, sets the options which have to be set before bind(),
+/// such as SO_REUSEADDR, SO_REUSEPORT and IPV6_V6ONLY, and binds it to the address.
+fn bind_socket(ifaddr: SocketAddr, options: &UdpSocketOptions) -> io::Result<std::net::UdpSocket> {
+    let family = if ifaddr.is_ipv4() {
+        AddressFamily::Inet
+    } else {
+        AddressFamily::Inet6
+    };
+    let fd: OwnedFd = socket(family, SockType::Datagram, SOCK_FLAGS, None)?;
+    options.apply_before_bind(fd.as_raw_fd(), ifaddr.is_ipv6())?;
+    bind(fd.as_raw_fd(), &SockaddrStorage::from(ifaddr))?;
+    Ok(std::net::UdpSocket::from(fd))
+}
+
+fn create_socket_v4(
+    ifaddr: SocketAddr,
+    options: &UdpSocketOptions,
+) -> io::Result<std::net::UdpSocket> {
+    let sock = bind_socket(ifaddr, options)?;
+    options.apply(&sock, false)?;
+    setsockopt(&sock, Ipv4PacketInfo, &true)?;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        setsockopt(&sock, Ipv4RecvTtl, &true)?;
+        enable_timestamp(&sock);
+    }
+    Ok(sock)
+}
+
+fn create_socket_v6(
+    ifaddr: SocketAddr,
+    options: &UdpSocketOptions,
+) -> io::Result<std::net::UdpSocket> {
+    let sock = bind_socket(ifaddr, options)?;
+    options.apply(&sock, true)?;
+    setsockopt(&sock, Ipv6RecvPacketInfo, &true)?;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        setsockopt(&sock, Ipv6RecvHopLimit, &true)?;
+        enable_timestamp(&sock);
+    }
+    Ok(sock)
 }
 
-fn create_socket_v6(ifaddr: SocketAddr) -> io::Result<std::net::UdpSocket> {
-    UdpBuilder::new_v6()?
-        .reuse_address(true)?
-        .reuse_port(true)?
-        .bind(ifaddr)
+/// with_scope_id sets the index of the interface to a link-local IPv6 address which has no scope id, since it can not be bound without it.
+pub(crate) fn with_scope_id(ifaddr: SocketAddr) -> SocketAddr {
+    match ifaddr {
+        SocketAddr::V6(addr) if addr.ip().is_unicast_link_local() && addr.scope_id() == 0 => {
+            let scope_id = get_interface_index(IpAddr::V6(*addr.ip())).unwrap_or(0);
+            SocketAddr::V6(SocketAddrV6::new(
+                *addr.ip(),
+                addr.port(),
+                addr.flowinfo(),
+                scope_id,
+            ))
+        }
+        _ => ifaddr,
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn enable_timestamp(sock: &std::net::UdpSocket) {
+    // NOTE: recv_packet() falls back to the system clock if SO_TIMESTAMPNS is not supported.
+    if setsockopt(sock, ReceiveTimestampns, &true).is_err() {
+        warn!("SO_TIMESTAMPNS is not supported");
+    }
+}
+
+impl Default for UdpSocket {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UdpSocket {
@@ -53,117 +129,616 @@ impl UdpSocket {
         }
     }
 
-    pub fn addr(&self) -> io::Result<SocketAddr> {
-        if self.addr.is_some() {
-            return Ok(self.addr.unwrap());
-        }
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "socket is not bound",
-        ))
+    fn sock(&self) -> Result<&std::net::UdpSocket> {
+        self.sock.as_ref().ok_or(Error::NotBound)
+    }
+
+    pub fn addr(&self) -> Result<SocketAddr> {
+        self.addr.ok_or(Error::NotBound)
     }
 
     pub fn bind(&mut self, ifaddr: SocketAddr) -> Result<()> {
+        self.bind_with_options(ifaddr, &UdpSocketOptions::new())
+    }
+
+    /// bind_with_options binds a new socket which has the specified options.
+    pub fn bind_with_options(
+        &mut self,
+        ifaddr: SocketAddr,
+        options: &UdpSocketOptions,
+    ) -> Result<()> {
         if self.sock.is_some() {
             self.close();
         }
 
-        // NOTE: Standard UdpSocket could not enable SO_REUSEADDR
-        // let sock = std::net::UdpSocket::bind(ifaddr.to_string());
-        // let fd = sock.as_ref().unwrap().as_raw_fd();
-        // if setsockopt(fd, ReuseAddr, &true).is_err() {
-        //     warn!("SO_REUSEADDR is not supported");
-        // }
-        // if setsockopt(fd, ReusePort, &true).is_err() {
-        //     warn!("SO_REUSEPORT is not supported");
-        // }
-        // if setsockopt(fd, IpMulticastLoop, &true).is_err() {
-        //     warn!("IP_MULTICAST_LOOP is not supported");
-        // }
-
-        let mut sock: Option<std::net::UdpSocket> = None;
-
-        // net2::UdpBuilder could enable SO_REUSEADDR and SO_REUSEPORT on macOS and Linux
-        if ifaddr.is_ipv4() {
-            let sock_v4 = create_socket_v4(ifaddr);
-            if sock_v4.is_ok() {
-                sock = Some(sock_v4.unwrap())
+        // NOTE: Standard UdpSocket could not enable SO_REUSEADDR and SO_REUSEPORT before bind(), so the socket is created with nix.
+        let bind_addr = with_scope_id(ifaddr);
+        let sock = if bind_addr.is_ipv4() {
+            create_socket_v4(bind_addr, options)
+        } else {
+            create_socket_v6(bind_addr, options)
+        };
+
+        match sock {
+            Ok(sock) => {
+                self.addr = Some(sock.local_addr().unwrap_or(bind_addr));
+                self.sock = Some(sock);
+                Ok(())
             }
-        } else if ifaddr.is_ipv6() {
-            let sock_v6 = create_socket_v6(ifaddr);
-            if sock_v6.is_ok() {
-                sock = Some(sock_v6.unwrap())
+            Err(source) => Err(Error::Bind {
+                addr: ifaddr,
+                source,
+            }),
+        }
+    }
+
+    /// shutdown shuts down the socket to wake up the threads blocked in recv_from(), but keeps the file descriptor open.
+    pub fn shutdown(&self) {
+        if let Some(sock) = self.sock.as_ref() {
+            match shutdown(sock.as_raw_fd(), Shutdown::Both) {
+                // NOTE: Unconnected UDP sockets return ENOTCONN, but Linux still wakes up the blocked readers.
+                Ok(_) | Err(Errno::ENOTCONN) => {}
+                Err(e) => warn!("shutdown ({})", e.desc()),
             }
         }
+    }
+
+    /// close shuts down the socket and releases the file descriptor by dropping the inner socket.
+    pub fn close(&mut self) {
+        self.shutdown();
+        self.sock = None;
+        self.addr = None;
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        Ok(self.sock()?.set_nonblocking(nonblocking)?)
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        Ok(self.sock()?.set_read_timeout(timeout)?)
+    }
 
-        if sock.is_none() {
-            return Err(ScoketError::new(&format!("could not bind to {}", ifaddr)));
+    /// set_recv_buffer_size sets the SO_RCVBUF option of the socket.
+    pub fn set_recv_buffer_size(&self, size: usize) -> Result<()> {
+        Ok(setsockopt(self.sock()?, RcvBuf, &size).map_err(io::Error::from)?)
+    }
+
+    pub fn recv_buffer_size(&self) -> Result<usize> {
+        Ok(getsockopt(self.sock()?, RcvBuf).map_err(io::Error::from)?)
+    }
+
+    /// set_send_buffer_size sets the SO_SNDBUF option of the socket.
+    pub fn set_send_buffer_size(&self, size: usize) -> Result<()> {
+        Ok(setsockopt(self.sock()?, SndBuf, &size).map_err(io::Error::from)?)
+    }
+
+    pub fn send_buffer_size(&self) -> Result<usize> {
+        Ok(getsockopt(self.sock()?, SndBuf).map_err(io::Error::from)?)
+    }
+
+    pub fn send_to(&self, buf: &[u8], to_addr: SocketAddr) -> Result<usize> {
+        self.sock()?
+            .send_to(buf, to_addr)
+            .map_err(|source| Error::Send {
+                to: to_addr,
+                source,
+            })
+    }
+
+    /// send_to_interface sends the datagram out of the specified interface with IP_PKTINFO or IPV6_PKTINFO,
+    /// which lets a socket bound to the unspecified address reply on the interface the request came in on.
+    pub fn send_to_interface(
+        &self,
+        buf: &[u8],
+        to_addr: SocketAddr,
+        if_index: u32,
+    ) -> Result<usize> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if if_index != 0 {
+            let fd = self.sock()?.as_raw_fd();
+            let iov = [IoSlice::new(buf)];
+            let dst = SockaddrStorage::from(to_addr);
+            let ret = match to_addr {
+                SocketAddr::V4(_) => {
+                    let info = libc::in_pktinfo {
+                        ipi_ifindex: if_index as libc::c_int,
+                        ipi_spec_dst: libc::in_addr { s_addr: 0 },
+                        ipi_addr: libc::in_addr { s_addr: 0 },
+                    };
+                    let cmsgs = [ControlMessage::Ipv4PacketInfo(&info)];
+                    sendmsg(fd, &iov, &cmsgs, MsgFlags::empty(), Some(&dst))
+                }
+                SocketAddr::V6(_) => {
+                    let info = libc::in6_pktinfo {
+                        ipi6_addr: libc::in6_addr { s6_addr: [0; 16] },
+                        ipi6_ifindex: if_index,
+                    };
+                    let cmsgs = [ControlMessage::Ipv6PacketInfo(&info)];
+                    sendmsg(fd, &iov, &cmsgs, MsgFlags::empty(), Some(&dst))
+                }
+            };
+            return ret.map_err(|e| Error::Send {
+                to: to_addr,
+                source: e.into(),
+            });
         }
+        self.send_to(buf, to_addr)
+    }
 
-        self.sock = sock;
-        self.addr = Some(ifaddr);
-        Ok(())
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.sock()?
+            .recv_from(buf)
+            .map_err(|source| Error::Recv { source })
     }
 
-    pub fn close(&self) {
-        if self.sock.is_none() {
-            return;
+    /// recv_packet receives a datagram with recvmsg(), and marks the Packet as truncated if it did not fit in the buffer.
+    /// The destination address and the receiving interface are taken from the IP_PKTINFO or IPV6_PKTINFO control message,
+    /// the TTL or hop limit from IP_TTL or IPV6_HOPLIMIT, and the receive timestamp from SO_TIMESTAMPNS or the system clock.
+    pub fn recv_packet(&self, buf: &mut [u8]) -> Result<Packet> {
+        let fd = self.sock()?.as_raw_fd();
+        let port = self.addr()?.port();
+        let mut iov = [IoSliceMut::new(buf)];
+        let mut cmsg_buf = nix::cmsg_space!(libc::in6_pktinfo, libc::c_int, libc::timespec);
+        let msg = recvmsg::<SockaddrStorage>(fd, &mut iov, Some(&mut cmsg_buf), MsgFlags::empty())
+            .map_err(|e| Error::Recv { source: e.into() })?;
+        let mut to = None;
+        let mut if_index = 0;
+        let mut ttl = None;
+        let mut timestamp = None;
+        for cmsg in msg.cmsgs().map_err(|e| Error::Recv { source: e.into() })? {
+            match cmsg {
+                ControlMessageOwned::Ipv4PacketInfo(info) => {
+                    let addr = Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr));
+                    to = Some(SocketAddr::new(IpAddr::V4(addr), port));
+                    if_index = info.ipi_ifindex as u32;
+                }
+                ControlMessageOwned::Ipv6PacketInfo(info) => {
+                    let addr = Ipv6Addr::from(info.ipi6_addr.s6_addr);
+                    to = Some(SocketAddr::new(IpAddr::V6(addr), port));
+                    if_index = info.ipi6_ifindex;
+                }
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                ControlMessageOwned::Ipv4Ttl(hops) | ControlMessageOwned::Ipv6HopLimit(hops) => {
+                    ttl = u8::try_from(hops).ok();
+                }
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                ControlMessageOwned::ScmTimestampns(ts) => {
+                    let since_epoch = Duration::new(ts.tv_sec() as u64, ts.tv_nsec() as u32);
+                    timestamp = Some(UNIX_EPOCH + since_epoch);
+                }
+                _ => {}
+            }
         }
-        let fd = self.sock.as_ref().unwrap().as_raw_fd();
-        let res = shutdown(fd, Shutdown::Both);
-        if res.is_err() {
-            warn!("shutdown ({})", res.err().unwrap().desc());
+        let n_bytes = msg.bytes;
+        let truncated = msg.flags.contains(MsgFlags::MSG_TRUNC);
+        let from = msg.address.as_ref().and_then(to_socket_addr);
+        let mut pkt = Packet::from_bytes(&buf[0..n_bytes]);
+        if let Some(from) = from {
+            pkt.set_from(from);
+        }
+        if let Some(to) = to {
+            pkt.set_to(to);
+            if to.ip().is_multicast() {
+                pkt.set_kind(PacketKind::Multicast);
+            } else if to.ip() == IpAddr::V4(Ipv4Addr::BROADCAST) {
+                pkt.set_kind(PacketKind::Broadcast);
+            }
         }
-        let res = close(fd);
-        if res.is_err() {
-            warn!("close {:?}", res.err());
+        pkt.set_interface_index(if_index);
+        if let Some(ttl) = ttl {
+            pkt.set_ttl(ttl);
         }
-        thread::sleep(time::Duration::from_secs(1));
+        pkt.set_timestamp(timestamp.unwrap_or_else(SystemTime::now));
+        pkt.set_truncated(truncated);
+        Ok(pkt)
     }
 
-    pub fn send_to(&self, buf: &[u8], to_addr: SocketAddr) -> Result<usize> {
-        if self.sock.is_none() {
-            return Err(BindError::new());
+    /// join_multicast_v4 joins the group on the interface. The socket can join any number of groups.
+    pub fn join_multicast_v4(&self, multiaddr: &Ipv4Addr, ifaddr: &Ipv4Addr) -> Result<()> {
+        self.sock()?
+            .join_multicast_v4(multiaddr, ifaddr)
+            .map_err(|source| Error::JoinMulticast {
+                group: IpAddr::V4(*multiaddr),
+                iface: IpAddr::V4(*ifaddr),
+                source,
+            })
+    }
+
+    /// join_multicast_v6 joins the group on the interface which has the specified address.
+    pub fn join_multicast_v6(&self, multiaddr: &Ipv6Addr, ifaddr: &Ipv6Addr) -> Result<()> {
+        // NOTE: The index 0 lets the kernel choose the interface, which is only expected for the unspecified address.
+        let if_index = get_interface_index(IpAddr::V6(*ifaddr)).unwrap_or(0);
+        set_membership_index(self.sock()?, true, &IpAddr::V6(*multiaddr), if_index).map_err(
+            |source| Error::JoinMulticast {
+                group: IpAddr::V6(*multiaddr),
+                iface: IpAddr::V6(*ifaddr),
+                source,
+            },
+        )
+    }
+
+    pub fn leave_multicast_v4(&self, multiaddr: &Ipv4Addr, ifaddr: &Ipv4Addr) -> Result<()> {
+        self.sock()?
+            .leave_multicast_v4(multiaddr, ifaddr)
+            .map_err(|source| Error::LeaveMulticast {
+                group: IpAddr::V4(*multiaddr),
+                iface: IpAddr::V4(*ifaddr),
+                source,
+            })
+    }
+
+    pub fn leave_multicast_v6(&self, multiaddr: &Ipv6Addr, ifaddr: &Ipv6Addr) -> Result<()> {
+        let if_index = get_interface_index(IpAddr::V6(*ifaddr)).unwrap_or(0);
+        set_membership_index(self.sock()?, false, &IpAddr::V6(*multiaddr), if_index).map_err(
+            |source| Error::LeaveMulticast {
+                group: IpAddr::V6(*multiaddr),
+                iface: IpAddr::V6(*ifaddr),
+                source,
+            },
+        )
+    }
+
+    /// join_multicast_index joins the group on the interface which has the specified index, with ip_mreqn for IPv4.
+    /// The interface address of the returned error is the unspecified address, since the interface is identified only by the index.
+    pub fn join_multicast_index(&self, multiaddr: &IpAddr, if_index: u32) -> Result<()> {
+        set_membership_index(self.sock()?, true, multiaddr, if_index).map_err(|source| {
+            Error::JoinMulticast {
+                group: *multiaddr,
+                iface: unspecified_addr(multiaddr),
+                source,
+            }
+        })
+    }
+
+    pub fn leave_multicast_index(&self, multiaddr: &IpAddr, if_index: u32) -> Result<()> {
+        set_membership_index(self.sock()?, false, multiaddr, if_index).map_err(|source| {
+            Error::LeaveMulticast {
+                group: *multiaddr,
+                iface: unspecified_addr(multiaddr),
+                source,
+            }
+        })
+    }
+
+    /// set_multicast_ttl sets IP_MULTICAST_TTL or IPV6_MULTICAST_HOPS, which limits how far the multicast datagrams are forwarded.
+    pub fn set_multicast_ttl(&self, ttl: u8) -> Result<()> {
+        let sock = self.sock()?;
+        if self.addr()?.is_ipv4() {
+            Ok(sock.set_multicast_ttl_v4(ttl as u32)?)
+        } else {
+            Ok(setsockopt(sock, Ipv6MulticastHops, &(ttl as libc::c_int))
+                .map_err(io::Error::from)?)
         }
-        let res = self.sock.as_ref().unwrap().send_to(buf, to_addr);
-        if res.is_err() {
-            return Err(ScoketError::new(&format!("{:?}", res.err())));
+    }
+
+    pub fn multicast_ttl(&self) -> Result<u8> {
+        let sock = self.sock()?;
+        let ttl = if self.addr()?.is_ipv4() {
+            sock.multicast_ttl_v4()?
+        } else {
+            getsockopt(sock, Ipv6MulticastHops).map_err(io::Error::from)? as u32
+        };
+        Ok(u8::try_from(ttl).unwrap_or(u8::MAX))
+    }
+
+    /// set_multicast_loop sets IP_MULTICAST_LOOP or IPV6_MULTICAST_LOOP, which loops the multicast datagrams back to the local sockets.
+    pub fn set_multicast_loop(&self, on: bool) -> Result<()> {
+        let sock = self.sock()?;
+        if self.addr()?.is_ipv4() {
+            Ok(sock.set_multicast_loop_v4(on)?)
+        } else {
+            Ok(sock.set_multicast_loop_v6(on)?)
         }
-        Ok(res.unwrap())
     }
 
-    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
-        if self.sock.is_none() {
-            return Err(BindError::new());
+    pub fn multicast_loop(&self) -> Result<bool> {
+        let sock = self.sock()?;
+        if self.addr()?.is_ipv4() {
+            Ok(sock.multicast_loop_v4()?)
+        } else {
+            Ok(sock.multicast_loop_v6()?)
         }
-        self.sock.as_ref().unwrap().recv_from(buf)
     }
 
-    pub fn join_multicast_v4(&mut self, multiaddr: &Ipv4Addr, ifaddr: &Ipv4Addr) -> Result<()> {
-        if self.sock.is_none() {
-            return Err(BindError::new());
+    /// set_multicast_all sets IP_MULTICAST_ALL or IPV6_MULTICAST_ALL, which delivers the datagrams of all groups joined on the host
+    /// to the socket bound to the wildcard address. It is enabled by default, and is supported only on Linux.
+    pub fn set_multicast_all(&self, on: bool) -> Result<()> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            let fd = self.sock()?.as_raw_fd();
+            let value = on as libc::c_int;
+            let ret = if self.addr()?.is_ipv4() {
+                setsockopt_raw(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_ALL, &value)
+            } else {
+                setsockopt_raw(fd, libc::IPPROTO_IPV6, IPV6_MULTICAST_ALL, &value)
+            };
+            Ok(ret?)
         }
-        self.addr = Some(
-            format!("{}:{}", ifaddr, self.addr.unwrap().port())
-                .parse()
-                .unwrap(),
-        );
-        self.sock
-            .as_ref()
-            .unwrap()
-            .join_multicast_v4(multiaddr, ifaddr)
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        {
+            let _ = on;
+            Err(Error::Io(io::Error::from(io::ErrorKind::Unsupported)))
+        }
+    }
+
+    /// set_multicast_interface sets IP_MULTICAST_IF or IPV6_MULTICAST_IF, which selects the interface to send the multicast datagrams from.
+    pub fn set_multicast_interface(&self, ifaddr: &IpAddr) -> Result<()> {
+        let fd = self.sock()?.as_raw_fd();
+        let ret = match ifaddr {
+            IpAddr::V4(ifaddr) => {
+                let addr = libc::in_addr {
+                    s_addr: u32::from(*ifaddr).to_be(),
+                };
+                setsockopt_raw(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_IF, &addr)
+            }
+            IpAddr::V6(_) => {
+                let if_index = get_interface_index(*ifaddr).unwrap_or(0);
+                setsockopt_raw(fd, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_IF, &if_index)
+            }
+        };
+        Ok(ret?)
+    }
+
+    /// source_filter checks the address families, and applies the operation to the source of the group on the interface.
+    fn source_filter(
+        &self,
+        op: SourceOp,
+        source: &IpAddr,
+        multiaddr: &IpAddr,
+        ifaddr: &IpAddr,
+    ) -> Result<()> {
+        if source.is_ipv4() != multiaddr.is_ipv4() || multiaddr.is_ipv4() != ifaddr.is_ipv4() {
+            return Err(Error::AddressFamilyMismatch {
+                group: *multiaddr,
+                iface: *ifaddr,
+            });
+        }
+        let fd = self.sock()?.as_raw_fd();
+        set_source_filter(fd, op, source, multiaddr, ifaddr).map_err(|source| match op {
+            SourceOp::Join => Error::JoinMulticast {
+                group: *multiaddr,
+                iface: *ifaddr,
+                source,
+            },
+            SourceOp::Leave => Error::LeaveMulticast {
+                group: *multiaddr,
+                iface: *ifaddr,
+                source,
+            },
+            SourceOp::Block | SourceOp::Unblock => Error::Io(source),
+        })
+    }
+
+    /// join_source_multicast joins the source-specific group, which only receives the datagrams from the source, on the interface.
+    pub fn join_source_multicast(
+        &self,
+        source: &IpAddr,
+        multiaddr: &IpAddr,
+        ifaddr: &IpAddr,
+    ) -> Result<()> {
+        self.source_filter(SourceOp::Join, source, multiaddr, ifaddr)
+    }
+
+    pub fn leave_source_multicast(
+        &self,
+        source: &IpAddr,
+        multiaddr: &IpAddr,
+        ifaddr: &IpAddr,
+    ) -> Result<()> {
+        self.source_filter(SourceOp::Leave, source, multiaddr, ifaddr)
+    }
+
+    /// block_source stops receiving the datagrams from the source in the any-source group joined on the interface.
+    pub fn block_source(&self, source: &IpAddr, multiaddr: &IpAddr, ifaddr: &IpAddr) -> Result<()> {
+        self.source_filter(SourceOp::Block, source, multiaddr, ifaddr)
+    }
+
+    pub fn unblock_source(
+        &self,
+        source: &IpAddr,
+        multiaddr: &IpAddr,
+        ifaddr: &IpAddr,
+    ) -> Result<()> {
+        self.source_filter(SourceOp::Unblock, source, multiaddr, ifaddr)
+    }
+
+    /// join_multicast joins the group on the interface which has the specified address, or returns Error::AddressFamilyMismatch.
+    pub fn join_multicast(&self, multiaddr: &IpAddr, ifaddr: &IpAddr) -> Result<()> {
+        match (multiaddr, ifaddr) {
+            (IpAddr::V4(maddr_v4), IpAddr::V4(ifaddr_v4)) => {
+                self.join_multicast_v4(maddr_v4, ifaddr_v4)
+            }
+            (IpAddr::V6(maddr_v6), IpAddr::V6(ifaddr_v6)) => {
+                self.join_multicast_v6(maddr_v6, ifaddr_v6)
+            }
+            _ => Err(Error::AddressFamilyMismatch {
+                group: *multiaddr,
+                iface: *ifaddr,
+            }),
+        }
+    }
+
+    /// leave_multicast leaves the group on the interface which has the specified address, or returns Error::AddressFamilyMismatch.
+    pub fn leave_multicast(&self, multiaddr: &IpAddr, ifaddr: &IpAddr) -> Result<()> {
+        match (multiaddr, ifaddr) {
+            (IpAddr::V4(maddr_v4), IpAddr::V4(ifaddr_v4)) => {
+                self.leave_multicast_v4(maddr_v4, ifaddr_v4)
+            }
+            (IpAddr::V6(maddr_v6), IpAddr::V6(ifaddr_v6)) => {
+                self.leave_multicast_v6(maddr_v6, ifaddr_v6)
+            }
+            _ => Err(Error::AddressFamilyMismatch {
+                group: *multiaddr,
+                iface: *ifaddr,
+            }),
+        }
+    }
+}
+
+fn unspecified_addr(addr: &IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }
+}
+
+/// set_membership_index joins or leaves the group on the interface which has the specified index,
+/// with IP_ADD_MEMBERSHIP and ip_mreqn for IPv4, and IPV6_JOIN_GROUP for IPv6.
+fn set_membership_index(
+    sock: &std::net::UdpSocket,
+    join: bool,
+    multiaddr: &IpAddr,
+    if_index: u32,
+) -> io::Result<()> {
+    match multiaddr {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        IpAddr::V4(multiaddr) => {
+            let mreq = libc::ip_mreqn {
+                imr_multiaddr: libc::in_addr {
+                    s_addr: u32::from(*multiaddr).to_be(),
+                },
+                imr_address: libc::in_addr { s_addr: 0 },
+                imr_ifindex: if_index as libc::c_int,
+            };
+            let name = if join {
+                libc::IP_ADD_MEMBERSHIP
+            } else {
+                libc::IP_DROP_MEMBERSHIP
+            };
+            setsockopt_raw(sock.as_raw_fd(), libc::IPPROTO_IP, name, &mreq)
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        IpAddr::V4(_) => Err(io::Error::from(io::ErrorKind::Unsupported)),
+        IpAddr::V6(multiaddr) => {
+            if join {
+                sock.join_multicast_v6(multiaddr, if_index)
+            } else {
+                sock.leave_multicast_v6(multiaddr, if_index)
+            }
+        }
+    }
+}
+
+/// SourceOp is an operation on the source filter of a multicast group.
+#[derive(Clone, Copy)]
+enum SourceOp {
+    Join,
+    Leave,
+    Block,
+    Unblock,
+}
+
+fn setsockopt_raw<T>(
+    fd: RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: &T,
+) -> io::Result<()> {
+    // SAFETY: The value is a plain C struct which lives during the call, and its size is passed with it.
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            value as *const T as *const libc::c_void,
+            std::mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
     }
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn to_sockaddr_storage(addr: &Ipv6Addr) -> libc::sockaddr_storage {
+    // SAFETY: sockaddr_storage is a plain C struct which is large enough and aligned for sockaddr_in6.
+    unsafe {
+        let mut storage: libc::sockaddr_storage = std::mem::zeroed();
+        let sin6 = &mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr_in6;
+        (*sin6).sin6_family = libc::AF_INET6 as libc::sa_family_t;
+        (*sin6).sin6_addr.s6_addr = addr.octets();
+        storage
+    }
+}
+
+/// set_source_filter applies the operation to the source of the group on the interface,
+/// with IP_ADD_SOURCE_MEMBERSHIP and the related options for IPv4, and MCAST_JOIN_SOURCE_GROUP and the related options for IPv6.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_source_filter(
+    fd: RawFd,
+    op: SourceOp,
+    source: &IpAddr,
+    multiaddr: &IpAddr,
+    ifaddr: &IpAddr,
+) -> io::Result<()> {
+    match (source, multiaddr, ifaddr) {
+        (IpAddr::V4(source), IpAddr::V4(multiaddr), IpAddr::V4(ifaddr)) => {
+            let mreq = libc::ip_mreq_source {
+                imr_multiaddr: libc::in_addr {
+                    s_addr: u32::from(*multiaddr).to_be(),
+                },
+                imr_interface: libc::in_addr {
+                    s_addr: u32::from(*ifaddr).to_be(),
+                },
+                imr_sourceaddr: libc::in_addr {
+                    s_addr: u32::from(*source).to_be(),
+                },
+            };
+            let name = match op {
+                SourceOp::Join => libc::IP_ADD_SOURCE_MEMBERSHIP,
+                SourceOp::Leave => libc::IP_DROP_SOURCE_MEMBERSHIP,
+                SourceOp::Block => libc::IP_BLOCK_SOURCE,
+                SourceOp::Unblock => libc::IP_UNBLOCK_SOURCE,
+            };
+            setsockopt_raw(fd, libc::IPPROTO_IP, name, &mreq)
+        }
+        (IpAddr::V6(source), IpAddr::V6(multiaddr), IpAddr::V6(_)) => {
+            let req = libc::group_source_req {
+                gsr_interface: get_interface_index(*ifaddr).unwrap_or(0),
+                gsr_group: to_sockaddr_storage(multiaddr),
+                gsr_source: to_sockaddr_storage(source),
+            };
+            let name = match op {
+                SourceOp::Join => libc::MCAST_JOIN_SOURCE_GROUP,
+                SourceOp::Leave => libc::MCAST_LEAVE_SOURCE_GROUP,
+                SourceOp::Block => libc::MCAST_BLOCK_SOURCE,
+                SourceOp::Unblock => libc::MCAST_UNBLOCK_SOURCE,
+            };
+            setsockopt_raw(fd, libc::IPPROTO_IPV6, name, &req)
+        }
+        _ => Err(io::Error::from(io::ErrorKind::InvalidInput)),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_source_filter(
+    _fd: RawFd,
+    _op: SourceOp,
+    _source: &IpAddr,
+    _multiaddr: &IpAddr,
+    _ifaddr: &IpAddr,
+) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+fn to_socket_addr(addr: &SockaddrStorage) -> Option<SocketAddr> {
+    if let Some(addr) = addr.as_sockaddr_in() {
+        return Some(SocketAddr::V4((*addr).into()));
+    }
+    if let Some(addr) = addr.as_sockaddr_in6() {
+        return Some(SocketAddr::V6((*addr).into()));
+    }
+    None
+}
 
-    pub fn join_multicast_v6(&mut self, multiaddr: &Ipv6Addr, ifaddr: &Ipv6Addr) -> Result<()> {
-        if self.sock.is_none() {
-            return Err(BindError::new());
+impl AsRawFd for UdpSocket {
+    /// as_raw_fd returns the underlying file descriptor, or -1 if the socket is not bound.
+    fn as_raw_fd(&self) -> RawFd {
+        match &self.sock {
+            Some(sock) => sock.as_raw_fd(),
+            None => -1,
         }
-        self.addr = Some(
-            format!("{}:{}", ifaddr, self.addr.unwrap().port())
-                .parse()
-                .unwrap(),
-        );
-        self.sock.as_ref().unwrap().join_multicast_v6(multiaddr, 0)
     }
 }
 
@@ -0,0 +1,191 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use nix::sys::socket::setsockopt;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use nix::sys::socket::sockopt::{BindToDevice, Ipv4Tos, Ipv6TClass};
+use nix::sys::socket::sockopt::{Broadcast, Ipv6V6Only, RcvBuf, ReuseAddr, ReusePort, SndBuf};
+use std::io;
+use std::os::unix::io::{BorrowedFd, RawFd};
+use std::time::Duration;
+
+/// UdpSocketOptions holds the options which UdpSocket sets when it binds a socket.
+/// The default options enable SO_REUSEADDR, SO_REUSEPORT and IPV6_V6ONLY, and leave the others to the kernel defaults.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UdpSocketOptions {
+    reuse_addr: bool,
+    reuse_port: bool,
+    only_v6: bool,
+    broadcast: Option<bool>,
+    tos: Option<u8>,
+    device: Option<String>,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+    nonblocking: Option<bool>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl Default for UdpSocketOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UdpSocketOptions {
+    pub fn new() -> UdpSocketOptions {
+        UdpSocketOptions {
+            reuse_addr: true,
+            reuse_port: true,
+            only_v6: true,
+            broadcast: None,
+            tos: None,
+            device: None,
+            recv_buffer_size: None,
+            send_buffer_size: None,
+            nonblocking: None,
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// reuse_address sets SO_REUSEADDR.
+    pub fn reuse_address(mut self, on: bool) -> Self {
+        self.reuse_addr = on;
+        self
+    }
+
+    /// reuse_port sets SO_REUSEPORT.
+    pub fn reuse_port(mut self, on: bool) -> Self {
+        self.reuse_port = on;
+        self
+    }
+
+    /// only_v6 sets IPV6_V6ONLY of the IPv6 sockets.
+    pub fn only_v6(mut self, on: bool) -> Self {
+        self.only_v6 = on;
+        self
+    }
+
+    /// broadcast sets SO_BROADCAST.
+    pub fn broadcast(mut self, on: bool) -> Self {
+        self.broadcast = Some(on);
+        self
+    }
+
+    /// tos sets IP_TOS of the IPv4 sockets, or IPV6_TCLASS (the traffic class) of the IPv6 sockets.
+    pub fn tos(mut self, tos: u8) -> Self {
+        self.tos = Some(tos);
+        self
+    }
+
+    /// dscp sets the DSCP code point, which is the upper six bits of the TOS or the traffic class.
+    pub fn dscp(mut self, dscp: u8) -> Self {
+        self.tos = Some(dscp << 2);
+        self
+    }
+
+    /// bind_to_device sets SO_BINDTODEVICE, which only sends and receives the datagrams on the named interface.
+    pub fn bind_to_device(mut self, name: &str) -> Self {
+        self.device = Some(name.to_string());
+        self
+    }
+
+    /// recv_buffer_size sets SO_RCVBUF.
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// send_buffer_size sets SO_SNDBUF.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// nonblocking sets O_NONBLOCK. The servers poll their sockets by themselves, so this is for the sockets driven by the caller.
+    pub fn nonblocking(mut self, on: bool) -> Self {
+        self.nonblocking = Some(on);
+        self
+    }
+
+    /// read_timeout sets SO_RCVTIMEO. The servers replace it with their own polling interval when they start.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// write_timeout sets SO_SNDTIMEO.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// apply_before_bind sets the options which have to be set before the socket is bound.
+    pub fn apply_before_bind(&self, fd: RawFd, is_ipv6: bool) -> io::Result<()> {
+        // SAFETY: The caller owns the file descriptor during this call.
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+        setsockopt(&fd, ReuseAddr, &self.reuse_addr)?;
+        setsockopt(&fd, ReusePort, &self.reuse_port)?;
+        if is_ipv6 {
+            setsockopt(&fd, Ipv6V6Only, &self.only_v6)?;
+        }
+        if let Some(device) = &self.device {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            setsockopt(&fd, BindToDevice, &std::ffi::OsString::from(device))?;
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            {
+                let _ = device;
+                return Err(io::Error::from(io::ErrorKind::Unsupported));
+            }
+        }
+        Ok(())
+    }
+
+    /// apply sets the other options to the bound socket.
+    pub fn apply(&self, sock: &std::net::UdpSocket, is_ipv6: bool) -> io::Result<()> {
+        if let Some(on) = self.broadcast {
+            setsockopt(sock, Broadcast, &on)?;
+        }
+        if let Some(tos) = self.tos {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            if is_ipv6 {
+                setsockopt(sock, Ipv6TClass, &(tos as i32))?;
+            } else {
+                setsockopt(sock, Ipv4Tos, &(tos as i32))?;
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            {
+                let _ = (tos, is_ipv6);
+                return Err(io::Error::from(io::ErrorKind::Unsupported));
+            }
+        }
+        if let Some(size) = self.recv_buffer_size {
+            setsockopt(sock, RcvBuf, &size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            setsockopt(sock, SndBuf, &size)?;
+        }
+        if let Some(on) = self.nonblocking {
+            sock.set_nonblocking(on)?;
+        }
+        if let Some(timeout) = self.read_timeout {
+            sock.set_read_timeout(Some(timeout))?;
+        }
+        if let Some(timeout) = self.write_timeout {
+            sock.set_write_timeout(Some(timeout))?;
+        }
+        Ok(())
+    }
+}
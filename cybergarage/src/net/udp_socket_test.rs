@@ -0,0 +1,285 @@
+// Copyright (C) 2022 Satoshi Konno All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::os::unix::io::{AsRawFd, BorrowedFd};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+
+    use nix::sys::socket::getsockopt;
+    use nix::sys::socket::sockopt::{BindToDevice, Broadcast, Ipv4Tos, Ipv6V6Only};
+
+    use crate::net::error::Error;
+    use crate::net::interface::get_interface_index;
+    use crate::net::udp_socket::*;
+    use crate::net::udp_socket_options::UdpSocketOptions;
+
+    #[test]
+    fn udp_socket_close() {
+        const TEST_SOCKET_COUNT: i32 = 100;
+        const TEST_FILE_COUNT: usize = 16;
+
+        // Open and check other descriptors concurrently, so that a descriptor number reused
+        // after close() would be closed again by a double close and detected here.
+        let done = Arc::new(AtomicBool::new(false));
+        let checker_done = done.clone();
+        let checker = thread::spawn(move || {
+            while !checker_done.load(Ordering::SeqCst) {
+                let mut files = Vec::new();
+                for _ in 0..TEST_FILE_COUNT {
+                    files.push(File::open("/dev/null").unwrap());
+                }
+                thread::yield_now();
+                for file in files.iter() {
+                    assert!(file.metadata().is_ok());
+                }
+            }
+        });
+
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        for _ in 0..TEST_SOCKET_COUNT {
+            let mut sock = UdpSocket::new();
+            assert!(sock.bind(addr).is_ok());
+            sock.close();
+            assert!(sock.addr().is_err());
+            sock.close();
+        }
+
+        done.store(true, Ordering::SeqCst);
+        assert!(checker.join().is_ok());
+    }
+
+    #[test]
+    fn udp_socket_recv_packet() {
+        const TEST_BUFFER_SIZE: usize = 10;
+
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        let mut sock = UdpSocket::new();
+        assert!(sock.bind(addr).is_ok());
+        let sock_addr = sock.addr().unwrap();
+        let mut sender = UdpSocket::new();
+        assert!(sender.bind(addr).is_ok());
+        let sender_addr = sender.addr().unwrap();
+
+        let mut buf = [0_u8; TEST_BUFFER_SIZE];
+
+        let sent_time = SystemTime::now();
+        assert!(sender.send_to(&[1_u8; TEST_BUFFER_SIZE], sock_addr).is_ok());
+        let pkt = sock.recv_packet(&mut buf).unwrap();
+        assert!(sent_time <= pkt.timestamp());
+        assert!(pkt.timestamp() <= SystemTime::now());
+        assert!(pkt.ttl().is_some());
+        assert_eq!(pkt.bytes().len(), TEST_BUFFER_SIZE);
+        assert_eq!(pkt.from(), sender_addr);
+        assert_eq!(pkt.to(), sock_addr);
+        assert_eq!(pkt.interface_name(), Some("lo".to_string()));
+        assert!(!pkt.is_truncated());
+
+        assert!(sender.send_to(&[2_u8; 100], sock_addr).is_ok());
+        let pkt = sock.recv_packet(&mut buf).unwrap();
+        assert_eq!(pkt.bytes(), &vec![2_u8; TEST_BUFFER_SIZE]);
+        assert!(pkt.is_truncated());
+    }
+
+    #[test]
+    fn udp_socket_buffer_size() {
+        const TEST_BUFFER_SIZE: usize = 65536;
+
+        let mut sock = UdpSocket::new();
+        assert!(sock.set_recv_buffer_size(TEST_BUFFER_SIZE).is_err());
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        assert!(sock.bind(addr).is_ok());
+        // Linux doubles the requested size for its bookkeeping overhead.
+        assert!(sock.set_recv_buffer_size(TEST_BUFFER_SIZE).is_ok());
+        assert!(TEST_BUFFER_SIZE <= sock.recv_buffer_size().unwrap());
+        assert!(sock.set_send_buffer_size(TEST_BUFFER_SIZE).is_ok());
+        assert!(TEST_BUFFER_SIZE <= sock.send_buffer_size().unwrap());
+    }
+
+    #[test]
+    fn udp_socket_source_multicast() {
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ssm_addr = IpAddr::V4(Ipv4Addr::new(232, 0, 23, 1));
+        let asm_addr = IpAddr::V4(Ipv4Addr::new(239, 255, 255, 2));
+
+        let mut sock = UdpSocket::new();
+        assert!(sock
+            .bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))
+            .is_ok());
+        assert!(sock
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .is_ok());
+        let port = sock.addr().unwrap().port();
+
+        let mut source = UdpSocket::new();
+        assert!(source.bind(SocketAddr::new(ifaddr, 0)).is_ok());
+        assert!(source.set_multicast_interface(&ifaddr).is_ok());
+        let source_addr = source.addr().unwrap().ip();
+        let mut other = UdpSocket::new();
+        let other_addr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+        assert!(other.bind(SocketAddr::new(other_addr, 0)).is_ok());
+        assert!(other.set_multicast_interface(&ifaddr).is_ok());
+
+        let mut buf = [0_u8; 1];
+        let mut is_received = |sender: &UdpSocket, maddr: IpAddr| {
+            let to_addr = SocketAddr::new(maddr, port);
+            assert!(sender.send_to(&[0_u8; 1], to_addr).is_ok());
+            matches!(sock.recv_packet(&mut buf), Ok(pkt) if pkt.to().ip() == maddr)
+        };
+
+        // A source-specific group receives the datagrams only from the joined source.
+        assert!(sock
+            .join_source_multicast(&source_addr, &ssm_addr, &ifaddr)
+            .is_ok());
+        assert!(is_received(&source, ssm_addr));
+        assert!(!is_received(&other, ssm_addr));
+        assert!(sock
+            .leave_source_multicast(&source_addr, &ssm_addr, &ifaddr)
+            .is_ok());
+        assert!(!is_received(&source, ssm_addr));
+
+        // An any-source group receives from all sources except the blocked ones.
+        assert!(sock.join_multicast(&asm_addr, &ifaddr).is_ok());
+        assert!(sock.block_source(&other_addr, &asm_addr, &ifaddr).is_ok());
+        assert!(is_received(&source, asm_addr));
+        assert!(!is_received(&other, asm_addr));
+        assert!(sock.unblock_source(&other_addr, &asm_addr, &ifaddr).is_ok());
+        assert!(is_received(&other, asm_addr));
+    }
+
+    #[test]
+    fn udp_socket_multicast_index() {
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let maddr = IpAddr::V4(Ipv4Addr::new(239, 255, 255, 3));
+        let if_index = get_interface_index(ifaddr).unwrap();
+
+        let mut sock = UdpSocket::new();
+        assert!(sock
+            .bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))
+            .is_ok());
+        assert!(sock
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .is_ok());
+        let to_addr = SocketAddr::new(maddr, sock.addr().unwrap().port());
+
+        let mut sender = UdpSocket::new();
+        assert!(sender.bind(SocketAddr::new(ifaddr, 0)).is_ok());
+        assert!(sender.set_multicast_interface(&ifaddr).is_ok());
+
+        let mut buf = [0_u8; 1];
+        assert!(sock.join_multicast_index(&maddr, if_index).is_ok());
+        assert!(sender.send_to(&[0_u8; 1], to_addr).is_ok());
+        match sock.recv_packet(&mut buf) {
+            Ok(pkt) => {
+                assert_eq!(pkt.to().ip(), maddr);
+                assert_eq!(pkt.interface_index(), if_index);
+            }
+            ret => panic!("{:?}", ret.err()),
+        }
+
+        assert!(sock.leave_multicast_index(&maddr, if_index).is_ok());
+        assert!(matches!(
+            sock.leave_multicast_index(&maddr, if_index),
+            Err(Error::LeaveMulticast { .. })
+        ));
+        assert!(sender.send_to(&[0_u8; 1], to_addr).is_ok());
+        assert!(sock.recv_packet(&mut buf).is_err());
+    }
+
+    #[test]
+    fn udp_socket_multicast_options() {
+        let sock = UdpSocket::new();
+        assert!(sock.set_multicast_ttl(255).is_err());
+
+        for ifaddr in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let mut sock = UdpSocket::new();
+            assert!(sock.bind(SocketAddr::new(ifaddr, 0)).is_ok());
+            assert!(sock.set_multicast_ttl(255).is_ok());
+            assert_eq!(sock.multicast_ttl().unwrap(), 255);
+            assert!(sock.set_multicast_ttl(2).is_ok());
+            assert_eq!(sock.multicast_ttl().unwrap(), 2);
+            assert!(sock.set_multicast_loop(false).is_ok());
+            assert!(!sock.multicast_loop().unwrap());
+            assert!(sock.set_multicast_loop(true).is_ok());
+            assert!(sock.multicast_loop().unwrap());
+            assert!(sock.set_multicast_interface(&ifaddr).is_ok());
+        }
+    }
+
+    #[test]
+    fn udp_socket_options() {
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+        // The default options reuse the address and the port, and the disabled ones do not.
+        let mut sock = UdpSocket::new();
+        assert!(sock.bind(addr).is_ok());
+        let mut other = UdpSocket::new();
+        assert!(other.bind(sock.addr().unwrap()).is_ok());
+
+        let options = UdpSocketOptions::new()
+            .reuse_address(false)
+            .reuse_port(false);
+        let mut sock = UdpSocket::new();
+        assert!(sock.bind_with_options(addr, &options).is_ok());
+        let mut other = UdpSocket::new();
+        match other.bind_with_options(sock.addr().unwrap(), &options) {
+            Err(Error::Bind { source, .. }) => assert_eq!(source.kind(), io::ErrorKind::AddrInUse),
+            ret => panic!("{:?}", ret),
+        }
+
+        let options = UdpSocketOptions::new()
+            .broadcast(true)
+            .dscp(46)
+            .bind_to_device("lo")
+            .recv_buffer_size(65536)
+            .send_buffer_size(65536)
+            .nonblocking(true)
+            .read_timeout(Duration::from_millis(100))
+            .write_timeout(Duration::from_millis(100));
+        let mut sock = UdpSocket::new();
+        assert!(sock.bind_with_options(addr, &options).is_ok());
+        let fd = unsafe { BorrowedFd::borrow_raw(sock.as_raw_fd()) };
+        assert!(getsockopt(&fd, Broadcast).unwrap());
+        assert_eq!(getsockopt(&fd, Ipv4Tos).unwrap(), 46 << 2);
+        assert_eq!(getsockopt(&fd, BindToDevice).unwrap(), "lo");
+        assert!(65536 <= sock.recv_buffer_size().unwrap());
+        let mut buf = [0_u8; 1];
+        match sock.recv_from(&mut buf) {
+            Err(Error::Recv { source }) => assert_eq!(source.kind(), io::ErrorKind::WouldBlock),
+            ret => panic!("{:?}", ret),
+        }
+
+        let v6_addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0);
+        let mut sock = UdpSocket::new();
+        assert!(sock.bind(v6_addr).is_ok());
+        let fd = unsafe { BorrowedFd::borrow_raw(sock.as_raw_fd()) };
+        assert!(getsockopt(&fd, Ipv6V6Only).unwrap());
+        let mut sock = UdpSocket::new();
+        let options = UdpSocketOptions::new().only_v6(false);
+        assert!(sock.bind_with_options(v6_addr, &options).is_ok());
+        let fd = unsafe { BorrowedFd::borrow_raw(sock.as_raw_fd()) };
+        assert!(!getsockopt(&fd, Ipv6V6Only).unwrap());
+
+        let options = UdpSocketOptions::new().bind_to_device("none0");
+        assert!(UdpSocket::new().bind_with_options(addr, &options).is_err());
+    }
+}
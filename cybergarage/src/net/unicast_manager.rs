@@ -12,48 +12,188 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use log::*;
 use std::net::{IpAddr, SocketAddr};
 
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::{Error, Result};
 use crate::net::interface::*;
-use crate::net::observer::ObserverObject;
+use crate::net::interface_observer::{InterfaceEvent, InterfaceObserver};
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
 use crate::net::packet::Packet;
-use crate::net::result::Result;
+use crate::net::route::{find_route, is_same_family, Route, SendFallback};
+use crate::net::send_report::SendReport;
+use crate::net::udp_socket_options::UdpSocketOptions;
 use crate::net::unicast_server::UnicastServer;
 
 pub struct UnicastManager {
     udp_servers: Vec<UnicastServer>,
+    observers: NotifytManager,
+    filter: InterfaceFilter,
+    port: Option<u16>,
+    fallback: SendFallback,
+    socket_options: UdpSocketOptions,
+}
+
+impl Default for UnicastManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UnicastManager {
     pub fn new() -> UnicastManager {
         UnicastManager {
             udp_servers: Vec::new(),
+            observers: NotifytManager::new(),
+            filter: InterfaceFilter::new(),
+            port: None,
+            fallback: SendFallback::default(),
+            socket_options: UdpSocketOptions::new(),
         }
     }
 
-    pub fn add_observer(&mut self, observer: ObserverObject) -> bool {
+    /// set_interface_filter sets the filter which selects the interfaces to bind in start() and rebind().
+    pub fn set_interface_filter(&mut self, filter: InterfaceFilter) {
+        self.filter = filter;
+    }
+
+    pub fn interface_filter(&self) -> &InterfaceFilter {
+        &self.filter
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers by the servers which are bound after this call.
+    /// The servers share the worker threads of DispatchMode::Pool, which are started once for the manager.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.observers.set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> &DispatchOptions {
+        self.observers.dispatch_options()
+    }
+
+    /// set_socket_options sets the socket options of the servers which are bound after this call.
+    pub fn set_socket_options(&mut self, options: UdpSocketOptions) {
+        self.socket_options = options;
+    }
+
+    pub fn socket_options(&self) -> &UdpSocketOptions {
+        &self.socket_options
+    }
+
+    /// remove_observer removes the observer from all servers, and returns false if it has not been added.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
         for udp_server in self.udp_servers.iter_mut() {
-            if !udp_server.add_observer(observer.clone()) {
-                return false;
-            }
+            udp_server.remove_observer(id);
         }
-        true
+        self.observers.remove_observer(id)
     }
 
-    pub fn send(&self, to_addr: SocketAddr, msg: &Packet) -> bool {
+    /// set_send_fallback sets how send() handles the destinations which are not on the subnet of any bound interface.
+    pub fn set_send_fallback(&mut self, fallback: SendFallback) {
+        self.fallback = fallback;
+    }
+
+    pub fn send_fallback(&self) -> SendFallback {
+        self.fallback
+    }
+
+    /// routes returns the bound servers with their interface addresses.
+    fn routes(&self) -> (Vec<&UnicastServer>, Vec<Route>) {
+        let mut udp_servers = Vec::new();
+        let mut routes = Vec::new();
         for udp_server in self.udp_servers.iter() {
-            if udp_server.send(to_addr, msg).is_err() {
-                return true;
+            if let Ok(ifaddr) = udp_server.ifaddr() {
+                udp_servers.push(udp_server);
+                routes.push(Route {
+                    ifaddr,
+                    network: udp_server.network(),
+                    if_index: udp_server.interface_index(),
+                });
             }
         }
-        false
+        (udp_servers, routes)
     }
 
-    pub fn is_running(&self) -> bool {
-        if self.udp_servers.len() == 0 {
-            return false;
+    /// send sends the packet from the server whose interface subnet reaches the destination,
+    /// or as specified by the send fallback, and returns the address of the interface which sent it.
+    pub fn send(&self, to_addr: SocketAddr, msg: &Packet) -> Result<SocketAddr> {
+        let (udp_servers, routes) = self.routes();
+        let n = match find_route(&routes, &to_addr) {
+            Some(n) => n,
+            None => match self.fallback {
+                SendFallback::FirstInterface => {
+                    match routes
+                        .iter()
+                        .position(|route| is_same_family(&route.ifaddr, &to_addr))
+                    {
+                        Some(n) => n,
+                        None => return Err(Error::NoRoute { to: to_addr }),
+                    }
+                }
+                SendFallback::AllInterfaces => {
+                    let mut first_err = None;
+                    for (ifaddr, result) in self.send_all(to_addr, msg).into_results() {
+                        match result {
+                            Ok(_) => return Ok(ifaddr),
+                            Err(e) => {
+                                first_err.get_or_insert(e);
+                            }
+                        }
+                    }
+                    return Err(first_err.unwrap_or(Error::NoRoute { to: to_addr }));
+                }
+                SendFallback::Fail => return Err(Error::NoRoute { to: to_addr }),
+            },
+        };
+        udp_servers[n].send(to_addr, msg)?;
+        Ok(routes[n].ifaddr)
+    }
+
+    /// send_all sends the packet from all servers of the same address family as the destination, and returns the outcome for each interface.
+    pub fn send_all(&self, to_addr: SocketAddr, msg: &Packet) -> SendReport {
+        let mut report = SendReport::new();
+        for udp_server in self.udp_servers.iter() {
+            let ifaddr = match udp_server.ifaddr() {
+                Ok(ifaddr) if is_same_family(&ifaddr, &to_addr) => ifaddr,
+                _ => continue,
+            };
+            report.add(ifaddr, udp_server.send(to_addr, msg));
+        }
+        report
+    }
+
+    /// reply sends the bytes back to the sender of the received packet, from the server which the packet was sent to,
+    /// or from a server on the interface which the packet came in on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        let is_same_family = |udp_server: &&UnicastServer| matches!(udp_server.ifaddr(), Ok(ifaddr) if ifaddr.is_ipv4() == msg.from().is_ipv4());
+        let udp_server = self
+            .udp_servers
+            .iter()
+            .find(|udp_server| matches!(udp_server.ifaddr(), Ok(ifaddr) if ifaddr.ip() == msg.to().ip()))
+            .or_else(|| {
+                self.udp_servers.iter().filter(is_same_family).find(|udp_server| {
+                    udp_server.interface_index() == msg.interface_index()
+                })
+            });
+        match udp_server {
+            Some(udp_server) => udp_server.reply(msg, bytes),
+            None => Err(Error::NoRoute { to: msg.from() }),
         }
-        true
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.port.is_some()
+    }
+
+    /// ifaddrs returns the addresses of the bound servers.
+    pub fn ifaddrs(&self) -> Vec<SocketAddr> {
+        self.udp_servers
+            .iter()
+            .filter_map(|server| server.ifaddr().ok())
+            .collect()
     }
 
     pub fn has_interface(&self, addr: IpAddr) -> bool {
@@ -68,40 +208,121 @@ impl UnicastManager {
         false
     }
 
+    fn start_server(&self, ifaddr: IpAddr, port: u16) -> Result<UnicastServer> {
+        let mut udp_server = UnicastServer::new();
+        udp_server.share_dispatch_options(&self.observers);
+        udp_server.set_socket_options(self.socket_options.clone());
+        for (id, observer) in self.observers.entries() {
+            udp_server.insert_observer(*id, observer.clone());
+        }
+        udp_server.bind(ifaddr, port)?;
+        udp_server.start()?;
+        Ok(udp_server)
+    }
+
     pub fn start(&mut self, port: u16) -> Result<()> {
+        self.start_with_interfaces(port, &self.filter.addresses(&get_interfaces()))
+    }
+
+    /// start_with_interfaces binds a server to each of the specified interfaces.
+    pub fn start_with_interfaces(&mut self, port: u16, ifaddrs: &[IpAddr]) -> Result<()> {
         if self.is_running() {
             return Ok(());
         }
 
-        for ifaddr in get_all_interfaces() {
-            let mut udp_server = UnicastServer::new();
-            let ret = udp_server.bind(ifaddr, port);
-            if ret.is_err() {
-                let _ = self.stop();
-                return ret;
-            }
-            let ret = udp_server.start();
-            if ret.is_err() {
-                let _ = self.stop();
-                return ret;
+        if ifaddrs.is_empty() {
+            return Err(Error::NoInterfaces);
+        }
+
+        self.port = Some(port);
+        for ifaddr in ifaddrs.iter().copied() {
+            match self.start_server(ifaddr, port) {
+                Ok(udp_server) => self.udp_servers.push(udp_server),
+                Err(e) => {
+                    let _ = self.stop();
+                    return Err(e);
+                }
             }
-            self.udp_servers.push(udp_server);
         }
         Ok(())
     }
 
+    /// rebind binds the interfaces which are newly selected by the interface filter, and stops the servers of the vanished interfaces.
+    pub fn rebind(&mut self) -> Result<()> {
+        self.rebind_with_interfaces(&self.filter.addresses(&get_interfaces()))
+    }
+
+    /// rebind_with_interfaces updates the running servers to the specified interfaces.
+    /// It tries all interfaces even if some of them could not be bound, and returns the first error.
+    pub fn rebind_with_interfaces(&mut self, ifaddrs: &[IpAddr]) -> Result<()> {
+        let port = match self.port {
+            Some(port) => port,
+            None => return Ok(()),
+        };
+
+        let mut udp_servers = Vec::new();
+        for mut udp_server in self.udp_servers.drain(..) {
+            match udp_server.ifaddr() {
+                Ok(addr) if ifaddrs.contains(&addr.ip()) => udp_servers.push(udp_server),
+                _ => {
+                    info!("UNBIND {:?}", udp_server.ifaddr());
+                    let _ = udp_server.stop();
+                }
+            }
+        }
+        self.udp_servers = udp_servers;
+
+        let mut ret = Ok(());
+        for ifaddr in ifaddrs.iter().copied() {
+            if self.has_interface(ifaddr) {
+                continue;
+            }
+            match self.start_server(ifaddr, port) {
+                Ok(udp_server) => {
+                    info!("BIND {}:{}", ifaddr, port);
+                    self.udp_servers.push(udp_server);
+                }
+                Err(e) => {
+                    warn!("BIND {}:{} ({})", ifaddr, port, e);
+                    if ret.is_ok() {
+                        ret = Err(e);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
     pub fn stop(&mut self) -> Result<()> {
         for udp_server in self.udp_servers.iter_mut() {
-            let ret = udp_server.stop();
-            if ret.is_err() {
-                return ret;
-            }
+            udp_server.stop()?;
         }
         self.udp_servers.clear();
+        self.port = None;
         Ok(())
     }
 }
 
+impl ObserverRegistry for UnicastManager {
+    /// insert_observer adds the observer to the running servers and the servers which are bound later.
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        let id = self.observers.insert_observer(id, observer.clone());
+        for udp_server in self.udp_servers.iter_mut() {
+            udp_server.insert_observer(id, observer.clone());
+        }
+        id
+    }
+}
+
+impl InterfaceObserver for UnicastManager {
+    /// interface_changed rebinds the servers, so that a manager shared with InterfaceWatcher follows the interface changes.
+    fn interface_changed(&mut self, _event: &InterfaceEvent) {
+        if let Err(e) = self.rebind() {
+            warn!("REBIND ({})", e);
+        }
+    }
+}
+
 impl Drop for UnicastManager {
     fn drop(&mut self) {
         let _ = self.stop();
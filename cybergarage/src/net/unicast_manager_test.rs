@@ -15,7 +15,25 @@
 #[cfg(test)]
 mod tests {
 
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time;
+
+    use crate::log::Logger;
     use crate::net::default_test::*;
+    use crate::net::dispatch_options::DispatchOptions;
+    use crate::net::error::Error;
+    use crate::net::interface::{Interface, InterfaceFilter};
+    use crate::net::interface_observer::{InterfaceEvent, InterfaceObserver};
+    use crate::net::notify_manager_test::*;
+    use crate::net::observer::ObserverObject;
+    use crate::net::observer_registry::ObserverRegistry;
+    use crate::net::packet::Packet;
+    use crate::net::packet_channel::OverflowPolicy;
+    use crate::net::packet_filter::PrefixFilter;
+    use crate::net::route::SendFallback;
+    use crate::net::udp_socket_options::UdpSocketOptions;
     use crate::net::unicast_manager::*;
 
     #[test]
@@ -24,4 +42,341 @@ mod tests {
         assert!(mgr.start(TEST_PORT).is_ok());
         assert!(mgr.stop().is_ok());
     }
+
+    #[test]
+    fn unicast_manager_with_interfaces() {
+        fn test_unicast_manager(ifaddrs: &[IpAddr]) {
+            let packets = Arc::new(Mutex::new(Vec::new()));
+
+            let mut mgr = UnicastManager::new();
+            let ret = mgr.start_with_interfaces(TEST_PORT, ifaddrs);
+            assert!(ret.is_ok(), "{}", ret.err().unwrap());
+            let observer = TestPacketRecorder::new(packets.clone());
+            mgr.add_observer(Arc::new(Mutex::new(observer)));
+
+            let mut pkt = Packet::new();
+            pkt.set_bytes(vec![0_u8; 1]);
+            for ifaddr in ifaddrs {
+                assert!(mgr.has_interface(*ifaddr));
+                let to_addr = SocketAddr::new(*ifaddr, TEST_PORT);
+                assert!(matches!(mgr.send(to_addr, &pkt), Ok(from) if from == to_addr));
+            }
+            thread::sleep(time::Duration::from_millis(500));
+            assert!(mgr.stop().is_ok());
+
+            let packets = packets.lock().unwrap();
+            for ifaddr in ifaddrs {
+                assert!(packets.iter().any(|pkt| pkt.to().ip() == *ifaddr));
+            }
+        }
+
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        test_unicast_manager(&[v4_ifaddr]);
+        test_unicast_manager(&[v6_ifaddr]);
+        test_unicast_manager(&[v4_ifaddr, v6_ifaddr]);
+
+        let mut mgr = UnicastManager::new();
+        assert!(mgr.start_with_interfaces(TEST_PORT, &[]).is_err());
+    }
+
+    #[test]
+    fn unicast_manager_with_filter() {
+        Logger::init();
+
+        let mut mgr = UnicastManager::new();
+        mgr.set_interface_filter(InterfaceFilter::new().include_name("lo").loopback(true));
+        assert!(mgr.start(TEST_PORT).is_ok());
+        assert!(mgr.has_interface(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert!(!mgr.has_interface(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))));
+        assert!(mgr.stop().is_ok());
+
+        mgr.set_interface_filter(InterfaceFilter::new().include_name("none*"));
+        assert!(mgr.start(TEST_PORT).is_err());
+    }
+
+    #[test]
+    fn unicast_manager_rebind() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = UnicastManager::new();
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+        assert!(mgr.rebind_with_interfaces(&[v4_ifaddr]).is_ok());
+        assert!(!mgr.has_interface(v4_ifaddr));
+
+        assert!(mgr.start_with_interfaces(TEST_PORT, &[v4_ifaddr]).is_ok());
+        assert!(mgr.rebind_with_interfaces(&[v6_ifaddr]).is_ok());
+        assert!(!mgr.has_interface(v4_ifaddr));
+        assert!(mgr.has_interface(v6_ifaddr));
+
+        // The server bound by rebind notifies the observers added before.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        assert!(mgr
+            .send(SocketAddr::new(v6_ifaddr, TEST_PORT), &pkt)
+            .is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(packets
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|pkt| pkt.to().ip() == v6_ifaddr));
+
+        assert!(mgr.rebind_with_interfaces(&[]).is_ok());
+        assert!(!mgr.has_interface(v6_ifaddr));
+        assert!(mgr.is_running());
+
+        mgr.set_interface_filter(InterfaceFilter::new().include_name("lo").loopback(true));
+        mgr.interface_changed(&InterfaceEvent::Added(Interface::new("lo", 1)));
+        assert!(mgr.has_interface(v4_ifaddr));
+
+        assert!(mgr.stop().is_ok());
+        assert!(!mgr.is_running());
+    }
+
+    #[test]
+    fn unicast_manager_send() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v4_alt_ifaddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = UnicastManager::new();
+        let observer = TestPacketRecorder::new(packets.clone());
+        mgr.add_observer(Arc::new(Mutex::new(observer)));
+        let ret = mgr.start_with_interfaces(TEST_PORT, &[v4_alt_ifaddr, v4_ifaddr, v6_ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+
+        // A local destination is sent from the server bound to it, and the other destinations by the interface subnet.
+        let to_addr = SocketAddr::new(v4_ifaddr, TEST_PORT);
+        assert!(matches!(mgr.send(to_addr, &pkt), Ok(from) if from == to_addr));
+        let to_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3)), TEST_PORT);
+        assert!(matches!(mgr.send(to_addr, &pkt), Ok(from) if from.ip() == v4_ifaddr));
+        let to_addr = SocketAddr::new(v6_ifaddr, TEST_PORT);
+        assert!(matches!(mgr.send(to_addr, &pkt), Ok(from) if from == to_addr));
+
+        // send_all sends from each server of the same address family.
+        let to_addr = SocketAddr::new(v4_ifaddr, TEST_PORT);
+        let report = mgr.send_all(to_addr, &pkt);
+        assert!(report.is_ok());
+        assert_eq!(report.len(), 2);
+        assert!(report
+            .successes()
+            .contains(&SocketAddr::new(v4_alt_ifaddr, TEST_PORT)));
+        assert!(report.successes().contains(&to_addr));
+        assert!(report.failures().is_empty());
+
+        // A destination out of the interface subnets is sent only as specified by the fallback.
+        let to_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)), TEST_PORT);
+        assert_eq!(mgr.send_fallback(), SendFallback::FirstInterface);
+        mgr.set_send_fallback(SendFallback::Fail);
+        assert!(matches!(
+            mgr.send(to_addr, &pkt),
+            Err(Error::NoRoute { .. })
+        ));
+
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        let packets = packets.lock().unwrap();
+        assert!(packets.iter().any(|pkt| pkt.from().ip() == v4_alt_ifaddr));
+        assert!(packets.iter().any(|pkt| pkt.to().ip() == v6_ifaddr));
+    }
+
+    #[test]
+    fn unicast_manager_dispatch_pool() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let workers = Arc::new(Mutex::new(Vec::new()));
+
+        // The servers share the single worker thread of the manager.
+        let mut mgr = UnicastManager::new();
+        mgr.set_dispatch_options(DispatchOptions::pool(1));
+        let recorder = workers.clone();
+        mgr.add_observer_fn(move |pkt| {
+            if pkt.bytes() == &[0x23] {
+                recorder
+                    .lock()
+                    .unwrap()
+                    .push((pkt.to().ip(), thread::current().id()));
+            }
+        });
+        let ret = mgr.start_with_interfaces(TEST_PORT, &[v4_ifaddr, v6_ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0x23]);
+        for ifaddr in [v4_ifaddr, v6_ifaddr] {
+            assert!(mgr.send(SocketAddr::new(ifaddr, TEST_PORT), &pkt).is_ok());
+        }
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        let workers = workers.lock().unwrap();
+        assert!(workers.iter().any(|(ifaddr, _)| *ifaddr == v4_ifaddr));
+        assert!(workers.iter().any(|(ifaddr, _)| *ifaddr == v6_ifaddr));
+        assert!(workers.iter().all(|(_, id)| *id == workers[0].1));
+    }
+
+    #[test]
+    fn unicast_manager_socket_options() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let mut mgr = UnicastManager::new();
+        mgr.set_socket_options(UdpSocketOptions::new().bind_to_device("none0"));
+        assert!(mgr.start_with_interfaces(TEST_PORT, &[v4_ifaddr]).is_err());
+        assert!(!mgr.is_running());
+
+        mgr.set_socket_options(UdpSocketOptions::new().bind_to_device("lo").dscp(46));
+        assert_eq!(
+            mgr.socket_options(),
+            &UdpSocketOptions::new().bind_to_device("lo").dscp(46)
+        );
+        assert!(mgr.start_with_interfaces(TEST_PORT, &[v4_ifaddr]).is_ok());
+        assert!(mgr.has_interface(v4_ifaddr));
+        assert!(mgr.stop().is_ok());
+    }
+
+    #[test]
+    fn unicast_manager_remove_observer() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+        let other_packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = UnicastManager::new();
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(packets.clone())));
+        let id = mgr.add_observer(observer.clone());
+        assert!(mgr.start_with_interfaces(0, &[ifaddr]).is_ok());
+        assert_eq!(mgr.add_observer(observer), id);
+        mgr.add_observer(Arc::new(Mutex::new(TestPacketRecorder::new(
+            other_packets.clone(),
+        ))));
+
+        // The observer added before start() is removed from the servers bound by start().
+        assert!(mgr.remove_observer(id));
+        assert!(!mgr.remove_observer(id));
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        assert!(mgr.send(mgr.ifaddrs()[0], &pkt).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        assert!(packets.lock().unwrap().is_empty());
+        assert_eq!(other_packets.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unicast_manager_packet_filter() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = UnicastManager::new();
+        mgr.add_observer_with_filter(
+            Arc::new(Mutex::new(TestPacketRecorder::new(packets.clone()))),
+            Arc::new(PrefixFilter::new(&[0x10, 0x81])),
+        );
+        assert!(mgr.start_with_interfaces(0, &[ifaddr]).is_ok());
+
+        for bytes in [vec![0x10, 0x81, 0x00], vec![0x10, 0x82, 0x00]] {
+            let mut pkt = Packet::new();
+            pkt.set_bytes(bytes);
+            assert!(mgr.send(mgr.ifaddrs()[0], &pkt).is_ok());
+        }
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        let packets = packets.lock().unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].bytes(), &vec![0x10, 0x81, 0x00]);
+    }
+
+    #[test]
+    fn unicast_manager_observer_order() {
+        Logger::init();
+
+        let v4_ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6_ifaddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let before_packets = Arc::new(Mutex::new(Vec::new()));
+        let after_packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut mgr = UnicastManager::new();
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(before_packets.clone())));
+        mgr.add_observer(observer);
+        assert!(mgr.start_with_interfaces(0, &[v4_ifaddr]).is_ok());
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(after_packets.clone())));
+        mgr.add_observer(observer);
+
+        // Both observers are attached to the server bound by start() and the server bound by rebind.
+        let ret = mgr.rebind_with_interfaces(&[v4_ifaddr, v6_ifaddr]);
+        assert!(ret.is_ok(), "{}", ret.err().unwrap());
+        let ifaddrs = mgr.ifaddrs();
+        assert_eq!(ifaddrs.len(), 2);
+
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        for ifaddr in ifaddrs {
+            assert!(mgr.send(ifaddr, &pkt).is_ok());
+        }
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(mgr.stop().is_ok());
+
+        assert_eq!(before_packets.lock().unwrap().len(), 2);
+        assert_eq!(after_packets.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn unicast_manager_stop_blocked_receiver() {
+        Logger::init();
+
+        let ifaddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let mut mgr = UnicastManager::new();
+        assert!(mgr.start_with_interfaces(0, &[ifaddr]).is_ok());
+        let receiver = mgr.subscribe_bounded(1, OverflowPolicy::Block);
+
+        // The receive thread blocks on the full receiver which nobody reads.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        for _ in 0..3 {
+            assert!(mgr.send(mgr.ifaddrs()[0], &pkt).is_ok());
+        }
+        thread::sleep(time::Duration::from_millis(500));
+        assert_eq!(receiver.len(), 1);
+
+        // stop() releases the blocked receive thread instead of waiting for the receiver.
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(mgr.stop().is_ok());
+        });
+        assert_eq!(rx.recv_timeout(time::Duration::from_secs(5)), Ok(true));
+        assert_eq!(receiver.len(), 1);
+        assert!(0 < receiver.dropped());
+
+        // The queued packet is received after the manager has been dropped, and then recv() returns None.
+        assert!(receiver.recv().is_some());
+        assert!(receiver.recv().is_none());
+    }
 }
@@ -15,21 +15,35 @@
 #![allow(dead_code)]
 
 use log::*;
-use std::io;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, RwLock};
-use std::thread;
 
-use crate::net::default::*;
+use crate::net::cidr::Cidr;
+use crate::net::dispatch_options::DispatchOptions;
+use crate::net::error::Result;
+use crate::net::interface::{find_interface_index, find_interface_network, get_interfaces};
 use crate::net::notifier::*;
-use crate::net::observer::ObserverObject;
+use crate::net::notify_manager::NotifytManager;
+use crate::net::observer::{ObserverEntry, ObserverId};
+use crate::net::observer_registry::ObserverRegistry;
 use crate::net::packet::Packet;
-use crate::net::result::Result;
+use crate::net::udp_receiver::UdpReceiver;
 use crate::net::udp_socket::UdpSocket;
+use crate::net::udp_socket_options::UdpSocketOptions;
 
 pub struct UnicastServer {
     socket: Arc<RwLock<UdpSocket>>,
     notifier: Notifier,
+    receiver: UdpReceiver,
+    if_index: u32,
+    network: Option<Cidr>,
+    socket_options: UdpSocketOptions,
+}
+
+impl Default for UnicastServer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UnicastServer {
@@ -37,11 +51,32 @@ impl UnicastServer {
         UnicastServer {
             socket: Arc::new(RwLock::new(UdpSocket::new())),
             notifier: notifier_new(),
+            receiver: UdpReceiver::new(),
+            if_index: 0,
+            network: None,
+            socket_options: UdpSocketOptions::new(),
         }
     }
 
-    pub fn add_observer(&mut self, observer: ObserverObject) -> bool {
-        self.notifier.lock().unwrap().add_observer(observer)
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        self.notifier.lock().unwrap().remove_observer(id)
+    }
+
+    /// set_dispatch_options sets how the received packets are dispatched to the observers.
+    pub fn set_dispatch_options(&mut self, options: DispatchOptions) {
+        self.notifier.lock().unwrap().set_dispatch_options(options);
+    }
+
+    pub fn dispatch_options(&self) -> DispatchOptions {
+        *self.notifier.lock().unwrap().dispatch_options()
+    }
+
+    /// share_dispatch_options sets the dispatch options of the manager, and shares its worker threads instead of starting new ones.
+    pub(crate) fn share_dispatch_options(&mut self, manager: &NotifytManager) {
+        self.notifier
+            .lock()
+            .unwrap()
+            .share_dispatch_options(manager);
     }
 
     pub fn send(&self, to_addr: SocketAddr, msg: &Packet) -> Result<usize> {
@@ -50,84 +85,115 @@ impl UnicastServer {
         let port = to_addr.port();
         info!(
             "SEND {} -> {}:{} ({})",
-            self.socket.read().unwrap().addr().unwrap(),
+            self.socket.read().unwrap().addr()?,
             addr,
             port,
             msg,
         );
-        let ret = self.socket.read().unwrap().send_to(&msg_bytes, to_addr);
+        let ret = self.socket.read().unwrap().send_to(msg_bytes, to_addr);
         if ret.is_err() {
             warn!("Couldn't send Packet to {} {}", addr, port);
         }
         ret
     }
 
-    pub fn ifaddr(&self) -> io::Result<SocketAddr> {
+    /// set_socket_options sets the socket options, which are applied when the socket is bound.
+    pub fn set_socket_options(&mut self, options: UdpSocketOptions) {
+        self.socket_options = options;
+    }
+
+    pub fn socket_options(&self) -> &UdpSocketOptions {
+        &self.socket_options
+    }
+
+    /// set_recv_buffer_size sets the size of the buffer which received datagrams are read into. Datagrams larger than it are notified as truncated Packets.
+    pub fn set_recv_buffer_size(&mut self, size: usize) {
+        self.receiver.set_buffer_size(size);
+    }
+
+    pub fn recv_buffer_size(&self) -> usize {
+        self.receiver.buffer_size()
+    }
+
+    /// set_socket_recv_buffer_size sets SO_RCVBUF of the bound socket.
+    pub fn set_socket_recv_buffer_size(&self, size: usize) -> Result<()> {
+        self.socket.read().unwrap().set_recv_buffer_size(size)
+    }
+
+    /// set_socket_send_buffer_size sets SO_SNDBUF of the bound socket.
+    pub fn set_socket_send_buffer_size(&self, size: usize) -> Result<()> {
+        self.socket.read().unwrap().set_send_buffer_size(size)
+    }
+
+    /// reply sends the bytes back to the sender of the received packet, out of the interface the packet came in on.
+    pub fn reply(&self, msg: &Packet, bytes: &[u8]) -> Result<usize> {
+        let to_addr = msg.from();
+        info!(
+            "SEND {} -> {} ({})",
+            self.socket.read().unwrap().addr()?,
+            to_addr,
+            hex::encode_upper(bytes),
+        );
+        let ret =
+            self.socket
+                .read()
+                .unwrap()
+                .send_to_interface(bytes, to_addr, msg.interface_index());
+        if ret.is_err() {
+            warn!("Couldn't send Packet to {}", to_addr);
+        }
+        ret
+    }
+
+    /// interface_index returns the index of the bound interface, or 0 if it is unknown.
+    pub fn interface_index(&self) -> u32 {
+        self.if_index
+    }
+
+    /// network returns the address and the prefix length of the bound interface, or None if it is unknown.
+    pub fn network(&self) -> Option<Cidr> {
+        self.network
+    }
+
+    pub fn ifaddr(&self) -> Result<SocketAddr> {
         self.socket.read().unwrap().addr()
     }
 
     pub fn bind(&mut self, ifaddr: IpAddr, port: u16) -> Result<()> {
-        let addr = format!("{}:{}", ifaddr, port).parse();
-        if addr.is_err() {
-            error!("bind {} {}", ifaddr, port);
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("{}:{}", ifaddr, port),
-            ));
-        }
-        let addr: SocketAddr = addr.unwrap();
+        let addr = SocketAddr::new(ifaddr, port);
         debug!("BIND UDP {}", addr);
-        let ret = self.socket.write().unwrap().bind(addr);
-        if ret.is_err() {
-            return ret;
-        }
+        self.socket
+            .write()
+            .unwrap()
+            .bind_with_options(addr, &self.socket_options)?;
+        let ifaces = get_interfaces();
+        self.if_index = find_interface_index(&ifaces, ifaddr).unwrap_or(0);
+        self.network = find_interface_network(&ifaces, ifaddr);
         Ok(())
     }
 
     pub fn close(&self) -> Result<()> {
-        self.socket.read().unwrap().close();
+        self.socket.write().unwrap().close();
         Ok(())
     }
 
     pub fn start(&mut self) -> Result<()> {
-        let socket = self.socket.clone();
-        let notifier = self.notifier.clone();
-        thread::spawn(move || {
-            let mut buf = [0 as u8; MAX_PACKET_SIZE];
-            loop {
-                let recv_res = socket.read().unwrap().recv_from(&mut buf);
-                match &recv_res {
-                    Ok((n_bytes, remote_addr)) => {
-                        let recv_msg = &buf[0..*n_bytes];
-                        let mut msg = Packet::from_bytes(&recv_msg.to_vec());
-                        info!(
-                            "RECV {} -> {} ({})",
-                            remote_addr.ip(),
-                            socket.read().unwrap().addr().ok().unwrap(),
-                            msg
-                        );
-                        msg.set_from(remote_addr.clone());
-                        notifier.lock().unwrap().notify(&msg);
-                    }
-                    Err(e) => {
-                        warn!(
-                            "RECV {} ({})",
-                            socket.read().unwrap().addr().ok().unwrap(),
-                            e
-                        );
-                        break;
-                    }
-                }
-            }
-        });
-        Ok(())
+        self.receiver
+            .start(self.socket.clone(), self.notifier.clone())
     }
 
-    pub fn stop(&self) -> Result<()> {
+    pub fn stop(&mut self) -> Result<()> {
+        self.receiver.stop();
         self.close()
     }
 }
 
+impl ObserverRegistry for UnicastServer {
+    fn insert_observer(&mut self, id: ObserverId, observer: ObserverEntry) -> ObserverId {
+        self.notifier.lock().unwrap().insert_observer(id, observer)
+    }
+}
+
 impl Drop for UnicastServer {
     fn drop(&mut self) {
         let _ = self.stop();
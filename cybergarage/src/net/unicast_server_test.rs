@@ -14,15 +14,19 @@
 
 #[cfg(test)]
 mod tests {
-    use std::net::IpAddr;
+    use std::net::{IpAddr, Ipv4Addr};
     use std::sync::Arc;
     use std::sync::Mutex;
     use std::thread;
     use std::time;
 
+    use crate::net::default::*;
     use crate::net::default_test::*;
     use crate::net::interface::*;
+    use crate::net::observer::ObserverObject;
+    use crate::net::observer_registry::ObserverRegistry;
     use crate::net::packet::Packet;
+    use crate::net::packet_channel::OverflowPolicy;
     use crate::net::unicast_server::*;
 
     use crate::log::Logger;
@@ -39,7 +43,7 @@ mod tests {
             let mut server = UnicastServer::new();
 
             let observer = TestNotifyCounter::new(counter.clone());
-            assert!(server.add_observer(Arc::new(Mutex::new(observer))));
+            server.add_observer(Arc::new(Mutex::new(observer)));
 
             let ret = server.bind(ifaddr, TEST_PORT);
             assert!(ret.is_ok(), "{:?}", ret);
@@ -47,7 +51,7 @@ mod tests {
             thread::sleep(time::Duration::from_secs(5));
 
             let mut pkt = Packet::new();
-            pkt.set_bytes(vec![0 as u8; 1]);
+            pkt.set_bytes(vec![0_u8; 1]);
             for _ in 0..TEST_OBSERVER_COUNT {
                 let server_addr = server.ifaddr();
                 assert!(server_addr.is_ok());
@@ -59,8 +63,8 @@ mod tests {
             let counter = counter.lock();
             // NOTE: GitHub Action is slow and may drop to send UDP packets.
             // assert_eq!(*counter.unwrap(), TEST_OBSERVER_COUNT);
-            if counter.is_ok() {
-                assert!(0 < *counter.unwrap());
+            if let Ok(counter) = counter {
+                assert!(0 < *counter);
             }
 
             assert!(server.stop().is_ok());
@@ -70,4 +74,159 @@ mod tests {
             test_udp_server(ifaddr);
         }
     }
+
+    #[test]
+    fn unicast_server_stop() {
+        Logger::init();
+
+        const TEST_RESTART_COUNT: i32 = 5;
+        for _ in 0..TEST_RESTART_COUNT {
+            let mut server = UnicastServer::new();
+            let ret = server.bind(IpAddr::V4(Ipv4Addr::LOCALHOST), TEST_PORT);
+            assert!(ret.is_ok(), "{:?}", ret);
+            assert!(server.start().is_ok());
+            let now = time::Instant::now();
+            assert!(server.stop().is_ok());
+            assert!(now.elapsed() < time::Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn unicast_server_recv_buffer_size() {
+        fn test_recv_buffer_size(recv_buffer_size: usize, pkt_size: usize) -> Packet {
+            let packets = Arc::new(Mutex::new(Vec::new()));
+
+            let mut server = UnicastServer::new();
+            let observer = TestPacketRecorder::new(packets.clone());
+            server.add_observer(Arc::new(Mutex::new(observer)));
+            server.set_recv_buffer_size(recv_buffer_size);
+            assert_eq!(server.recv_buffer_size(), recv_buffer_size);
+
+            let ret = server.bind(IpAddr::V4(Ipv4Addr::LOCALHOST), TEST_PORT);
+            assert!(ret.is_ok(), "{:?}", ret);
+            assert!(server.set_socket_recv_buffer_size(MAX_PACKET_SIZE).is_ok());
+            assert!(server.start().is_ok());
+
+            let mut pkt = Packet::new();
+            pkt.set_bytes(vec![1_u8; pkt_size]);
+            let ret = server.send(server.ifaddr().unwrap(), &pkt);
+            assert!(ret.is_ok(), "{:?}", ret);
+            thread::sleep(time::Duration::from_millis(500));
+
+            assert!(server.stop().is_ok());
+            let mut packets = packets.lock().unwrap();
+            assert_eq!(packets.len(), 1);
+            packets.remove(0)
+        }
+
+        Logger::init();
+
+        const TEST_PACKET_SIZE: usize = 8192;
+        let pkt = test_recv_buffer_size(MAX_PACKET_SIZE, TEST_PACKET_SIZE);
+        assert_eq!(pkt.bytes().len(), TEST_PACKET_SIZE);
+        assert!(!pkt.is_truncated());
+
+        const TEST_BUFFER_SIZE: usize = 1024;
+        let pkt = test_recv_buffer_size(TEST_BUFFER_SIZE, TEST_PACKET_SIZE);
+        assert_eq!(pkt.bytes().len(), TEST_BUFFER_SIZE);
+        assert!(pkt.is_truncated());
+    }
+
+    #[test]
+    fn unicast_server_remove_observer() {
+        Logger::init();
+
+        let removed_packets = Arc::new(Mutex::new(Vec::new()));
+        let weak_packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut server = UnicastServer::new();
+        let observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(removed_packets.clone())));
+        let id = server.add_observer(observer.clone());
+        assert_eq!(server.add_observer(observer), id);
+        let weak_observer: ObserverObject =
+            Arc::new(Mutex::new(TestPacketRecorder::new(weak_packets.clone())));
+        server.add_weak_observer(Arc::downgrade(&weak_observer));
+
+        let ret = server.bind(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        assert!(ret.is_ok(), "{:?}", ret);
+        assert!(server.start().is_ok());
+
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        assert!(server.remove_observer(id));
+        assert!(!server.remove_observer(id));
+        assert!(server.send(server.ifaddr().unwrap(), &pkt).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert!(removed_packets.lock().unwrap().is_empty());
+        assert_eq!(weak_packets.lock().unwrap().len(), 1);
+
+        // The weak observer is not notified once the owner drops it.
+        drop(weak_observer);
+        assert!(server.send(server.ifaddr().unwrap(), &pkt).is_ok());
+        thread::sleep(time::Duration::from_millis(500));
+        assert_eq!(weak_packets.lock().unwrap().len(), 1);
+
+        assert!(server.stop().is_ok());
+    }
+
+    #[test]
+    fn unicast_server_panic_observer() {
+        Logger::init();
+
+        let packets = Arc::new(Mutex::new(Vec::new()));
+
+        let mut server = UnicastServer::new();
+        server.add_observer(Arc::new(Mutex::new(TestPanicObserver {})));
+        server.add_observer(Arc::new(Mutex::new(TestPacketRecorder::new(
+            packets.clone(),
+        ))));
+
+        let ret = server.bind(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        assert!(ret.is_ok(), "{:?}", ret);
+        assert!(server.start().is_ok());
+
+        // The receive loop keeps notifying after an observer has panicked.
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        for _ in 0..2 {
+            assert!(server.send(server.ifaddr().unwrap(), &pkt).is_ok());
+            thread::sleep(time::Duration::from_millis(500));
+        }
+        assert_eq!(packets.lock().unwrap().len(), 2);
+
+        assert!(server.stop().is_ok());
+    }
+
+    #[test]
+    fn unicast_server_subscribe() {
+        Logger::init();
+
+        let counter = Arc::new(Mutex::new(0));
+
+        let mut server = UnicastServer::new();
+        let fn_counter = counter.clone();
+        server.add_observer_fn(move |_| *fn_counter.lock().unwrap() += 1);
+        let rx = server.subscribe();
+        let receiver = server.subscribe_bounded(1, OverflowPolicy::DropOldest);
+
+        let ret = server.bind(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        assert!(ret.is_ok(), "{:?}", ret);
+        assert!(server.start().is_ok());
+
+        let mut pkt = Packet::new();
+        pkt.set_bytes(vec![0_u8; 1]);
+        assert!(server.send(server.ifaddr().unwrap(), &pkt).is_ok());
+
+        // The packets are received on another thread.
+        let consumer = thread::spawn(move || rx.recv_timeout(time::Duration::from_secs(5)));
+        let recv_pkt = consumer.join().unwrap();
+        assert!(recv_pkt.is_ok());
+        assert_eq!(recv_pkt.unwrap().bytes(), pkt.bytes());
+        let recv_pkt = receiver.recv_timeout(time::Duration::from_secs(5));
+        assert!(recv_pkt.is_some());
+        assert_eq!(*counter.lock().unwrap(), 1);
+
+        assert!(server.stop().is_ok());
+    }
 }